@@ -0,0 +1,440 @@
+//! Local HTTP/JSON API to control the emulator and query its state, enabled by `http_address`.
+//!
+//! | method | path                        | body                              |
+//! |--------|-----------------------------|-----------------------------------|
+//! | GET    | /state                      |                                   |
+//! | GET    | /events                     | server-sent `state` events        |
+//! | GET    | /models                     |                                   |
+//! | PUT    | /mode                       | `{"mode": "free_space"}`          |
+//! | PUT    | /manual_attenuation         | `{"manual_attenuation": 40}`      |
+//! | PUT    | /magic_scaling_coeff        | `{"magic_scaling_coeff": 30000}`  |
+//! | PUT    | /models/{name}/parameters   | `{"parameter": value, ...}`       |
+//!
+//! Commands answer `{"ok": true}` once they are queued (parameters once they are applied) or
+//! `{"ok": false, "error": "..."}` with an error status.
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, put};
+use axum::{Json, Router};
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::io;
+use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::{oneshot, watch};
+use tokio_stream::wrappers::WatchStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::config::MANUAL_MODE;
+use crate::{mode_event, mode_index, Ev, MODEL_INDEX_MANUAL};
+use channel_models::{ChannelModel, ChannelStatistics, PropagationPath, MAX_TAPS};
+
+/// last position update, local frame
+#[derive(Clone, Debug, Serialize)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    /// given by the update or estimated from successive ones
+    pub velocity: [f32; 3],
+    /// seconds since the Unix epoch
+    pub received_at: f64,
+}
+
+impl Position {
+    pub fn new(state: &channel_models::State) -> Self {
+        Position {
+            x: state.x,
+            y: state.y,
+            z: state.z,
+            roll: state.roll,
+            pitch: state.pitch,
+            yaw: state.yaw,
+            velocity: state.velocity,
+            received_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0., |elapsed| elapsed.as_secs_f64()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PathSummary {
+    pub kind: &'static str,
+    /// linear amplitude loss
+    pub loss: f32,
+    /// seconds
+    pub delay: f32,
+    /// radians
+    pub phase: f32,
+    /// Hz
+    pub doppler: f32,
+}
+
+impl From<&PropagationPath> for PathSummary {
+    fn from(path: &PropagationPath) -> Self {
+        PathSummary { kind: path.kind.label(), loss: path.loss, delay: path.delay, phase: path.phase, doppler: path.doppler }
+    }
+}
+
+/// statistics of the channel at the last position update, non-finite values are null
+#[derive(Clone, Debug, Serialize)]
+pub struct Statistics {
+    pub power: f32,
+    pub mean_delay: f32,
+    pub rms_delay_spread: f32,
+    /// dB
+    pub k_factor: f32,
+    /// Hz
+    pub coherence_bandwidth: f32,
+}
+
+impl From<&ChannelStatistics> for Statistics {
+    fn from(statistics: &ChannelStatistics) -> Self {
+        Statistics {
+            power: statistics.power,
+            mean_delay: statistics.mean_delay,
+            rms_delay_spread: statistics.rms_delay_spread,
+            k_factor: statistics.k_factor,
+            coherence_bandwidth: statistics.coherence_bandwidth,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Model {
+    pub name: &'static str,
+    pub parameters: BTreeMap<&'static str, f64>,
+}
+
+/// name and parameter values of all models, in mode index order
+pub fn models(models: &[Box<dyn ChannelModel>]) -> Vec<Model> {
+    models.iter().map(|model| Model { name: model.name(), parameters: model.parameters().into_iter().collect() }).collect()
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Counters {
+    pub position_updates: u64,
+    /// taps sent to the FPGA
+    pub tap_updates: u64,
+    pub tap_send_errors: u64,
+    /// configurations reloaded on SIGHUP
+    pub reloads: u64,
+}
+
+/// State of the emulator as published by its main loop after every event.
+#[derive(Clone, Debug, Serialize)]
+pub struct Snapshot {
+    /// name of the selected model or "manual"
+    pub mode: &'static str,
+    /// mode index as used by the GUI and the gamepad
+    pub mode_index: usize,
+    pub manual_attenuation: f32,
+    pub magic_scaling_coeff: f32,
+    pub position: Option<Position>,
+    /// paths of the model at the last position update, before fading and phase rotation
+    pub paths: Vec<PathSummary>,
+    pub statistics: Option<Statistics>,
+    /// last taps sent to the FPGA, real and imaginary parts
+    pub taps: [Vec<i16>; 2],
+    pub models: Vec<Model>,
+    pub counters: Counters,
+}
+
+impl Snapshot {
+    pub fn new(models: Vec<Model>, manual_attenuation: f32, magic_scaling_coeff: f32) -> Self {
+        Snapshot {
+            mode: MANUAL_MODE,
+            mode_index: MODEL_INDEX_MANUAL,
+            manual_attenuation,
+            magic_scaling_coeff,
+            position: None,
+            paths: Vec::new(),
+            statistics: None,
+            taps: [vec![0; MAX_TAPS], vec![0; MAX_TAPS]],
+            models,
+            counters: Counters::default(),
+        }
+    }
+
+    /// mode index and name of the selected model, manual mode if None
+    pub fn set_mode(&mut self, model_index: Option<usize>) {
+        self.mode_index = mode_index(model_index);
+        self.mode = model_index.map_or(MANUAL_MODE, |model_index| self.models[model_index].name);
+    }
+
+    pub fn set_taps(&mut self, taps: &[i16]) {
+        let (re, im) = taps.split_at(MAX_TAPS);
+        self.taps = [re.to_vec(), im.to_vec()];
+    }
+}
+
+/// what the handlers share: the events of the main loop, the GUI and the published state
+#[derive(Clone)]
+pub struct Context {
+    pub tx: UnboundedSender<Ev>,
+    pub to_gui_tx: UnboundedSender<Vec<u8>>,
+    pub snapshot_rx: watch::Receiver<Snapshot>,
+    pub model_names: Vec<&'static str>,
+}
+
+/// `{"ok": false, "error": ...}` with the given status
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "ok": false, "error": self.1 }))).into_response()
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        ApiError(rejection.status(), rejection.body_text())
+    }
+}
+
+type ApiResult = Result<Json<serde_json::Value>, ApiError>;
+
+fn ok() -> ApiResult {
+    Ok(Json(json!({ "ok": true })))
+}
+
+fn bad_request(message: String) -> ApiError {
+    ApiError(StatusCode::BAD_REQUEST, message)
+}
+
+impl Context {
+    fn send(&self, event: Ev) -> Result<(), ApiError> {
+        self.tx.send(event).map_err(|_| ApiError(StatusCode::SERVICE_UNAVAILABLE, "emulator is shutting down".to_string()))
+    }
+
+    /// tells the GUI about a mode change like the gamepad does
+    fn notify_gui(&self, mode_index: usize, manual_attenuation: f32) {
+        let mut send_buf = manual_attenuation.to_be_bytes().to_vec();
+        send_buf.insert(0_usize, mode_index as u8);
+        send_buf.insert(0_usize, b'M');
+        // the GUI sender only stops with the emulator
+        let _ = self.to_gui_tx.send(send_buf);
+    }
+}
+
+async fn get_state(State(context): State<Context>) -> Json<Snapshot> {
+    Json(context.snapshot_rx.borrow().clone())
+}
+
+async fn get_events(State(context): State<Context>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    // the watch channel only keeps the latest state, so slow clients skip intermediate ones
+    let events = WatchStream::new(context.snapshot_rx).map(|snapshot| Ok(Event::default().event("state").json_data(&snapshot).unwrap()));
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+async fn get_models(State(context): State<Context>) -> Json<Vec<Model>> {
+    Json(context.snapshot_rx.borrow().models.clone())
+}
+
+#[derive(Deserialize)]
+struct ModeRequest {
+    mode: String,
+}
+
+async fn put_mode(State(context): State<Context>, request: Result<Json<ModeRequest>, JsonRejection>) -> ApiResult {
+    let Json(request) = request?;
+    let mode_index = match context.model_names.iter().position(|name| *name == request.mode) {
+        Some(model_index) => mode_index(Some(model_index)),
+        None if request.mode == MANUAL_MODE => MODEL_INDEX_MANUAL,
+        None => return Err(bad_request(format!("unknown mode '{}', available: {:?} and \"{}\"", request.mode, context.model_names, MANUAL_MODE))),
+    };
+    info!("API: select mode {}", request.mode);
+    // manual mode keeps its last attenuation
+    context.send(mode_event(mode_index, &context.model_names, None))?;
+    let manual_attenuation = context.snapshot_rx.borrow().manual_attenuation;
+    context.notify_gui(mode_index, manual_attenuation);
+    ok()
+}
+
+#[derive(Deserialize)]
+struct ManualAttenuationRequest {
+    manual_attenuation: f32,
+}
+
+/// selects manual mode with the given attenuation
+async fn put_manual_attenuation(State(context): State<Context>, request: Result<Json<ManualAttenuationRequest>, JsonRejection>) -> ApiResult {
+    let Json(request) = request?;
+    if !(0.0..=120.0).contains(&request.manual_attenuation) {
+        return Err(bad_request(format!("manual_attenuation must be between 0 and 120 dB, got {}", request.manual_attenuation)));
+    }
+    context.send(mode_event(MODEL_INDEX_MANUAL, &context.model_names, Some(request.manual_attenuation)))?;
+    context.notify_gui(MODEL_INDEX_MANUAL, request.manual_attenuation);
+    ok()
+}
+
+#[derive(Deserialize)]
+struct ScalingCoeffRequest {
+    magic_scaling_coeff: f32,
+}
+
+async fn put_magic_scaling_coeff(State(context): State<Context>, request: Result<Json<ScalingCoeffRequest>, JsonRejection>) -> ApiResult {
+    let Json(request) = request?;
+    if !(request.magic_scaling_coeff > 0. && request.magic_scaling_coeff.is_finite()) {
+        return Err(bad_request(format!("magic_scaling_coeff must be positive, got {}", request.magic_scaling_coeff)));
+    }
+    info!("API: new magic scaling coefficient: {}", request.magic_scaling_coeff);
+    context.send(Ev::ScalingCoeff(request.magic_scaling_coeff))?;
+    ok()
+}
+
+async fn put_model_parameters(State(context): State<Context>, Path(name): Path<String>, request: Result<Json<HashMap<String, f64>>, JsonRejection>) -> ApiResult {
+    let Json(parameters) = request?;
+    // check everything first so that a request is applied either completely or not at all
+    let model = context.snapshot_rx.borrow().models.iter().find(|model| model.name == name).cloned();
+    let Some(model) = model else {
+        return Err(ApiError(StatusCode::NOT_FOUND, format!("unknown model '{}', available: {:?}", name, context.model_names)));
+    };
+    for (parameter, value) in &parameters {
+        if !model.parameters.contains_key(parameter.as_str()) {
+            return Err(bad_request(format!("unknown parameter '{}' of model '{}', available: {:?}", parameter, name, model.parameters.keys().collect::<Vec<_>>())));
+        }
+        if !value.is_finite() {
+            return Err(bad_request(format!("parameter '{}' must be finite, got {}", parameter, value)));
+        }
+    }
+    let (reply_tx, reply_rx) = oneshot::channel();
+    context.send(Ev::ModelParameters(name, parameters, reply_tx))?;
+    match reply_rx.await {
+        Ok(Ok(())) => ok(),
+        Ok(Err(e)) => Err(bad_request(e)),
+        Err(_) => Err(ApiError(StatusCode::SERVICE_UNAVAILABLE, "emulator is shutting down".to_string())),
+    }
+}
+
+fn router(context: Context) -> Router {
+    Router::new()
+        .route("/state", get(get_state))
+        .route("/events", get(get_events))
+        .route("/models", get(get_models))
+        .route("/mode", put(put_mode))
+        .route("/manual_attenuation", put(put_manual_attenuation))
+        .route("/magic_scaling_coeff", put(put_magic_scaling_coeff))
+        .route("/models/:name/parameters", put(put_model_parameters))
+        .with_state(context)
+}
+
+/// serves the API on `address` until the emulator stops
+pub async fn serve(address: SocketAddr, context: Context) -> io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(address).await?;
+    axum::serve(listener, router(context)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+    use tower::ServiceExt;
+
+    const MODEL_NAMES: [&str; 2] = ["free_space", "two_ray"];
+
+    fn context() -> (Context, UnboundedReceiver<Ev>) {
+        let (tx, rx) = unbounded_channel();
+        let (to_gui_tx, _) = unbounded_channel();
+        let models = MODEL_NAMES.iter().map(|name| Model { name, parameters: BTreeMap::from([("frequency", 5.9e9)]) }).collect();
+        let (_, snapshot_rx) = watch::channel(Snapshot::new(models, 40., 30000.));
+        (Context { tx, to_gui_tx, snapshot_rx, model_names: MODEL_NAMES.to_vec() }, rx)
+    }
+
+    async fn put_json(context: Context, uri: &str, body: &str) -> (StatusCode, serde_json::Value) {
+        let request = Request::put(uri).header("content-type", "application/json").body(Body::from(body.to_string())).unwrap();
+        let response = router(context).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn mode_selects_model_or_keeps_manual_attenuation() {
+        let (context, mut rx) = context();
+        let (status, body) = put_json(context.clone(), "/mode", r#"{"mode": "two_ray"}"#).await;
+        assert_eq!((status, body), (StatusCode::OK, json!({ "ok": true })));
+        assert!(matches!(rx.try_recv(), Ok(Ev::ModeAutomatic(1))));
+        let (status, _) = put_json(context, "/mode", r#"{"mode": "manual"}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(matches!(rx.try_recv(), Ok(Ev::ModeManual(None))));
+    }
+
+    #[tokio::test]
+    async fn unknown_mode_is_rejected() {
+        let (context, mut rx) = context();
+        let (status, body) = put_json(context, "/mode", r#"{"mode": "ground_wave"}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["ok"], json!(false));
+        assert!(body["error"].as_str().unwrap().contains("ground_wave"));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn manual_attenuation_is_checked_and_forwarded() {
+        let (context, mut rx) = context();
+        let (status, _) = put_json(context.clone(), "/manual_attenuation", r#"{"manual_attenuation": 130}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(rx.try_recv().is_err());
+        let (status, _) = put_json(context, "/manual_attenuation", r#"{"manual_attenuation": 55}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(matches!(rx.try_recv(), Ok(Ev::ModeManual(Some(v))) if v == 55.));
+    }
+
+    #[tokio::test]
+    async fn parameters_of_unknown_model_are_not_found() {
+        let (context, mut rx) = context();
+        let (status, body) = put_json(context, "/models/ground_wave/parameters", r#"{"frequency": 2.4e9}"#).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["ok"], json!(false));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn unknown_parameter_rejects_the_whole_request() {
+        let (context, mut rx) = context();
+        let (status, body) = put_json(context, "/models/two_ray/parameters", r#"{"frequency": 2.4e9, "height": 3}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body["error"].as_str().unwrap().contains("height"));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn parameters_are_applied_by_the_main_loop() {
+        let (context, mut rx) = context();
+        let main_loop = tokio::spawn(async move {
+            let Some(Ev::ModelParameters(name, parameters, reply)) = rx.recv().await else {
+                panic!("expected new model parameters");
+            };
+            reply.send(Ok(())).unwrap();
+            (name, parameters)
+        });
+        let (status, body) = put_json(context, "/models/two_ray/parameters", r#"{"frequency": 2.4e9}"#).await;
+        assert_eq!((status, body), (StatusCode::OK, json!({ "ok": true })));
+        assert_eq!(main_loop.await.unwrap(), ("two_ray".to_string(), HashMap::from([("frequency".to_string(), 2.4e9)])));
+    }
+
+    #[tokio::test]
+    async fn parameters_rejected_by_the_model_are_a_bad_request() {
+        let (context, mut rx) = context();
+        tokio::spawn(async move {
+            if let Some(Ev::ModelParameters(_, _, reply)) = rx.recv().await {
+                reply.send(Err("frequency must be positive".to_string())).unwrap();
+            }
+        });
+        let (status, body) = put_json(context, "/models/two_ray/parameters", r#"{"frequency": -1}"#).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], json!("frequency must be positive"));
+    }
+}
@@ -0,0 +1,259 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+use crate::mavlink::PositionSource;
+use crate::natnet::UpAxis;
+use channel_models::{create_models, model_names, resolve_file_names, ChannelModel, Scenario, TapKernel, MAGIC_SCALING_COEFF};
+
+/// name of the manual mode in `default_model`
+pub const MANUAL_MODE: &str = "manual";
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Configuration of chanem, read from a TOML file (see `chanem.example.toml`) and overridden
+/// by command line flags and environment variables. Keys that are not given keep their
+/// default value.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// UDP port to receive position updates
+    pub local_udp_port: u32,
+    /// UDP port to receive the model selection and scaling coefficient from the GUI
+    pub model_selection_udp_port: u32,
+    /// host the taps are sent to
+    pub chanem_host: String,
+    /// UDP port the taps are sent to
+    pub chanem_port: u32,
+    /// address of the GUI
+    pub gui_address: String,
+    /// also accept the unframed position updates of earlier versions
+    pub legacy_position_updates: bool,
+    /// id of the emulated UAV in framed position updates, updates of all UAVs if None
+    pub uav_id: Option<u16>,
+    /// UDP port to receive MAVLink telemetry on, disabled if None
+    pub mavlink_udp_port: Option<u32>,
+    /// MAVLink system id of the UAV, the first system sending a position or attitude if None
+    pub mavlink_system_id: Option<u8>,
+    pub mavlink_position_source: PositionSource,
+    /// position (x east, y north, z up) of the local origin of the autopilot in the local
+    /// frame, used with LOCAL_POSITION_NED
+    pub mavlink_local_origin: [f32; 3],
+    /// address of the motion-capture host streaming NatNet, disabled if None
+    pub natnet_server: Option<String>,
+    pub natnet_command_port: u16,
+    pub natnet_data_port: u16,
+    /// receive the frames via multicast rather than unicast
+    pub natnet_multicast: bool,
+    pub natnet_multicast_address: String,
+    /// name (or id) of the rigid body of the UAV
+    pub natnet_rigid_body: String,
+    /// scale of the motion-capture coordinates to metres
+    pub natnet_scale: f32,
+    pub natnet_up_axis: UpAxis,
+    /// position of the motion-capture origin in the local frame
+    pub natnet_origin: [f32; 3],
+    /// rotation in degrees (counter-clockwise) of the motion-capture axes about the vertical
+    pub natnet_yaw: f32,
+    /// address of the HTTP/JSON API, disabled if None
+    pub http_address: Option<String>,
+    /// samples per second
+    pub sample_rate: f64,
+    /// model selected at startup, one of the registered models or "manual"
+    pub default_model: String,
+    /// attenuation in dB of the manual mode until it is changed via GUI or gamepad
+    pub manual_attenuation: f32,
+    /// scaling of the taps until it is changed via GUI
+    pub magic_scaling_coeff: f32,
+    /// rate in Hz at which the taps are refreshed between position updates (0 disables)
+    pub tap_update_rate: f32,
+    pub tap_kernel: TapKernel,
+    pub sinc_half_width: usize,
+    /// scenario file, relative to the configuration file
+    pub scenario_file: Option<PathBuf>,
+    /// the scenario as an inline table, alternative to `scenario_file`
+    pub scenario: Option<Scenario>,
+    /// parameter values per model name, see `ChannelModel::parameters`
+    pub model_parameters: HashMap<String, HashMap<String, f64>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            local_udp_port: 1337,
+            model_selection_udp_port: 1341,
+            chanem_host: "127.0.0.1".to_string(),
+            chanem_port: 1338,
+            // forward to Host (has .1 address of every docker compose network)
+            gui_address: "172.18.0.1:1342".to_string(),
+            legacy_position_updates: true,
+            uav_id: None,
+            mavlink_udp_port: None,
+            mavlink_system_id: None,
+            mavlink_position_source: PositionSource::default(),
+            mavlink_local_origin: [0.0; 3],
+            natnet_server: None,
+            natnet_command_port: 1510,
+            natnet_data_port: 1511,
+            natnet_multicast: true,
+            natnet_multicast_address: "239.255.42.99".to_string(),
+            natnet_rigid_body: String::new(),
+            natnet_scale: 1.0,
+            natnet_up_axis: UpAxis::default(),
+            natnet_origin: [0.0; 3],
+            natnet_yaw: 0.0,
+            http_address: None,
+            sample_rate: 200e6,
+            default_model: model_names()[0].to_string(),
+            manual_attenuation: 50.0,
+            magic_scaling_coeff: MAGIC_SCALING_COEFF,
+            tap_update_rate: 100.0,
+            tap_kernel: TapKernel::default(),
+            sinc_half_width: 4,
+            scenario_file: None,
+            scenario: None,
+            model_parameters: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads a configuration from a TOML file; a relative `scenario_file` and the relative file
+    /// names in an inline `scenario` table are resolved against the directory of the
+    /// configuration file.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Config> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        let mut table: toml::Table = content.parse().map_err(|e: toml::de::Error| invalid(e.to_string()))?;
+        if let (Some(toml::Value::Table(scenario)), Some(directory)) = (table.get_mut("scenario"), path.parent()) {
+            resolve_file_names(scenario, directory);
+        }
+        let mut config: Config = table.try_into().map_err(|e: toml::de::Error| invalid(e.to_string()))?;
+        if let (Some(scenario_file), Some(directory)) = (&config.scenario_file, path.parent()) {
+            config.scenario_file = Some(directory.join(scenario_file));
+        }
+        Ok(config)
+    }
+
+    /// the scenario from `scenario_file`, the inline `scenario` table or the built-in default
+    pub fn load_scenario(&self) -> io::Result<Scenario> {
+        match (&self.scenario_file, &self.scenario) {
+            (Some(_), Some(_)) => Err(invalid("scenario_file and an inline scenario table are mutually exclusive".to_string())),
+            (Some(path), None) => Scenario::from_file(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e))),
+            (None, Some(scenario)) => Ok(scenario.clone()),
+            (None, None) => Ok(Scenario::default()),
+        }
+    }
+
+    /// multicast group of the NatNet data stream, None with unicast or if
+    /// `natnet_multicast_address` is not an IPv4 multicast address
+    pub fn natnet_multicast_group(&self) -> Option<Ipv4Addr> {
+        self.natnet_multicast_address.parse::<Ipv4Addr>().ok().filter(|address| self.natnet_multicast && address.is_multicast())
+    }
+
+    /// mode index of `default_model` as used by the GUI and the gamepad
+    pub fn default_mode_index(&self) -> usize {
+        crate::mode_index(model_names().iter().position(|name| *name == self.default_model))
+    }
+
+    /// Checks all values, including that the model parameters exist in the models of `scenario`.
+    pub fn validate(&self, scenario: &Scenario) -> io::Result<()> {
+        let names = model_names();
+        if self.default_model != MANUAL_MODE && !names.contains(&self.default_model.as_str()) {
+            return Err(invalid(format!("unknown default_model '{}', available: {:?} and \"{}\"", self.default_model, names, MANUAL_MODE)));
+        }
+        if self.gui_address.parse::<SocketAddr>().is_err() {
+            return Err(invalid(format!("gui_address '{}' is not an address like 172.18.0.1:1342", self.gui_address)));
+        }
+        if let Some(http_address) = &self.http_address {
+            if http_address.parse::<SocketAddr>().is_err() {
+                return Err(invalid(format!("http_address '{}' is not an address like 127.0.0.1:1343", http_address)));
+            }
+        }
+        if self.chanem_host.is_empty() {
+            return Err(invalid("chanem_host must not be empty".to_string()));
+        }
+        if !(self.sample_rate > 0. && self.sample_rate.is_finite()) {
+            return Err(invalid(format!("sample_rate must be positive, got {}", self.sample_rate)));
+        }
+        if !(0.0..=120.0).contains(&self.manual_attenuation) {
+            return Err(invalid(format!("manual_attenuation must be between 0 and 120 dB, got {}", self.manual_attenuation)));
+        }
+        if !(self.magic_scaling_coeff > 0. && self.magic_scaling_coeff.is_finite()) {
+            return Err(invalid(format!("magic_scaling_coeff must be positive, got {}", self.magic_scaling_coeff)));
+        }
+        if !(self.tap_update_rate >= 0. && self.tap_update_rate.is_finite()) {
+            return Err(invalid(format!("tap_update_rate must not be negative, got {}", self.tap_update_rate)));
+        }
+        if self.tap_kernel == TapKernel::Sinc && self.sinc_half_width == 0 {
+            return Err(invalid("sinc_half_width must be at least 1".to_string()));
+        }
+        if self.mavlink_udp_port.is_some() && self.mavlink_position_source == PositionSource::GlobalPositionInt && scenario.origin.is_none() {
+            return Err(invalid("MAVLink positions from global_position_int need the geodetic origin of the local frame (--origin or [origin] in the scenario)".to_string()));
+        }
+        if self.natnet_server.is_some() {
+            if self.natnet_rigid_body.is_empty() {
+                return Err(invalid("natnet_rigid_body must name the rigid body of the UAV".to_string()));
+            }
+            if self.natnet_multicast && self.natnet_multicast_group().is_none() {
+                return Err(invalid(format!("natnet_multicast_address '{}' is not an IPv4 multicast address", self.natnet_multicast_address)));
+            }
+            if !(self.natnet_scale > 0. && self.natnet_scale.is_finite()) {
+                return Err(invalid(format!("natnet_scale must be positive, got {}", self.natnet_scale)));
+            }
+        }
+        self.create_models(scenario).map(|_| ())
+    }
+
+    /// all registered models with `model_parameters` applied
+    pub fn create_models(&self, scenario: &Scenario) -> io::Result<Vec<Box<dyn ChannelModel>>> {
+        let mut models = create_models(scenario);
+        for (name, parameters) in &self.model_parameters {
+            let model = models
+                .iter_mut()
+                .find(|model| model.name() == name)
+                .ok_or_else(|| invalid(format!("model_parameters: unknown model '{}', available: {:?}", name, model_names())))?;
+            for (parameter, &value) in parameters {
+                model.set_parameter(parameter, value).map_err(|e| invalid(format!("model_parameters.{}: {}", name, e)))?;
+            }
+        }
+        Ok(models)
+    }
+
+    /// names of the settings that differ from `other` and only take effect after a restart
+    pub fn restart_required(&self, other: &Config) -> Vec<&'static str> {
+        [
+            ("local_udp_port", self.local_udp_port != other.local_udp_port),
+            ("model_selection_udp_port", self.model_selection_udp_port != other.model_selection_udp_port),
+            ("chanem_host", self.chanem_host != other.chanem_host),
+            ("chanem_port", self.chanem_port != other.chanem_port),
+            ("gui_address", self.gui_address != other.gui_address),
+            ("legacy_position_updates", self.legacy_position_updates != other.legacy_position_updates),
+            ("uav_id", self.uav_id != other.uav_id),
+            ("mavlink_udp_port", self.mavlink_udp_port != other.mavlink_udp_port),
+            ("mavlink_system_id", self.mavlink_system_id != other.mavlink_system_id),
+            ("mavlink_position_source", self.mavlink_position_source != other.mavlink_position_source),
+            ("mavlink_local_origin", self.mavlink_local_origin != other.mavlink_local_origin),
+            ("natnet_server", self.natnet_server != other.natnet_server),
+            ("natnet_command_port", self.natnet_command_port != other.natnet_command_port),
+            ("natnet_data_port", self.natnet_data_port != other.natnet_data_port),
+            ("natnet_multicast", self.natnet_multicast != other.natnet_multicast),
+            ("natnet_multicast_address", self.natnet_multicast_address != other.natnet_multicast_address),
+            ("natnet_rigid_body", self.natnet_rigid_body != other.natnet_rigid_body),
+            ("natnet_scale", self.natnet_scale != other.natnet_scale),
+            ("natnet_up_axis", self.natnet_up_axis != other.natnet_up_axis),
+            ("natnet_origin", self.natnet_origin != other.natnet_origin),
+            ("natnet_yaw", self.natnet_yaw != other.natnet_yaw),
+            ("http_address", self.http_address != other.http_address),
+            ("sample_rate", self.sample_rate != other.sample_rate),
+        ]
+        .into_iter()
+        .filter(|&(_, changed)| changed)
+        .map(|(name, _)| name)
+        .collect()
+    }
+}
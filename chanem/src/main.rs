@@ -7,127 +7,399 @@ use gilrs::{Gilrs, Button, Event, EventType};
 use log::debug;
 use log::info;
 use log::warn;
+use std::collections::HashMap;
 use std::io;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use tokio::net::UdpSocket;
+use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::mpsc::unbounded_channel;
-use tokio::sync::watch;
-// use cgmath::Vector2;
-// use rand::distributions::{Normal, IndependentSample};
+use tokio::sync::{oneshot, watch};
 use std::f32::consts::PI;
-// use cgmath::Vector2;
-// use rand_distr::{Normal, Distribution};
-// use rand;
-// use cgmath::InnerSpace;
-use num::complex::Complex32;
+use rand::Rng;
 
-use channel_models::{calculate_paths_freespace, calculate_paths_two_ray, calculate_paths_ce2r, calculate_paths_9ray_suburban, distance, FREQUENCY, FAR_FIELD_DISTANCE};
+mod api;
+mod config;
+mod mavlink;
+mod natnet;
+mod protocol;
 
-const MAX_TAPS: usize = 41;
+use config::{Config, MANUAL_MODE};
+use protocol::{Frame, PositionUpdate, Rejection, SequenceFilter};
+use channel_models::{attitude_from_ned, scenario_changed_for, vector_from_ned, ChannelModel, ChannelStatistics, FadingProcesses, GeodeticPosition, ImpulseResponse, PathKind, PropagationPath, Scenario, State, TapGrid, TapKernel, MAX_TAPS};
 
-const TAP_VALUE_NO_LOSS: Complex32 = Complex32::new(32767.0, 0.0);
-// const MAGIC_SCALING_COEFF: f32 = 140.5;
-const MAGIC_SCALING_COEFF: f32 = 30000.0;
-const TAP_VALUE_MAX: i16 = 32760;
-const TAP_VALUE_MIN: i16 = -32760;
 
-#[derive(Parser, Debug)]
+/// Command line flags, each of which can also be given as environment variable and overrides
+/// the respective key of the configuration file.
+#[derive(Parser, Debug, Clone)]
 struct Args {
-    /// UDP port to receive position updates
-    #[clap(short, long, default_value_t = 1337)]
-    local_udp_port: u32,
-    /// UDP port to receive position updates
-    #[clap(short, long, default_value_t = 1341)]
-    model_selection_udp_port: u32,
-    /// UDP port of channel emulator
-    #[clap(short, long, default_value_t = 1338)]
-    chanem_port: u32,
-    /// Sample Rate
-    #[clap(long, default_value_t = 200e6)]
-    sample_rate: f64,
+    /// TOML configuration file, see chanem.example.toml (built-in defaults if omitted); reloaded on SIGHUP
+    #[clap(long, env = "CHANEM_CONFIG")]
+    config: Option<PathBuf>,
+    /// UDP port to receive position updates [default: 1337]
+    #[clap(short, long, env = "CHANEM_LOCAL_UDP_PORT")]
+    local_udp_port: Option<u32>,
+    /// UDP port to receive the model selection and scaling coefficient from the GUI [default: 1341]
+    #[clap(short, long, env = "CHANEM_MODEL_SELECTION_UDP_PORT")]
+    model_selection_udp_port: Option<u32>,
+    /// Host of channel emulator [default: 127.0.0.1]
+    #[clap(long, env = "CHANEM_HOST")]
+    chanem_host: Option<String>,
+    /// UDP port of channel emulator [default: 1338]
+    #[clap(short, long, env = "CHANEM_PORT")]
+    chanem_port: Option<u32>,
+    /// Address of the GUI [default: 172.18.0.1:1342]
+    #[clap(long, env = "CHANEM_GUI_ADDRESS")]
+    gui_address: Option<String>,
+    /// Accept the unframed position updates of earlier versions: true or false [default: true]
+    #[clap(long, env = "CHANEM_LEGACY_POSITION_UPDATES", value_parser)]
+    legacy_position_updates: Option<bool>,
+    /// Id of the emulated UAV in framed position updates [default: any]
+    #[clap(long, env = "CHANEM_UAV_ID")]
+    uav_id: Option<u16>,
+    /// UDP port to receive MAVLink telemetry (GLOBAL_POSITION_INT, LOCAL_POSITION_NED, ATTITUDE) on, e.g. 14550 [default: disabled]
+    #[clap(long, env = "CHANEM_MAVLINK_UDP_PORT")]
+    mavlink_udp_port: Option<u32>,
+    /// MAVLink system id of the UAV [default: the first system sending a position or attitude]
+    #[clap(long, env = "CHANEM_MAVLINK_SYSTEM_ID")]
+    mavlink_system_id: Option<u8>,
+    /// MAVLink message the position is taken from: global_position_int (needs the origin) or local_position_ned [default: global_position_int]
+    #[clap(long, env = "CHANEM_MAVLINK_POSITION_SOURCE", value_parser)]
+    mavlink_position_source: Option<mavlink::PositionSource>,
+    /// Position x,y,z (metres, east, north, up) of the local origin of the autopilot, used with local_position_ned [default: 0,0,0]
+    #[clap(long, env = "CHANEM_MAVLINK_LOCAL_ORIGIN", value_parser = parse_vector)]
+    mavlink_local_origin: Option<[f32; 3]>,
+    /// Address of the motion-capture host streaming NatNet (OptiTrack Motive) [default: disabled]
+    #[clap(long, env = "CHANEM_NATNET_SERVER")]
+    natnet_server: Option<String>,
+    /// Name (or id) of the rigid body of the UAV in the motion-capture system
+    #[clap(long, env = "CHANEM_NATNET_RIGID_BODY")]
+    natnet_rigid_body: Option<String>,
+    /// Receive the NatNet frames via multicast rather than unicast: true or false [default: true]
+    #[clap(long, env = "CHANEM_NATNET_MULTICAST", value_parser)]
+    natnet_multicast: Option<bool>,
+    /// Scale of the motion-capture coordinates to metres, e.g. 0.001 for millimetres [default: 1]
+    #[clap(long, env = "CHANEM_NATNET_SCALE")]
+    natnet_scale: Option<f32>,
+    /// Vertical axis of the motion-capture system: y or z [default: y]
+    #[clap(long, env = "CHANEM_NATNET_UP_AXIS", value_parser)]
+    natnet_up_axis: Option<natnet::UpAxis>,
+    /// Position x,y,z (metres, local frame) of the motion-capture origin [default: 0,0,0]
+    #[clap(long, env = "CHANEM_NATNET_ORIGIN", value_parser = parse_vector)]
+    natnet_origin: Option<[f32; 3]>,
+    /// Rotation in degrees (counter-clockwise) of the motion-capture axes about the vertical [default: 0]
+    #[clap(long, env = "CHANEM_NATNET_YAW")]
+    natnet_yaw: Option<f32>,
+    /// Address of the HTTP/JSON control and state API, e.g. 127.0.0.1:1343 [default: disabled]
+    #[clap(long, env = "CHANEM_HTTP_ADDRESS")]
+    http_address: Option<String>,
+    /// Sample Rate [default: 200e6]
+    #[clap(long, env = "CHANEM_SAMPLE_RATE")]
+    sample_rate: Option<f64>,
+    /// Model selected at startup, one of the registered models or "manual" [default: the first model]
+    #[clap(long, env = "CHANEM_DEFAULT_MODEL")]
+    default_model: Option<String>,
+    /// Attenuation in dB of the manual mode at startup [default: 50]
+    #[clap(long, env = "CHANEM_MANUAL_ATTENUATION")]
+    manual_attenuation: Option<f32>,
+    /// Scaling of the taps at startup [default: 30000]
+    #[clap(long, env = "CHANEM_MAGIC_SCALING_COEFF")]
+    magic_scaling_coeff: Option<f32>,
+    /// TOML file describing station position, carrier and ground, overrides the scenario of the configuration file
+    #[clap(long, env = "CHANEM_SCENARIO")]
+    scenario: Option<PathBuf>,
+    /// Geodetic reference of the local frame as latitude,longitude,altitude (degrees, metres), overrides the origin of the scenario file
+    #[clap(long, env = "CHANEM_ORIGIN", value_parser = parse_geodetic_position)]
+    origin: Option<GeodeticPosition>,
+    /// Seed of the stochastic channel models, overrides the seed of the scenario file (random if neither is given)
+    #[clap(long, env = "CHANEM_SEED")]
+    seed: Option<u64>,
+    /// Rate in Hz at which the taps are refreshed between position updates to rotate the phases of Doppler-shifted paths and evolve the fading (0 disables); it has to be at least twice the largest Doppler shift, e.g. 330 Hz at 2.45 GHz and 20 m/s, else a warning is logged [default: 100]
+    #[clap(long, env = "CHANEM_TAP_UPDATE_RATE")]
+    tap_update_rate: Option<f32>,
+    /// Interpolation kernel that places each path on the tap grid according to its (fractional) delay: floor, linear or sinc (adds a latency of --sinc-half-width samples, 20 ns at 200 MS/s with the default half width) [default: floor]
+    #[clap(long, env = "CHANEM_TAP_KERNEL", value_parser)]
+    tap_kernel: Option<TapKernel>,
+    /// Half width in taps of the windowed-sinc kernel, all paths are delayed by as many samples to keep it causal [default: 4]
+    #[clap(long, env = "CHANEM_SINC_HALF_WIDTH")]
+    sinc_half_width: Option<usize>,
 }
 
-fn loss_to_tap_value(loss_linear: f32, phase: Complex32, magic_scaling_coeff: f32) -> Complex32 {
-    if loss_linear == 0. {
-        TAP_VALUE_NO_LOSS * phase
-    } else {
-        (TAP_VALUE_NO_LOSS / loss_linear) * magic_scaling_coeff
+/// The configuration file (or the defaults) with the command line flags and environment
+/// variables on top, and the scenario it describes, both validated.
+fn load_config(args: &Args) -> io::Result<(Config, Scenario)> {
+    let mut config = match &args.config {
+        Some(path) => Config::from_file(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?,
+        None => Config::default(),
+    };
+    macro_rules! override_with_args {
+        ($($field:ident),*) => {
+            $(if let Some(value) = args.$field.clone() {
+                config.$field = value;
+            })*
+        };
+    }
+    override_with_args!(local_udp_port, model_selection_udp_port, chanem_host, chanem_port, gui_address, legacy_position_updates, mavlink_position_source, mavlink_local_origin, natnet_rigid_body, natnet_multicast, natnet_scale, natnet_up_axis, natnet_origin, natnet_yaw, sample_rate, default_model, manual_attenuation, magic_scaling_coeff, tap_update_rate, tap_kernel, sinc_half_width);
+    if args.uav_id.is_some() {
+        config.uav_id = args.uav_id;
+    }
+    if args.mavlink_udp_port.is_some() {
+        config.mavlink_udp_port = args.mavlink_udp_port;
+    }
+    if args.mavlink_system_id.is_some() {
+        config.mavlink_system_id = args.mavlink_system_id;
+    }
+    if args.natnet_server.is_some() {
+        config.natnet_server = args.natnet_server.clone();
     }
+    if args.http_address.is_some() {
+        config.http_address = args.http_address.clone();
+    }
+    if let Some(path) = &args.scenario {
+        config.scenario_file = Some(path.clone());
+        config.scenario = None;
+    }
+    let mut scenario = config.load_scenario()?;
+    if let Some(origin) = args.origin {
+        scenario.origin = Some(origin);
+    }
+    if let Some(seed) = args.seed {
+        scenario.seed = Some(seed);
+    }
+    config.validate(&scenario)?;
+    Ok((config, scenario))
 }
 
-fn convert_paths_to_taps(paths: Vec<(f32, f32, f32)>, sample_rate: f32, magic_scaling_coeff: f32) -> [i16; MAX_TAPS * 2] {
-    let delay_per_tap = 1. / sample_rate;
-    let mut taps_complex = [Complex32::new(0., 0.); MAX_TAPS];
-    for (loss_linear, delay, additional_phase_shift) in paths.into_iter() {
-        let tap_index = (delay / delay_per_tap).floor() as usize;
-        if tap_index > 2 {
-            println!("tap index: {}", tap_index);
-        }
-        if tap_index < MAX_TAPS {
-            let phase_offset = 2. * PI * delay * FREQUENCY + additional_phase_shift;
-            let phase = Complex32::from_polar(1., phase_offset);
-            let mpc = loss_to_tap_value(loss_linear, phase, magic_scaling_coeff);
-            taps_complex[tap_index] += mpc;
-        };
+fn parse_geodetic_position(value: &str) -> Result<GeodeticPosition, String> {
+    let numbers = value.split(',').map(|number| number.trim().parse::<f64>()).collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    match numbers[..] {
+        [latitude, longitude] => Ok(GeodeticPosition::new(latitude, longitude, 0.0)),
+        [latitude, longitude, altitude] => Ok(GeodeticPosition::new(latitude, longitude, altitude)),
+        _ => Err("expected latitude,longitude[,altitude]".to_string()),
     }
+}
+
+fn parse_vector(value: &str) -> Result<[f32; 3], String> {
+    let numbers = value.split(',').map(|number| number.trim().parse::<f32>()).collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?;
+    numbers.try_into().map_err(|_| "expected x,y,z".to_string())
+}
+
+fn convert_paths_to_taps(paths: Vec<PropagationPath>, frequency: f32, tap_grid: &TapGrid, magic_scaling_coeff: f32) -> [i16; MAX_TAPS * 2] {
     let mut taps = [0_i16; MAX_TAPS * 2];
-    for (i, complex_tap) in taps_complex.iter().enumerate() {
-        taps[i] = (complex_tap.re as i16).clamp(TAP_VALUE_MIN, TAP_VALUE_MAX);
-        taps[i + MAX_TAPS] = (complex_tap.im as i16).clamp(TAP_VALUE_MIN, TAP_VALUE_MAX);
-    }
+    taps.copy_from_slice(&tap_grid.quantized_taps(&paths, frequency, magic_scaling_coeff));
     taps
 }
 
-// fn calculate_taps_two_segment_log_dist(
-//     x: f32, y: f32, z: f32, r_rad: f32, p_rad: f32, y_rad: f32
-// ) -> [i16; MAX_TAPS * 2] {
-//     let mut taps = [0i16; MAX_TAPS * 2];
-//
-//     let dist = distance(x.copy(), y.copy(), z);
-//     let theta = (distance(x.copy(), y.copy(), STATION_Z) / dist.copy()).atan();
-//
-//     let to_origin = Vector2(x - STATION_X, y - STATION_Y);
-//     let yaw_vec = Vector2(y_rad.cos(), y_rad.sin());
-//     let direction_of_travel = if to_origin.dot(yaw_vec) > 0 {-1} else {1};
-//
-//     if theta < 5.0_f64.to_radians() {
-//         let normal = Normal::new(0.0, 3.3);
-//         let x = normal.ind_sample(&mut rand::thread_rng());
-//         let path_loss = 116.4 + 10 * 1.6 * (dist / 3000).log10() + X_s + direction_of_travel*3.0;
-//     }
-//     else {
-//         let normal = Normal::new(0.0, 3.0);
-//         let x = normal.ind_sample(&mut rand::thread_rng());
-//         let path_loss = 123.5 + 10 * 1.8 * (dist / 6600).log10() + X_l + direction_of_travel*1.7;
-//     }
-//     let tap = 10 ** (REFERENCE_SIGNAL_LEVEL_NO_LOSS_DBM - path_loss);
-//     let tap = tap.clamp(0.0, 10000.0);
-//
-//     info!("distance {:?}, yaw {:?}rad, path-loss {:?}dB", dist, y_rad, path_loss);
-//
-//     taps[0] = tap as i16;
-//     taps[MAX_TAPS] = tap as i16;
-//     //taps[0] = 8000;
-//     taps
-// }
-
 #[derive(Debug)]
 enum Ev {
-    ModeManual(f32),
-    ModeAutomaticFreeSpace,
-    ModeAutomaticFlatEarthTwoRay,
-    ModeAutomaticCurvedEarthTwoRay,
-    ModeAutomaticNineRay,
-    Value(f32, f32, f32, f32, f32, f32),
+    /// manual mode with a new attenuation in dB, None keeps the last one
+    ModeManual(Option<f32>),
+    /// index into the model registry of channel_models
+    ModeAutomatic(usize),
+    /// UAV state, its velocity if the update carried one, and when the update was received
+    Value(State, Option<[f32; 3]>, Instant),
     ScalingCoeff(f32),
+    /// configuration and scenario reloaded on SIGHUP
+    Reload(Box<Config>, Box<Scenario>),
+    /// parameter values of the named model, set via the HTTP API, which awaits the outcome
+    ModelParameters(String, HashMap<String, f64>, oneshot::Sender<Result<(), String>>),
+}
+
+/// updates further apart than this are not used to estimate the velocity (e.g. after a pause)
+const MAX_VELOCITY_ESTIMATION_GAP: Duration = Duration::from_secs(1);
+/// updates closer than this are considered duplicates
+const MIN_VELOCITY_ESTIMATION_GAP: Duration = Duration::from_millis(5);
+
+/// Estimates the UAV velocity by differentiating successive positions, used when the position
+/// updates do not carry a velocity.
+#[derive(Default)]
+struct VelocityEstimator {
+    last: Option<(Instant, [f32; 3])>,
+    velocity: [f32; 3],
+}
+
+impl VelocityEstimator {
+    fn update(&mut self, position: [f32; 3], received: Instant) -> [f32; 3] {
+        match self.last {
+            Some((last_received, _)) if received.duration_since(last_received) < MIN_VELOCITY_ESTIMATION_GAP => return self.velocity,
+            Some((last_received, last_position)) if received.duration_since(last_received) <= MAX_VELOCITY_ESTIMATION_GAP => {
+                let dt = received.duration_since(last_received).as_secs_f32();
+                self.velocity = [0, 1, 2].map(|i| (position[i] - last_position[i]) / dt);
+            }
+            _ => self.velocity = [0.0; 3],
+        }
+        self.last = Some((received, position));
+        self.velocity
+    }
+}
+
+/// paths as they are at `elapsed` after their computation, i.e. with the carrier phase rotated
+/// by each path's Doppler shift
+fn advance_paths(paths: &[PropagationPath], elapsed: Duration) -> Vec<PropagationPath> {
+    let elapsed = elapsed.as_secs_f32();
+    paths
+        .iter()
+        .map(|path| PropagationPath { phase: path.phase + 2. * PI * path.doppler * elapsed, ..path.clone() })
+        .collect()
+}
+
+/// Largest Doppler shift in Hz the taps have to follow: that of the paths and, with fading,
+/// `fading_max_doppler`. Refreshing the taps at less than twice this rate aliases the phase
+/// rotation, the emulated channel then shows a wrong Doppler shift.
+fn max_doppler(paths: &[PropagationPath], fading_max_doppler: Option<f32>) -> f32 {
+    paths.iter().map(|path| path.doppler.abs()).chain(fading_max_doppler).fold(0., f32::max)
+}
+
+/// local state of a UAV at a geodetic position, with attitude and velocity in the NED
+/// convention of autopilots
+fn state_from_geodetic(origin: &GeodeticPosition, position: &GeodeticPosition, attitude: [f32; 3], velocity: Option<[f32; 3]>) -> (State, Option<[f32; 3]>) {
+    let [x, y, z] = origin.local(position);
+    let (roll, pitch, yaw) = attitude_from_ned(attitude[0], attitude[1], attitude[2]);
+    (State::new(x, y, z, roll, pitch, yaw), velocity.map(vector_from_ned))
+}
+
+/// Local state and velocity of a position update, None if it is geodetic and the local frame
+/// has no geodetic origin.
+fn state_from_update(update: &PositionUpdate, origin: Option<GeodeticPosition>) -> Option<(State, Option<[f32; 3]>)> {
+    match update.frame {
+        Frame::Local => {
+            let [x, y, z] = update.position.map(|coordinate| coordinate as f32);
+            let [roll, pitch, yaw] = update.attitude;
+            Some((State::new(x, y, z, roll, pitch, yaw), update.velocity))
+        }
+        Frame::Geodetic => {
+            let Some(origin) = origin else {
+                warn!("geodetic position received, but no origin is configured (--origin or [origin] in the scenario)");
+                return None;
+            };
+            let [latitude, longitude, altitude] = update.position;
+            Some(state_from_geodetic(&origin, &GeodeticPosition::new(latitude, longitude, altitude), update.attitude, update.velocity))
+        }
+    }
+}
+
+/// 'P' message for the GUI with the local position and attitude in the format of the plain
+/// position updates
+fn position_message(state: &State) -> Vec<u8> {
+    let mut send_buf: Vec<u8> = [state.x, state.y, state.z, state.roll, state.pitch, state.yaw].iter().flat_map(|v| v.to_be_bytes()).collect();
+    // prepend 'P' as message type to distinguish between [P]osition, [T]aps, and [M]ode
+    send_buf.insert(0_usize, b'P');
+    send_buf
+}
+
+/// 'N' message for the GUI with the mode names (newline separated, in mode index order) to
+/// label its mode selector, repeated on every mode change so a GUI started later gets it too
+fn names_message(model_names: &[&str]) -> Vec<u8> {
+    let mut send_buf = model_names.join("\n").into_bytes();
+    // prepend 'N' as message type to distinguish between [P]osition, [T]aps, [M]ode and model [N]ames
+    send_buf.insert(0_usize, b'N');
+    send_buf
 }
 
-const NUM_MODES: usize = 5;
-const MODEL_INDEX_AUTOMATIC_FREE_SPACE: usize = 0;
-const MODEL_INDEX_AUTOMATIC_FLAT_EARTH_TWO_RAY: usize = 1;
-const MODEL_INDEX_AUTOMATIC_CURVED_EARTH_TWO_RAY: usize = 2;
-const MODEL_INDEX_AUTOMATIC_NINE_RAY: usize = 3;
+/// 'S' message for the GUI with the statistics of the channel at a position update: power
+/// (linear), mean delay and RMS delay spread (s), K-factor (dB) and coherence bandwidth (Hz)
+fn statistics_message(statistics: &ChannelStatistics) -> Vec<u8> {
+    let mut send_buf: Vec<u8> = [statistics.power, statistics.mean_delay, statistics.rms_delay_spread, statistics.k_factor, statistics.coherence_bandwidth]
+        .iter()
+        .flat_map(|v| v.to_be_bytes())
+        .collect();
+    // prepend 'S' as message type to distinguish between [P]osition, [T]aps, [M]ode and channel [S]tatistics
+    send_buf.insert(0_usize, b'S');
+    send_buf
+}
+
+/// timer of the tap updates between position updates, `rate` in Hz
+fn tap_update_timer(rate: f32) -> tokio::time::Interval {
+    let mut interval = tokio::time::interval(Duration::from_secs_f32(1. / rate.max(1e-3)));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    interval
+}
+
+/// lets the fading processes evolve from their last update until now
+fn advance_fading(fading: &mut FadingProcesses, updated: &mut Instant, max_doppler: f32) {
+    let now = Instant::now();
+    fading.advance(now.duration_since(*updated).as_secs_f32(), max_doppler);
+    *updated = now;
+}
+
+/// Takes the models of a reloaded configuration over without losing the state of the others:
+/// a model whose part of the scenario changed is replaced by its new instance, the others only
+/// get the `model_parameters` that differ from the previously loaded ones, so values set at
+/// runtime are kept. A parameter removed from the file goes back to its value from the
+/// scenario. Returns the names of the replaced models.
+fn reload_models(
+    models: &mut [Box<dyn ChannelModel>],
+    new_models: Vec<Box<dyn ChannelModel>>,
+    (scenario, previous_scenario): (&Scenario, &Scenario),
+    (config, previous_config): (&Config, &Config),
+) -> Vec<&'static str> {
+    let none = HashMap::new();
+    let mut replaced = Vec::new();
+    for (model, new_model) in models.iter_mut().zip(new_models) {
+        let name = new_model.name();
+        if scenario_changed_for(name, scenario, previous_scenario) {
+            *model = new_model;
+            replaced.push(name);
+            continue;
+        }
+        let (parameters, previous_parameters) = (config.model_parameters.get(name).unwrap_or(&none), previous_config.model_parameters.get(name).unwrap_or(&none));
+        for (parameter, value) in new_model.parameters() {
+            if parameters.get(parameter) != previous_parameters.get(parameter) {
+                if let Err(e) = model.set_parameter(parameter, value) {
+                    warn!("SIGHUP: setting {} of {} failed: {}", parameter, name, e);
+                }
+            }
+        }
+    }
+    replaced
+}
+
+/// Mode index of manual mode as used by the GUI, the gamepad, the control port and the HTTP
+/// API. Originally the four models had the mode indices 0 to 3 and every higher index meant
+/// manual mode, so the models registered after them follow from 5 upwards.
 const MODEL_INDEX_MANUAL: usize = 4;
 
+/// index into the model registry of the model selected by a mode index, None for manual mode
+fn model_index(mode_index: usize, model_count: usize) -> Option<usize> {
+    let model_index = match mode_index {
+        MODEL_INDEX_MANUAL => return None,
+        mode_index if mode_index < MODEL_INDEX_MANUAL => mode_index,
+        mode_index => mode_index - 1,
+    };
+    (model_index < model_count).then_some(model_index)
+}
+
+/// mode index of a registered model, or of manual mode if None
+fn mode_index(model_index: Option<usize>) -> usize {
+    match model_index {
+        Some(model_index) if model_index >= MODEL_INDEX_MANUAL => model_index + 1,
+        Some(model_index) => model_index,
+        None => MODEL_INDEX_MANUAL,
+    }
+}
+
+/// names of all modes, model names and "manual", in mode index order
+fn mode_names(model_names: &[&'static str]) -> Vec<&'static str> {
+    (0..=model_names.len()).map(|mode_index| model_index(mode_index, model_names.len()).map_or(MANUAL_MODE, |model_index| model_names[model_index])).collect()
+}
+
+/// Translates a mode index as used by the GUI and the gamepad into an event. Mode indices of
+/// registered models select the respective model, everything else selects manual mode with
+/// `manual_value` or, if None, the last manual attenuation.
+fn mode_event(mode_index: usize, model_names: &[&str], manual_value: Option<f32>) -> Ev {
+    if let Some(model_index) = model_index(mode_index, model_names.len()) {
+        info!("mode automatic - {}", model_names[model_index]);
+        Ev::ModeAutomatic(model_index)
+    } else {
+        match manual_value {
+            Some(manual_value) => info!("mode manual - {}dB", manual_value),
+            None => info!("mode manual"),
+        }
+        Ev::ModeManual(manual_value)
+    }
+}
+
 
 #[tokio::main]
 async fn main() -> io::Result<()> {
@@ -149,18 +421,42 @@ async fn main() -> io::Result<()> {
     let args = Args::parse();
     info!("Args: {:?}", args);
 
+    let (config, mut scenario) = load_config(&args)?;
+    info!("Config: {:?}", config);
+    // fix the seed here so it can be logged and a run can be reproduced with --seed
+    let seed = scenario.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    scenario.seed = Some(seed);
+    info!("Scenario: {:?}", scenario);
+    info!("seed of the stochastic models: {} (reproduce with --seed {})", seed, seed);
+
+    let mut models = config.create_models(&scenario)?;
+    let model_names: Vec<&'static str> = models.iter().map(|model| model.name()).collect();
+    info!("available models: {:?}", model_names);
+    // in the main loop manual mode comes after all registered models, its mode index is MODEL_INDEX_MANUAL
+    let registry_index_manual = models.len();
+    let num_modes = models.len() + 1;
+    let model_names_gamepad = model_names.clone();
+    let model_names_gui = model_names.clone();
+
     let (tx, mut rx) = unbounded_channel();
     let my_tx = tx.clone();
     let my_tx_1 = tx.clone();
+    let my_tx_2 = tx.clone();
+    let my_tx_3 = tx.clone();
+    let my_tx_4 = tx.clone();
+    let reload_tx = tx.clone();
 
-    let (to_gui_udp_handler_tx, mut to_gui_udp_handler_rx) = unbounded_channel();
+    let (to_gui_udp_handler_tx, to_gui_udp_handler_rx) = unbounded_channel();
     let to_gui_udp_handler_tx_1 = to_gui_udp_handler_tx.clone();
     let to_gui_udp_handler_tx_2 = to_gui_udp_handler_tx.clone();
+    let to_gui_udp_handler_tx_3 = to_gui_udp_handler_tx.clone();
+    let to_gui_udp_handler_tx_4 = to_gui_udp_handler_tx.clone();
+    let to_gui_udp_handler_tx_5 = to_gui_udp_handler_tx.clone();
 
-    let (mode_channel_gui_to_gamepad_tx, mode_channel_gui_to_gamepad_rx) = watch::channel(MODEL_INDEX_AUTOMATIC_FREE_SPACE);
+    let (mode_channel_gui_to_gamepad_tx, mode_channel_gui_to_gamepad_rx) = watch::channel(0_usize);
 
     std::thread::spawn(move || {
-        let mut current_value = 50.0_f32;
+        let mut current_value = config.manual_attenuation;
         let mut gilrs = Gilrs::new().unwrap();
         let gamepad = gilrs.gamepads().next().map(|(_, b)| b);
         if let Some(pad) = gamepad {
@@ -173,30 +469,15 @@ async fn main() -> io::Result<()> {
                     let mut control_event = b"E00";
                     if matches!(event, EventType::ButtonReleased(Button::East, _)) {
                         pl_model_index += 1;
-                        pl_model_index = pl_model_index % NUM_MODES;
-                        if pl_model_index == MODEL_INDEX_AUTOMATIC_FREE_SPACE {
-                            info!("mode automatic - Free-Space PL");
-                            my_tx.send(Ev::ModeAutomaticFreeSpace).unwrap();
-                        } else if pl_model_index == MODEL_INDEX_AUTOMATIC_FLAT_EARTH_TWO_RAY {
-                            info!("mode automatic - Flat-Earth Two-Ray PL");
-                            my_tx.send(Ev::ModeAutomaticFlatEarthTwoRay).unwrap();
-                        } else if pl_model_index == MODEL_INDEX_AUTOMATIC_CURVED_EARTH_TWO_RAY {
-                            info!("mode automatic - Curved-Earth Two-Ray PL");
-                            my_tx.send(Ev::ModeAutomaticCurvedEarthTwoRay).unwrap();
-                        } else if pl_model_index == MODEL_INDEX_AUTOMATIC_NINE_RAY {
-                            info!("mode automatic - Curved-Earth Two-Ray PL");
-                            my_tx.send(Ev::ModeAutomaticNineRay).unwrap();
-                        } else {
-                            info!("mode manual - {}dB", current_value);
-                            my_tx.send(Ev::ModeManual(current_value)).unwrap();
-                        }
+                        pl_model_index %= num_modes;
+                        my_tx.send(mode_event(pl_model_index, &model_names_gamepad, Some(current_value))).unwrap();
                         send=true;
                     } else if matches!(event, EventType::ButtonReleased(Button::DPadDown, _)) {
                         if pl_model_index == MODEL_INDEX_MANUAL {
                             current_value += 5.0;
                             current_value = current_value.clamp(0.0, 120.0);
                             info!("mode manual - {}dB", current_value);
-                            my_tx.send(Ev::ModeManual(current_value)).unwrap();
+                            my_tx.send(Ev::ModeManual(Some(current_value))).unwrap();
                         }
                         send=true;
                     } else if matches!(event, EventType::ButtonReleased(Button::DPadUp, _)) {
@@ -204,7 +485,7 @@ async fn main() -> io::Result<()> {
                             current_value -= 5.0;
                             current_value = current_value.clamp(0.0, 120.0);
                             info!("mode manual - {}dB", current_value);
-                            my_tx.send(Ev::ModeManual(current_value)).unwrap();
+                            my_tx.send(Ev::ModeManual(Some(current_value))).unwrap();
                         }
                         send=true;
                     } else if matches!(event, EventType::ButtonPressed(Button::RightTrigger2, _)) {
@@ -241,51 +522,242 @@ async fn main() -> io::Result<()> {
 
     let sock_tx = UdpSocket::bind("0.0.0.0:0").await?;
     sock_tx
-        .connect(format!("127.0.0.1:{}", args.chanem_port))
+        .connect(format!("{}:{}", config.chanem_host, config.chanem_port))
         .await
         .unwrap();
 
+    // the origin can change when the configuration is reloaded
+    let (origin_tx, origin_rx) = watch::channel(scenario.origin);
+    let local_udp_port = config.local_udp_port;
+    let legacy_position_updates = config.legacy_position_updates;
+    let mut sequence_filter = SequenceFilter::new(config.uav_id);
     tokio::spawn(async move {
-        info!("spawning position update receiver, listening on port {}", args.local_udp_port);
-        let sock = UdpSocket::bind(format!("0.0.0.0:{}", args.local_udp_port)).await.unwrap();
+        info!("spawning position update receiver, listening on port {}", local_udp_port);
+        let sock = UdpSocket::bind(format!("0.0.0.0:{}", local_udp_port)).await.unwrap();
         let mut buf = [0; 2048];
         loop {
             let (len, addr) = sock.recv_from(&mut buf).await.unwrap();
             debug!("{:?} bytes received from {:?}", len, addr);
+            let received = Instant::now();
 
-            if len == 24 {
-                let x = f32::from_be_bytes(buf[0..4].try_into().unwrap());
-                let y = f32::from_be_bytes(buf[4..8].try_into().unwrap());
-                let z = f32::from_be_bytes(buf[8..12].try_into().unwrap());
-                let r_rad = f32::from_be_bytes(buf[12..16].try_into().unwrap());
-                let p_rad = f32::from_be_bytes(buf[16..20].try_into().unwrap());
-                let y_rad = f32::from_be_bytes(buf[20..24].try_into().unwrap());
-
-                tx.send(Ev::Value(x, y, z, r_rad, p_rad, y_rad)).unwrap();
-                debug!("received ([{}, {}, {}], [{}, {}, {}])", x, y, z, r_rad, p_rad, y_rad);
-
-                let mut send_buf = buf.to_vec();
-                // prepend 'P' as message type to distinguish between [P]osition, [T]aps, and [M]ode
-                send_buf.insert(0_usize, b'P');
-                to_gui_udp_handler_tx_1.send(send_buf).unwrap();  // TODO
-            }
-            else {
-                // erroneous message contains: b'PowerFolder node: [1337]-[AUTJpBd5EcTPnEtSPDkZ]\x00'
-                // some external program (PowerFolder, probably connected to HessenBox on some PC in the local network) also uses port 1337 -> ignore this specific message
-                // there might still arrive other malformed packages -> log for further inspection
-                let known_malformed_msg_prefix: [u8; 24] = [80, 111, 119, 101, 114, 70, 111, 108, 100, 101, 114, 32, 110, 111, 100, 101, 58, 32, 91, 49, 51, 51, 55, 93];
-                if len > 24 && buf[..24] == known_malformed_msg_prefix {
+            match protocol::parse(&buf[..len]).and_then(|update| sequence_filter.accept(&update).map(|_| update)) {
+                Ok(update) => {
+                    debug!("received {:?}", update);
+                    let Some((state, velocity)) = state_from_update(&update, *origin_rx.borrow()) else {
+                        continue;
+                    };
+                    tx.send(Ev::Value(state, velocity, received)).unwrap();
+                    to_gui_udp_handler_tx_1.send(position_message(&state)).unwrap();
+                }
+                Err(Rejection::NotFramed) if legacy_position_updates => {
+                    // 'G', latitude, longitude (f64, degrees), altitude (f32, metres), roll, pitch, yaw
+                    // (NED convention), optionally followed by the NED velocity vn, ve, vd
+                    if (len == 33 || len == 45) && buf[0] == b'G' {
+                        let Some(origin) = *origin_rx.borrow() else {
+                            warn!("geodetic position received, but no origin is configured (--origin or [origin] in the scenario)");
+                            continue;
+                        };
+                        let latitude = f64::from_be_bytes(buf[1..9].try_into().unwrap());
+                        let longitude = f64::from_be_bytes(buf[9..17].try_into().unwrap());
+                        let altitude = f32::from_be_bytes(buf[17..21].try_into().unwrap());
+                        let attitude = [0, 1, 2].map(|i| f32::from_be_bytes(buf[21 + 4 * i..25 + 4 * i].try_into().unwrap()));
+                        let velocity = (len == 45).then(|| [0, 1, 2].map(|i| f32::from_be_bytes(buf[33 + 4 * i..37 + 4 * i].try_into().unwrap())));
+                        let (state, velocity) = state_from_geodetic(&origin, &GeodeticPosition::new(latitude, longitude, altitude as f64), attitude, velocity);
+
+                        tx.send(Ev::Value(state, velocity, received)).unwrap();
+                        debug!("received geodetic ([{}, {}, {}], {:?}) -> local {:?}", latitude, longitude, altitude, attitude, state);
+
+                        // the GUI gets the local position in the format of the plain position updates
+                        to_gui_udp_handler_tx_1.send(position_message(&state)).unwrap();
+                    }
+                    // x, y, z, roll, pitch, yaw, optionally followed by the velocity vx, vy, vz
+                    else if len == 24 || len == 36 {
+                        let x = f32::from_be_bytes(buf[0..4].try_into().unwrap());
+                        let y = f32::from_be_bytes(buf[4..8].try_into().unwrap());
+                        let z = f32::from_be_bytes(buf[8..12].try_into().unwrap());
+                        let r_rad = f32::from_be_bytes(buf[12..16].try_into().unwrap());
+                        let p_rad = f32::from_be_bytes(buf[16..20].try_into().unwrap());
+                        let y_rad = f32::from_be_bytes(buf[20..24].try_into().unwrap());
+                        let velocity = (len == 36).then(|| [0, 1, 2].map(|i| f32::from_be_bytes(buf[24 + 4 * i..28 + 4 * i].try_into().unwrap())));
+
+                        tx.send(Ev::Value(State::new(x, y, z, r_rad, p_rad, y_rad), velocity, received)).unwrap();
+                        debug!("received ([{}, {}, {}], [{}, {}, {}], {:?})", x, y, z, r_rad, p_rad, y_rad, velocity);
+
+                        let mut send_buf = buf.to_vec();
+                        // prepend 'P' as message type to distinguish between [P]osition, [T]aps, and [M]ode
+                        send_buf.insert(0_usize, b'P');
+                        to_gui_udp_handler_tx_1.send(send_buf).unwrap();  // TODO
+                    }
+                    else {
+                        // erroneous message contains: b'PowerFolder node: [1337]-[AUTJpBd5EcTPnEtSPDkZ]\x00'
+                        // some external program (PowerFolder, probably connected to HessenBox on some PC in the local network) also uses port 1337 -> ignore this specific message
+                        // there might still arrive other malformed packages -> log for further inspection
+                        let known_malformed_msg_prefix: [u8; 24] = [80, 111, 119, 101, 114, 70, 111, 108, 100, 101, 114, 32, 110, 111, 100, 101, 58, 32, 91, 49, 51, 51, 55, 93];
+                        if len > 24 && buf[..24] == known_malformed_msg_prefix {
+                        }
+                        else {
+                            info!("WARNING 001: received {:?}", &buf[..len]);
+                        }
+                    }
                 }
-                else {
-                    info!("WARNING 001: received {:?}", &buf);
+                // stray packets and reordered or duplicated updates are expected on a shared network
+                Err(rejection @ (Rejection::NotFramed | Rejection::OutOfOrder { .. } | Rejection::OtherUav(_))) => {
+                    debug!("rejected {} bytes from {:?}: {}", len, addr, rejection);
+                }
+                Err(rejection) => {
+                    warn!("rejected position update from {:?}: {}", addr, rejection);
                 }
             }
         }
     });
 
+    // MAVLink telemetry, e.g. of a SITL instance or forwarded from a telemetry radio
+    if let Some(mavlink_udp_port) = config.mavlink_udp_port {
+        let tx = my_tx_2;
+        let to_gui_udp_handler_tx = to_gui_udp_handler_tx_3;
+        let origin_rx = origin_tx.subscribe();
+        let mut tracker = mavlink::Tracker::new(config.mavlink_system_id, config.mavlink_position_source, config.mavlink_local_origin);
+        let source = config.mavlink_position_source;
+        tokio::spawn(async move {
+            info!("spawning MAVLink receiver, listening on port {} for {}", mavlink_udp_port, source);
+            let sock = match UdpSocket::bind(format!("0.0.0.0:{}", mavlink_udp_port)).await {
+                Ok(sock) => sock,
+                Err(e) => {
+                    warn!("MAVLink: cannot listen on port {}, no MAVLink positions ({:?})", mavlink_udp_port, e);
+                    return;
+                }
+            };
+            let mut parser = mavlink::Parser::default();
+            let mut buf = [0; 2048];
+            loop {
+                let (len, addr) = sock.recv_from(&mut buf).await.unwrap();
+                let received = Instant::now();
+                for packet in parser.push(&buf[..len]) {
+                    debug!("MAVLink from {:?}: {:?}", addr, packet);
+                    let Some(update) = tracker.update(&packet) else {
+                        continue;
+                    };
+                    let Some((state, velocity)) = state_from_update(&update, *origin_rx.borrow()) else {
+                        continue;
+                    };
+                    tx.send(Ev::Value(state, velocity, received)).unwrap();
+                    to_gui_udp_handler_tx.send(position_message(&state)).unwrap();
+                }
+            }
+        });
+    }
+
+    // rigid body of a motion-capture system streaming NatNet, e.g. OptiTrack Motive
+    if let Some(natnet_server) = config.natnet_server.clone() {
+        let tx = my_tx_3;
+        let to_gui_udp_handler_tx = to_gui_udp_handler_tx_4;
+        let (command_port, data_port) = (config.natnet_command_port, config.natnet_data_port);
+        // validated by Config::validate, None means unicast
+        let multicast_group = config.natnet_multicast_group();
+        let rigid_body = config.natnet_rigid_body.clone();
+        let transform = natnet::Transform {
+            scale: config.natnet_scale,
+            up_axis: config.natnet_up_axis,
+            origin: config.natnet_origin,
+            yaw: config.natnet_yaw.to_radians(),
+        };
+        tokio::spawn(async move {
+            info!("spawning NatNet client of {} for rigid body '{}' ({})", natnet_server, rigid_body, if multicast_group.is_some() { "multicast" } else { "unicast" });
+            let server = format!("{}:{}", natnet_server, command_port);
+            let (command_sock, data_sock) = match (UdpSocket::bind("0.0.0.0:0").await, UdpSocket::bind(format!("0.0.0.0:{}", data_port)).await) {
+                (Ok(command_sock), Ok(data_sock)) => (command_sock, data_sock),
+                (Err(e), _) | (_, Err(e)) => {
+                    warn!("NatNet: cannot bind the command socket or data port {}, no NatNet positions ({:?})", data_port, e);
+                    return;
+                }
+            };
+            if let Some(multicast_group) = multicast_group {
+                if let Err(e) = data_sock.join_multicast_v4(multicast_group, Ipv4Addr::UNSPECIFIED) {
+                    warn!("NatNet: cannot join multicast group {}, no NatNet positions ({:?})", multicast_group, e);
+                    return;
+                }
+            }
+            let mut version = None;
+            let mut rigid_body_id = None;
+            let mut tracking_lost = false;
+            // (re)connects and requests the model definitions until the rigid body is known, then keeps a unicast stream alive
+            let mut request_interval = tokio::time::interval(Duration::from_secs(1));
+            let mut command_buf = [0; 65536];
+            let mut data_buf = [0; 65536];
+            loop {
+                let (len, from_command_sock) = tokio::select! {
+                    _ = request_interval.tick() => {
+                        let requests = match (version, rigid_body_id) {
+                            (None, _) => vec![natnet::command(natnet::NAT_CONNECT, b"Ping\0")],
+                            (Some(_), None) => vec![natnet::command(natnet::NAT_REQUEST_MODELDEF, &[])],
+                            (Some(_), Some(_)) => vec![natnet::command(natnet::NAT_KEEPALIVE, &[])],
+                        };
+                        for request in requests {
+                            if let Err(e) = command_sock.send_to(&request, &server).await {
+                                warn!("NatNet: error sending request to {} ({:?})", server, e);
+                            }
+                        }
+                        continue;
+                    }
+                    Ok((len, _)) = command_sock.recv_from(&mut command_buf) => (len, true),
+                    Ok((len, _)) = data_sock.recv_from(&mut data_buf) => (len, false),
+                };
+                let received = Instant::now();
+                let packet = if from_command_sock { &command_buf[..len] } else { &data_buf[..len] };
+                let Some((message_id, payload)) = natnet::split(packet) else {
+                    debug!("NatNet: ignored truncated packet of {} bytes", len);
+                    continue;
+                };
+                match (message_id, version) {
+                    (natnet::NAT_SERVERINFO, _) => {
+                        version = natnet::server_version(payload);
+                        info!("NatNet: server speaks version {:?}", version);
+                    }
+                    (natnet::NAT_MODELDEF, Some(version)) => {
+                        let rigid_bodies = natnet::rigid_body_names(payload, version);
+                        rigid_body_id = rigid_bodies
+                            .iter()
+                            .find(|(_, name)| *name == rigid_body)
+                            .map(|&(id, _)| id)
+                            .or_else(|| rigid_body.parse().ok());
+                        match rigid_body_id {
+                            Some(id) => info!("NatNet: rigid body '{}' has id {}", rigid_body, id),
+                            None => warn!("NatNet: no rigid body '{}', available: {:?}", rigid_body, rigid_bodies),
+                        }
+                    }
+                    (natnet::NAT_FRAMEOFDATA, Some(version)) => {
+                        let Some(id) = rigid_body_id else {
+                            continue;
+                        };
+                        let Some(rigid_bodies) = natnet::rigid_bodies(payload, version) else {
+                            debug!("NatNet: ignored malformed frame of {} bytes", len);
+                            continue;
+                        };
+                        let Some(body) = rigid_bodies.iter().find(|body| body.id == id) else {
+                            continue;
+                        };
+                        if !body.tracking_valid {
+                            if !tracking_lost {
+                                warn!("NatNet: tracking of rigid body '{}' lost", rigid_body);
+                            }
+                            tracking_lost = true;
+                            continue;
+                        }
+                        tracking_lost = false;
+                        let state = transform.state(body);
+                        tx.send(Ev::Value(state, None, received)).unwrap();
+                        to_gui_udp_handler_tx.send(position_message(&state)).unwrap();
+                    }
+                    _ => {}
+                }
+            }
+        });
+    }
+
     // udp receiver from gui
+    let model_selection_udp_port = config.model_selection_udp_port;
     tokio::spawn(async move {
-        let sock = UdpSocket::bind(format!("0.0.0.0:{}", args.model_selection_udp_port)).await.unwrap();
+        let sock = UdpSocket::bind(format!("0.0.0.0:{}", model_selection_udp_port)).await.unwrap();
         let mut buf = [0; 1024];
         loop {
             let (len, addr) = sock.recv_from(&mut buf).await.unwrap();
@@ -295,22 +767,7 @@ async fn main() -> io::Result<()> {
                 // let mut received = std::str::from_utf8(&buf[0..1]).unwrap().trim();
                 // let new_pl_model_index = received.parse::<usize>().unwrap();
                 let new_pl_model_index = buf[0] as usize;
-                if new_pl_model_index == MODEL_INDEX_AUTOMATIC_FREE_SPACE {
-                    info!("mode automatic - Free-Space PL");
-                    my_tx_1.send(Ev::ModeAutomaticFreeSpace).unwrap();
-                } else if new_pl_model_index == MODEL_INDEX_AUTOMATIC_FLAT_EARTH_TWO_RAY {
-                    info!("mode automatic - Flat-Earth Two-Ray PL");
-                    my_tx_1.send(Ev::ModeAutomaticFlatEarthTwoRay).unwrap();
-                } else if new_pl_model_index == MODEL_INDEX_AUTOMATIC_CURVED_EARTH_TWO_RAY {
-                    info!("mode automatic - Curved-Earth Two-Ray PL");
-                    my_tx_1.send(Ev::ModeAutomaticCurvedEarthTwoRay).unwrap();
-                } else if new_pl_model_index == MODEL_INDEX_AUTOMATIC_NINE_RAY {
-                    info!("mode automatic - Curved-Earth Two-Ray PL");
-                    my_tx_1.send(Ev::ModeAutomaticNineRay).unwrap();
-                } else {
-                    info!("mode manual {}", -1.);
-                    my_tx_1.send(Ev::ModeManual(-1.)).unwrap();
-                }
+                my_tx_1.send(mode_event(new_pl_model_index, &model_names_gui, None)).unwrap();
                 info!("received new pl_model_index: {}", new_pl_model_index);
             } else if len == 4 {
                 let magic_scaling_coeff_tmp = f32::from_be_bytes(buf[0..4].try_into().unwrap());
@@ -322,12 +779,26 @@ async fn main() -> io::Result<()> {
         }
     });
 
+    // HTTP/JSON API, the main loop publishes its state after every event
+    let (snapshot_tx, snapshot_rx) = watch::channel(api::Snapshot::new(api::models(&models), config.manual_attenuation, config.magic_scaling_coeff));
+    if let Some(http_address) = &config.http_address {
+        // validated with the configuration
+        let http_address = http_address.parse().unwrap();
+        let context = api::Context { tx: my_tx_4, to_gui_tx: to_gui_udp_handler_tx_5, snapshot_rx, model_names: model_names.clone() };
+        tokio::spawn(async move {
+            info!("spawning HTTP API, listening on {}", http_address);
+            if let Err(e) = api::serve(http_address, context).await {
+                warn!("HTTP API stopped ({:?})", e);
+            }
+        });
+    }
+
     // udp sender to gui
+    let gui_address = config.gui_address.clone();
     tokio::spawn(async move {
         let sock_tx_to_gui = UdpSocket::bind("0.0.0.0:0").await.unwrap();
         sock_tx_to_gui
-            // forward to Host (has .1 address of every docker compose network)
-            .connect("172.18.0.1:1342")
+            .connect(gui_address)
             .await
             .unwrap();
         let mut to_gui_udp_handler_rx = to_gui_udp_handler_rx;
@@ -345,111 +816,287 @@ async fn main() -> io::Result<()> {
         }
     });
 
+    // select the default model, or manual mode with its initial attenuation
+    reload_tx.send(mode_event(config.default_mode_index(), &model_names, Some(config.manual_attenuation))).unwrap();
+
+    // reload the configuration on SIGHUP, a configuration that fails to load or validate is ignored
+    let reload_args = args.clone();
+    tokio::spawn(async move {
+        let mut hangups = signal(SignalKind::hangup()).unwrap();
+        while hangups.recv().await.is_some() {
+            match load_config(&reload_args) {
+                Ok((config, scenario)) => reload_tx.send(Ev::Reload(Box::new(config), Box::new(scenario))).unwrap(),
+                Err(e) => warn!("SIGHUP: keeping the current configuration, reloading failed: {}", e),
+            }
+        }
+    });
+
+    let mut tap_grid = TapGrid::new(config.sample_rate as f32, MAX_TAPS).with_kernel(config.tap_kernel, config.sinc_half_width);
     let mut taps = [0i16; MAX_TAPS * 2];
-    let mut pl_model_index = MODEL_INDEX_AUTOMATIC_FREE_SPACE;
-    let mut magic_scaling_coeff: f32 = MAGIC_SCALING_COEFF;
-    let mut last_manual = 50.0_f32;
+    let mut pl_model_index = 0_usize;
+    let mut magic_scaling_coeff: f32 = config.magic_scaling_coeff;
+    let mut last_manual = config.manual_attenuation;
+    let mut velocity_estimator = VelocityEstimator::default();
+    let mut current_velocity = [0.0_f32; 3];
+    let mut fading = FadingProcesses::new(&scenario.fading, seed);
+    let mut fading_updated = Instant::now();
+    // paths of the last position update and when it was received, kept to rotate their phases
+    let mut current_paths: Vec<PropagationPath> = Vec::new();
+    let mut current_paths_received = Instant::now();
+    let mut tap_update_rate = config.tap_update_rate;
+    let mut tap_update_interval = tap_update_timer(tap_update_rate);
+    // whether the last position update had Doppler shifts the tap update rate cannot follow, warned once per episode
+    let mut doppler_aliased = false;
+    // the configuration as last loaded from the files, a reload applies what differs from it
+    let mut loaded_config = config.clone();
+    let mut snapshot = snapshot_tx.borrow().clone();
     loop {
         let mut send = false;
-        if let Some(e) = rx.recv().await {
-            match e {
-                Ev::ModeAutomaticFreeSpace => {
-                    pl_model_index = MODEL_INDEX_AUTOMATIC_FREE_SPACE;
-                    if let Err(e) = mode_channel_gui_to_gamepad_tx.send(MODEL_INDEX_AUTOMATIC_FREE_SPACE) {
-                        warn!("error sending PL model index to gui ({:?})", e);
-                    }
-                },
-                Ev::ModeAutomaticFlatEarthTwoRay => {
-                    pl_model_index = MODEL_INDEX_AUTOMATIC_FLAT_EARTH_TWO_RAY;
-                    if let Err(e) = mode_channel_gui_to_gamepad_tx.send(MODEL_INDEX_AUTOMATIC_FLAT_EARTH_TWO_RAY) {
-                        warn!("error sending PL model index to gui ({:?})", e);
-                    }
-                },
-                Ev::ModeAutomaticCurvedEarthTwoRay => {
-                    pl_model_index = MODEL_INDEX_AUTOMATIC_CURVED_EARTH_TWO_RAY;
-                    if let Err(e) = mode_channel_gui_to_gamepad_tx.send(MODEL_INDEX_AUTOMATIC_CURVED_EARTH_TWO_RAY) {
-                        warn!("error sending PL model index to gui ({:?})", e);
-                    }
-                },
-                Ev::ModeAutomaticNineRay => {
-                    pl_model_index = MODEL_INDEX_AUTOMATIC_NINE_RAY;
-                    if let Err(e) = mode_channel_gui_to_gamepad_tx.send(MODEL_INDEX_AUTOMATIC_NINE_RAY) {
-                        warn!("error sending PL model index to gui ({:?})", e);
-                    }
-                },
-                Ev::ModeManual(v) => {
-                    pl_model_index = MODEL_INDEX_MANUAL;
-                    if let Err(e) = mode_channel_gui_to_gamepad_tx.send(MODEL_INDEX_MANUAL) {
-                        warn!("error sending PL model index to gui ({:?})", e);
+        let tap_updates_active = tap_update_rate > 0. && ((fading.is_enabled() && !current_paths.is_empty()) || current_paths.iter().any(|path| path.doppler != 0.));
+        tokio::select! {
+            Some(e) = rx.recv() => {
+                if matches!(e, Ev::ModeAutomatic(_) | Ev::ModeManual(_)) {
+                    if let Err(e) = to_gui_udp_handler_tx_2.send(names_message(&mode_names(&model_names))) {
+                        warn!("error sending model names to gui ({:?})", e);
                     }
-                    if v >= 0. {
-                        last_manual = v;
-                    }
-                    taps.fill(0);
-                    let tap: Complex32 = TAP_VALUE_NO_LOSS / 10.0_f32.powf(last_manual / 20.0_f32) * magic_scaling_coeff;
-                    let tap = (tap.re as i16).clamp(TAP_VALUE_MIN, TAP_VALUE_MAX);
-                    taps[0] = tap;
-                    // taps[MAX_TAPS] = tap;
-                    send = true;
-                },
-                Ev::Value(x, y, z, _r_rad, _p_rad, _y_rad) => {
-                    if !(pl_model_index == MODEL_INDEX_MANUAL) {
+                }
+                match e {
+                    Ev::ModeAutomatic(model_index) => {
+                        if model_index != pl_model_index {
+                            models[model_index].reset();
+                            fading.reset();
+                            current_paths.clear();
+                            snapshot.paths.clear();
+                        }
+                        pl_model_index = model_index;
+                        if let Err(e) = mode_channel_gui_to_gamepad_tx.send(mode_index(Some(model_index))) {
+                            warn!("error sending PL model index to gui ({:?})", e);
+                        }
+                    },
+                    Ev::ModeManual(v) => {
+                        pl_model_index = registry_index_manual;
+                        current_paths.clear();
+                        if let Err(e) = mode_channel_gui_to_gamepad_tx.send(MODEL_INDEX_MANUAL) {
+                            warn!("error sending PL model index to gui ({:?})", e);
+                        }
+                        if let Some(v) = v {
+                            last_manual = v;
+                        }
+                        snapshot.manual_attenuation = last_manual;
+                        snapshot.paths.clear();
+                        // a single undelayed path, placed like the model paths so the latency does not change with the mode
+                        let path = PropagationPath::new(PathKind::LineOfSight, 10.0_f32.powf(last_manual / 20.0_f32), 0., 0.);
+                        taps = convert_paths_to_taps(vec![path], scenario.frequency, &tap_grid, magic_scaling_coeff);
                         send = true;
-                        let dist = distance(x, y, z);
-                        let paths: Vec<(f32, f32, f32)> = if dist < FAR_FIELD_DISTANCE {
-                            vec![(1., 0., 0.)]
-                        } else {
-                            if pl_model_index == MODEL_INDEX_AUTOMATIC_FLAT_EARTH_TWO_RAY {
-                                calculate_paths_two_ray(x, y, z)
-                            } else if pl_model_index == MODEL_INDEX_AUTOMATIC_CURVED_EARTH_TWO_RAY {
-                                calculate_paths_ce2r(x, y, z)
-                            } else if pl_model_index == MODEL_INDEX_AUTOMATIC_NINE_RAY {
-                                calculate_paths_9ray_suburban(x, y, z)
-                            } else if pl_model_index == MODEL_INDEX_AUTOMATIC_FREE_SPACE {
-                                calculate_paths_freespace(x, y, z)
+                    },
+                    Ev::Value(state, velocity, received) => {
+                        let estimated_velocity = velocity_estimator.update([state.x, state.y, state.z], received);
+                        let state = state.with_velocity(velocity.unwrap_or(estimated_velocity));
+                        current_velocity = state.velocity;
+                        snapshot.position = Some(api::Position::new(&state));
+                        snapshot.counters.position_updates += 1;
+                        if let Some(model) = models.get_mut(pl_model_index) {
+                            send = true;
+                            let dist = scenario.distance(state.x, state.y, state.z);
+                            current_paths = if dist < scenario.far_field_distance() {
+                                vec![PropagationPath::new(PathKind::LineOfSight, 1., 0., 0.)]
                             } else {
-                                panic!("invalid pl_model_index: {}", pl_model_index)
+                                model.paths(&state)
+                            };
+                            current_paths_received = received;
+                            snapshot.paths = current_paths.iter().map(api::PathSummary::from).collect();
+                            let doppler = max_doppler(&current_paths, fading.is_enabled().then(|| scenario.fading.max_doppler_for(&scenario, current_velocity)));
+                            let aliased = tap_update_rate > 0. && 2. * doppler > tap_update_rate;
+                            if aliased && !doppler_aliased {
+                                warn!("Doppler shifts up to {:.0} Hz alias at a tap update rate of {} Hz, set tap_update_rate to at least {:.0} Hz", doppler, tap_update_rate, (2. * doppler).ceil());
+                            }
+                            doppler_aliased = aliased;
+                            // println!("{:?}", &current_paths);
+                            advance_fading(&mut fading, &mut fading_updated, scenario.fading.max_doppler_for(&scenario, current_velocity));
+                            let paths = fading.apply(&advance_paths(&current_paths, received.elapsed()));
+                            let statistics = ImpulseResponse::from_paths(&paths, scenario.frequency).statistics(config.sample_rate as f32);
+                            debug!("channel: {}", statistics);
+                            snapshot.statistics = Some(api::Statistics::from(&statistics));
+                            if let Err(e) = to_gui_udp_handler_tx_2.send(statistics_message(&statistics)) {
+                                warn!("error sending channel statistics to gui ({:?})", e);
+                            }
+                            taps = convert_paths_to_taps(paths, scenario.frequency, &tap_grid, magic_scaling_coeff);
+                        }
+                    },
+                    Ev::ScalingCoeff(v) => {
+                        magic_scaling_coeff = v;
+                    }
+                    Ev::Reload(new_config, new_scenario) => {
+                        let restart_required = new_config.restart_required(&config);
+                        if !restart_required.is_empty() {
+                            warn!("SIGHUP: changes of {:?} take effect after a restart", restart_required);
+                        }
+                        let mut new_scenario = *new_scenario;
+                        // keep the seed chosen at startup if none is configured
+                        new_scenario.seed = new_scenario.seed.or(scenario.seed);
+                        match new_config.create_models(&new_scenario) {
+                            Ok(new_models) => {
+                                // only what changed in the files is applied, values set at runtime via GUI, gamepad or API stay
+                                let replaced = reload_models(&mut models, new_models, (&new_scenario, &scenario), (&new_config, &loaded_config));
+                                info!("SIGHUP: reloaded configuration: {:?}", new_config);
+                                info!("Scenario: {:?}", new_scenario);
+                                info!("SIGHUP: rebuilt models {:?}", replaced);
+                                if matches!(model_names.get(pl_model_index), Some(name) if replaced.contains(name)) {
+                                    current_paths.clear();
+                                    snapshot.paths.clear();
+                                }
+                                if new_scenario.fading != scenario.fading || new_scenario.seed != scenario.seed {
+                                    fading = FadingProcesses::new(&new_scenario.fading, new_scenario.seed.unwrap_or(seed));
+                                }
+                                if new_scenario.origin != scenario.origin {
+                                    origin_tx.send_replace(new_scenario.origin);
+                                }
+                                scenario = new_scenario;
+                                tap_grid = tap_grid.with_kernel(new_config.tap_kernel, new_config.sinc_half_width);
+                                if new_config.magic_scaling_coeff != loaded_config.magic_scaling_coeff {
+                                    magic_scaling_coeff = new_config.magic_scaling_coeff;
+                                }
+                                if new_config.tap_update_rate != tap_update_rate {
+                                    tap_update_rate = new_config.tap_update_rate;
+                                    tap_update_interval = tap_update_timer(tap_update_rate);
+                                }
+                                if new_config.manual_attenuation != loaded_config.manual_attenuation {
+                                    last_manual = new_config.manual_attenuation;
+                                    snapshot.manual_attenuation = last_manual;
+                                }
+                                if pl_model_index == registry_index_manual {
+                                    let path = PropagationPath::new(PathKind::LineOfSight, 10.0_f32.powf(last_manual / 20.0_f32), 0., 0.);
+                                    taps = convert_paths_to_taps(vec![path], scenario.frequency, &tap_grid, magic_scaling_coeff);
+                                    send = true;
+                                }
+                                loaded_config = *new_config;
+                                snapshot.models = api::models(&models);
+                                snapshot.counters.reloads += 1;
                             }
+                            Err(e) => warn!("SIGHUP: keeping the current configuration, reloading failed: {}", e),
+                        }
+                    }
+                    Ev::ModelParameters(name, parameters, reply) => {
+                        let result = match models.iter_mut().find(|model| model.name() == name) {
+                            Some(model) => parameters.iter().try_for_each(|(parameter, &value)| model.set_parameter(parameter, value).map_err(|e| e.to_string())),
+                            None => Err(format!("unknown model '{}'", name)),
                         };
-                        // println!("{:?}", &paths);
-                        taps = convert_paths_to_taps(paths, args.sample_rate as f32, magic_scaling_coeff);
+                        match &result {
+                            Ok(()) => info!("API: set parameters of {}: {:?}", name, parameters),
+                            Err(e) => warn!("API: setting parameters of {} failed: {}", name, e),
+                        }
+                        snapshot.models = api::models(&models);
+                        // the API handler may have given up waiting
+                        let _ = reply.send(result);
                     }
-                },
-                Ev::ScalingCoeff(v) => {
-                    magic_scaling_coeff = v;
                 }
             }
+            _ = tap_update_interval.tick(), if tap_updates_active => {
+                advance_fading(&mut fading, &mut fading_updated, scenario.fading.max_doppler_for(&scenario, current_velocity));
+                let paths = fading.apply(&advance_paths(&current_paths, current_paths_received.elapsed()));
+                taps = convert_paths_to_taps(paths, scenario.frequency, &tap_grid, magic_scaling_coeff);
+                send = true;
+            }
+        }
 
-            if send {
-                match sock_tx
-                    .send(
-                        &taps
-                            .iter()
-                            .flat_map(|v| v.to_be_bytes())
-                            .collect::<Vec<u8>>(),
-                    )
-                    .await
-                {
-                    Ok(l) => {
-                        if l != 2 * 2 * MAX_TAPS {
-                            panic!("error sending all taps (bytes sent {})", l);
-                        }
-                    }
-                    Err(e) => {
-                        warn!("error sending taps ({:?})", e);
+        if send {
+            match sock_tx
+                .send(
+                    &taps
+                        .iter()
+                        .flat_map(|v| v.to_be_bytes())
+                        .collect::<Vec<u8>>(),
+                )
+                .await
+            {
+                Ok(l) => {
+                    if l != 2 * 2 * MAX_TAPS {
+                        panic!("error sending all taps (bytes sent {})", l);
                     }
+                    snapshot.counters.tap_updates += 1;
                 }
-                let mut send_buf = taps
-                    .iter()
-                    .flat_map(|v| v.to_be_bytes())
-                    .collect::<Vec<u8>>();
-                // prepend 'T' as message type to distinguish between [P]osition, [T]aps, and [M]ode
-                send_buf.insert(0_usize, b'T');
-                if let Err(e) = to_gui_udp_handler_tx_2.send(send_buf.clone()) {
-                    warn!("error sending Filter Taps to gui ({:?})", e);
+                Err(e) => {
+                    warn!("error sending taps ({:?})", e);
+                    snapshot.counters.tap_send_errors += 1;
                 }
-                debug!("sent message to handler: {:?}", send_buf);
             }
+            let mut send_buf = taps
+                .iter()
+                .flat_map(|v| v.to_be_bytes())
+                .collect::<Vec<u8>>();
+            // prepend 'T' as message type to distinguish between [P]osition, [T]aps, and [M]ode
+            send_buf.insert(0_usize, b'T');
+            if let Err(e) = to_gui_udp_handler_tx_2.send(send_buf.clone()) {
+                warn!("error sending Filter Taps to gui ({:?})", e);
+            }
+            debug!("sent message to handler: {:?}", send_buf);
+            snapshot.set_taps(&taps);
+        }
+        snapshot.set_mode(models.get(pl_model_index).map(|_| pl_model_index));
+        snapshot.magic_scaling_coeff = magic_scaling_coeff;
+        snapshot_tx.send_replace(snapshot.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mode_index_four_stays_manual() {
+        let names = channel_models::model_names();
+        assert_eq!(model_index(3, names.len()), Some(3));
+        assert_eq!(model_index(MODEL_INDEX_MANUAL, names.len()), None);
+        assert_eq!(model_index(5, names.len()), Some(4));
+        assert_eq!(model_index(names.len() + 1, names.len()), None);
+        for registry_index in 0..names.len() {
+            assert_eq!(model_index(mode_index(Some(registry_index)), names.len()), Some(registry_index));
         }
+        let modes = mode_names(&names);
+        assert_eq!(modes.len(), names.len() + 1);
+        assert_eq!(modes[MODEL_INDEX_MANUAL], MANUAL_MODE);
+    }
+
+    #[test]
+    fn reload_keeps_models_and_runtime_parameters() {
+        let previous_config = Config::default();
+        let previous_scenario = Scenario::default();
+        let mut models = previous_config.create_models(&previous_scenario).unwrap();
+        models[2].set_parameter("ground_roughness", 0.25).unwrap();
+
+        let mut config = Config::default();
+        config.model_parameters.insert("curved_earth_two_ray".to_string(), HashMap::from([("ground_permittivity".to_string(), 5.0)]));
+        let replaced = reload_models(&mut models, config.create_models(&previous_scenario).unwrap(), (&previous_scenario, &previous_scenario), (&config, &previous_config));
+        assert!(replaced.is_empty());
+        let parameter = |model: &dyn ChannelModel, name: &str| model.parameters().into_iter().find(|(parameter, _)| *parameter == name).unwrap().1;
+        assert_eq!(parameter(models[2].as_ref(), "ground_permittivity"), 5.0);
+        assert_eq!(parameter(models[2].as_ref(), "ground_roughness"), 0.25);
+
+        let scenario = Scenario { environment: channel_models::Environment { additional_loss_mean: 20.0, ..Default::default() }, ..Scenario::default() };
+        let replaced = reload_models(&mut models, config.create_models(&scenario).unwrap(), (&scenario, &previous_scenario), (&config, &config));
+        assert_eq!(replaced, vec!["nine_ray_suburban"]);
+        assert_eq!(parameter(models[2].as_ref(), "ground_roughness"), 0.25);
+
+        let replaced = reload_models(&mut models, previous_config.create_models(&scenario).unwrap(), (&scenario, &scenario), (&previous_config, &config));
+        assert!(replaced.is_empty());
+        assert_eq!(parameter(models[2].as_ref(), "ground_permittivity"), Scenario::default().ground_permittivity);
+    }
+
+    #[test]
+    fn max_doppler_covers_paths_and_fading() {
+        let path = |doppler: f32| PropagationPath { doppler, ..PropagationPath::new(PathKind::LineOfSight, 1., 0., 0.) };
+        let paths = [path(120.), path(-163.), path(0.)];
+        assert_eq!(max_doppler(&paths, None), 163.);
+        assert_eq!(max_doppler(&paths, Some(200.)), 200.);
+        assert_eq!(max_doppler(&[], None), 0.);
+    }
+
+    #[test]
+    fn statistics_message_layout() {
+        let statistics = ChannelStatistics { power: 1e-6, mean_delay: 5e-9, rms_delay_spread: 2e-9, k_factor: f32::INFINITY, coherence_bandwidth: 3e6 };
+        let message = statistics_message(&statistics);
+        assert_eq!((message[0], message.len()), (b'S', 21));
+        let values: Vec<f32> = message[1..].chunks_exact(4).map(|bytes| f32::from_be_bytes(bytes.try_into().unwrap())).collect();
+        assert_eq!(values, vec![1e-6, 5e-9, 2e-9, f32::INFINITY, 3e6]);
     }
 }
@@ -0,0 +1,371 @@
+//! Decoder of the MAVLink v1/v2 messages that carry the position and attitude of PX4 and
+//! ArduPilot vehicles: GLOBAL_POSITION_INT, LOCAL_POSITION_NED and ATTITUDE.
+
+use log::info;
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::protocol::{Frame, PositionUpdate};
+use channel_models::{attitude_from_ned, vector_from_ned};
+
+const STX_V1: u8 = 0xFE;
+const STX_V2: u8 = 0xFD;
+const HEADER_LENGTH_V1: usize = 6;
+const HEADER_LENGTH_V2: usize = 10;
+const CHECKSUM_LENGTH: usize = 2;
+const SIGNATURE_LENGTH: usize = 13;
+/// incompatibility flag of MAVLink v2 frames followed by a signature
+const INCOMPAT_FLAG_SIGNED: u8 = 0x01;
+
+pub const MSG_ID_ATTITUDE: u32 = 30;
+pub const MSG_ID_LOCAL_POSITION_NED: u32 = 32;
+pub const MSG_ID_GLOBAL_POSITION_INT: u32 = 33;
+
+/// CRC extra and payload length of the decoded messages
+fn message_info(message_id: u32) -> Option<(u8, usize)> {
+    match message_id {
+        MSG_ID_ATTITUDE => Some((39, 28)),
+        MSG_ID_LOCAL_POSITION_NED => Some((185, 28)),
+        MSG_ID_GLOBAL_POSITION_INT => Some((104, 28)),
+        _ => None,
+    }
+}
+
+/// X.25 (CRC-16/MCRF4XX) checksum as used by MAVLink, continuing from `crc`
+fn accumulate_crc(mut crc: u16, data: &[u8]) -> u16 {
+    for &byte in data {
+        let mut tmp = byte ^ (crc & 0xFF) as u8;
+        tmp ^= tmp << 4;
+        let tmp = tmp as u16;
+        crc = (crc >> 8) ^ (tmp << 8) ^ (tmp << 3) ^ (tmp >> 4);
+    }
+    crc
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    /// roll, pitch and yaw in radians, NED convention
+    Attitude { time_boot_ms: u32, attitude: [f32; 3] },
+    /// latitude and longitude in degrees, altitude above mean sea level in metres and the
+    /// north, east, down velocity in m/s
+    GlobalPositionInt { time_boot_ms: u32, latitude: f64, longitude: f64, altitude: f64, velocity: [f32; 3] },
+    /// north, east, down position in metres relative to the local origin of the autopilot and
+    /// velocity in m/s
+    LocalPositionNed { time_boot_ms: u32, position: [f32; 3], velocity: [f32; 3] },
+}
+
+impl Message {
+    /// `payload` zero-extended to the full length of the message
+    fn decode(message_id: u32, payload: &[u8]) -> Option<Message> {
+        let u32_at = |offset: usize| u32::from_le_bytes(payload[offset..offset + 4].try_into().unwrap());
+        let i32_at = |offset: usize| i32::from_le_bytes(payload[offset..offset + 4].try_into().unwrap());
+        let i16_at = |offset: usize| i16::from_le_bytes(payload[offset..offset + 2].try_into().unwrap());
+        let f32_at = |offset: usize| f32::from_le_bytes(payload[offset..offset + 4].try_into().unwrap());
+        match message_id {
+            MSG_ID_ATTITUDE => Some(Message::Attitude { time_boot_ms: u32_at(0), attitude: [f32_at(4), f32_at(8), f32_at(12)] }),
+            MSG_ID_LOCAL_POSITION_NED => Some(Message::LocalPositionNed {
+                time_boot_ms: u32_at(0),
+                position: [f32_at(4), f32_at(8), f32_at(12)],
+                velocity: [f32_at(16), f32_at(20), f32_at(24)],
+            }),
+            MSG_ID_GLOBAL_POSITION_INT => Some(Message::GlobalPositionInt {
+                time_boot_ms: u32_at(0),
+                latitude: i32_at(4) as f64 * 1e-7,
+                longitude: i32_at(8) as f64 * 1e-7,
+                altitude: i32_at(12) as f64 * 1e-3,
+                velocity: [i16_at(20), i16_at(22), i16_at(24)].map(|v| v as f32 * 1e-2),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Packet {
+    pub sequence: u8,
+    pub system_id: u8,
+    pub component_id: u8,
+    pub message: Message,
+}
+
+/// Splits a byte stream into MAVLink frames. Bytes that do not form a frame with a valid
+/// checksum are skipped, frames of other messages are dropped, and an incomplete frame at
+/// the end is kept until the rest arrives, so recorded logs can be streamed in in any chunks.
+#[derive(Debug, Default)]
+pub struct Parser {
+    buffer: Vec<u8>,
+}
+
+impl Parser {
+    pub fn push(&mut self, data: &[u8]) -> Vec<Packet> {
+        self.buffer.extend_from_slice(data);
+        let mut packets = Vec::new();
+        let mut start = 0;
+        // start of an incomplete frame at the end of the buffer
+        let mut incomplete = None;
+        while let Some(offset) = self.buffer[start..].iter().position(|&byte| byte == STX_V1 || byte == STX_V2) {
+            start += offset;
+            let frame = &self.buffer[start..];
+            let v2 = frame[0] == STX_V2;
+            let header_length = if v2 { HEADER_LENGTH_V2 } else { HEADER_LENGTH_V1 };
+            if frame.len() < header_length {
+                incomplete = Some(start);
+                break;
+            }
+            let payload_length = frame[1] as usize;
+            let signature_length = if v2 && frame[2] & INCOMPAT_FLAG_SIGNED != 0 { SIGNATURE_LENGTH } else { 0 };
+            let frame_length = header_length + payload_length + CHECKSUM_LENGTH + signature_length;
+            if frame.len() < frame_length {
+                incomplete = Some(start);
+                break;
+            }
+            let (sequence, system_id, component_id, message_id) = if v2 {
+                (frame[4], frame[5], frame[6], u32::from_le_bytes([frame[7], frame[8], frame[9], 0]))
+            } else {
+                (frame[2], frame[3], frame[4], frame[5] as u32)
+            };
+            let Some((crc_extra, message_length)) = message_info(message_id) else {
+                // the checksum of unknown messages cannot be verified, skip the frame as announced
+                start += frame_length;
+                continue;
+            };
+            let checksum_offset = header_length + payload_length;
+            let crc = accumulate_crc(accumulate_crc(0xFFFF, &frame[1..checksum_offset]), &[crc_extra]);
+            if crc != u16::from_le_bytes([frame[checksum_offset], frame[checksum_offset + 1]]) || payload_length > message_length || (!v2 && payload_length != message_length) {
+                // not a frame after all, resynchronize at the next start byte
+                start += 1;
+                continue;
+            }
+            // MAVLink v2 truncates trailing zeros of the payload
+            let mut payload = frame[header_length..checksum_offset].to_vec();
+            payload.resize(message_length, 0);
+            if let Some(message) = Message::decode(message_id, &payload) {
+                packets.push(Packet { sequence, system_id, component_id, message });
+            }
+            start += frame_length;
+        }
+        // keep an incomplete frame, drop everything before it
+        self.buffer.drain(..incomplete.unwrap_or(self.buffer.len()));
+        packets
+    }
+}
+
+/// the MAVLink message the position of the UAV is taken from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionSource {
+    /// WGS84 position from GPS and EKF, needs the geodetic origin of the local frame
+    #[default]
+    GlobalPositionInt,
+    /// position relative to the local origin of the autopilot, see `Tracker::new`
+    LocalPositionNed,
+}
+
+impl FromStr for PositionSource {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "global_position_int" => Ok(PositionSource::GlobalPositionInt),
+            "local_position_ned" => Ok(PositionSource::LocalPositionNed),
+            _ => Err(format!("unknown position source '{}', available: global_position_int, local_position_ned", name)),
+        }
+    }
+}
+
+impl fmt::Display for PositionSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PositionSource::GlobalPositionInt => f.write_str("global_position_int"),
+            PositionSource::LocalPositionNed => f.write_str("local_position_ned"),
+        }
+    }
+}
+
+/// Combines the position messages of one vehicle with its latest attitude into position
+/// updates.
+#[derive(Debug)]
+pub struct Tracker {
+    system_id: Option<u8>,
+    source: PositionSource,
+    local_origin: [f32; 3],
+    /// latest attitude, NED convention
+    attitude: [f32; 3],
+}
+
+impl Tracker {
+    /// Follows the vehicle with `system_id`, or the first one that sends a position or attitude
+    /// if None. `local_origin` is the position (x east, y north, z up) of the local origin of
+    /// the autopilot in the local frame of the emulator, used with LOCAL_POSITION_NED.
+    pub fn new(system_id: Option<u8>, source: PositionSource, local_origin: [f32; 3]) -> Self {
+        Tracker { system_id, source, local_origin, attitude: [0.0; 3] }
+    }
+
+    /// the position update of the packet, if it is a position message of the followed vehicle
+    /// from the configured source
+    pub fn update(&mut self, packet: &Packet) -> Option<PositionUpdate> {
+        match self.system_id {
+            Some(system_id) if system_id != packet.system_id => return None,
+            Some(_) => {}
+            None => {
+                info!("MAVLink: following system {}", packet.system_id);
+                self.system_id = Some(packet.system_id);
+            }
+        }
+        let update = |timestamp: u32, frame: Frame, position: [f64; 3], attitude: [f32; 3], velocity: [f32; 3]| PositionUpdate {
+            uav_id: packet.system_id as u16,
+            sequence: packet.sequence as u32,
+            timestamp: timestamp as u64 * 1000,
+            frame,
+            position,
+            attitude,
+            velocity: Some(velocity),
+        };
+        match packet.message {
+            Message::Attitude { attitude, .. } => {
+                self.attitude = attitude;
+                None
+            }
+            Message::GlobalPositionInt { time_boot_ms, latitude, longitude, altitude, velocity } if self.source == PositionSource::GlobalPositionInt => {
+                Some(update(time_boot_ms, Frame::Geodetic, [latitude, longitude, altitude], self.attitude, velocity))
+            }
+            Message::LocalPositionNed { time_boot_ms, position, velocity } if self.source == PositionSource::LocalPositionNed => {
+                let position = vector_from_ned(position);
+                let position = [0, 1, 2].map(|i| (self.local_origin[i] + position[i]) as f64);
+                let (roll, pitch, yaw) = attitude_from_ned(self.attitude[0], self.attitude[1], self.attitude[2]);
+                Some(update(time_boot_ms, Frame::Local, position, [roll, pitch, yaw], vector_from_ned(velocity)))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Frames encoded from the MAVLink message definitions by an independent implementation
+    // of the framing (not by this parser): system 1, component 1 unless noted.
+    /// v1, sequence 7: time_boot_ms 123456, lat 50.1109221, lon 8.6821267, alt 120.5 m,
+    /// relative_alt 20 m, vx 1.5, vy -2.3, vz 0.1 m/s, hdg 90°
+    const V1_GLOBAL_POSITION_INT: &str = "fe1c0701012140e20100e551de1d93c92c05b4d60100204e000096001aff0a002823e27c";
+    /// v2, sequence 8: time_boot_ms 123400, roll 0.1, pitch -0.05, yaw 1.2, zero rates, so
+    /// the trailing 12 payload bytes are truncated
+    const V2_ATTITUDE: &str = "fd1000000801011e000008e20100cdcccc3dcdcc4cbd9a99993f9ee6";
+    /// v2, sequence 9: time_boot_ms 123500, x 10, y -5, z -30 m, vx 1, vy 2, vz -0.5 m/s
+    const V2_LOCAL_POSITION_NED: &str = "fd1c00000901012000006ce20100000020410000a0c00000f0c10000803f00000040000000bf0ec6";
+    /// v2 HEARTBEAT, sequence 10, not decoded
+    const V2_HEARTBEAT: &str = "fd0900000a010100000000000000020c510403ee30";
+    /// v2, system 2, sequence 3, same payload as V1_GLOBAL_POSITION_INT
+    const V2_GLOBAL_POSITION_INT_SYSTEM_2: &str = "fd1c000003020121000040e20100e551de1d93c92c05b4d60100204e000096001aff0a002823f53c";
+
+    fn bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn global_position_int() -> Message {
+        Message::GlobalPositionInt {
+            time_boot_ms: 123456,
+            latitude: 501109221_f64 * 1e-7,
+            longitude: 86821267_f64 * 1e-7,
+            altitude: 120500_f64 * 1e-3,
+            velocity: [150_i16, -230, 10].map(|v| v as f32 * 1e-2),
+        }
+    }
+
+    fn attitude() -> Message {
+        Message::Attitude { time_boot_ms: 123400, attitude: [0.1, -0.05, 1.2] }
+    }
+
+    fn packet(sequence: u8, system_id: u8, message: Message) -> Packet {
+        Packet { sequence, system_id, component_id: 1, message }
+    }
+
+    #[test]
+    fn crc_check_value() {
+        assert_eq!(accumulate_crc(0xFFFF, b"123456789"), 0x6F91);
+    }
+
+    #[test]
+    fn decodes_v1_frame() {
+        assert_eq!(Parser::default().push(&bytes(V1_GLOBAL_POSITION_INT)), vec![packet(7, 1, global_position_int())]);
+    }
+
+    #[test]
+    fn decodes_v2_frames_with_truncated_payload() {
+        let mut stream = bytes(V2_ATTITUDE);
+        stream.extend(bytes(V2_LOCAL_POSITION_NED));
+        let local_position = Message::LocalPositionNed { time_boot_ms: 123500, position: [10.0, -5.0, -30.0], velocity: [1.0, 2.0, -0.5] };
+        assert_eq!(Parser::default().push(&stream), vec![packet(8, 1, attitude()), packet(9, 1, local_position)]);
+    }
+
+    #[test]
+    fn wrong_crc_is_skipped_and_the_parser_resynchronizes() {
+        let mut corrupted = bytes(V2_ATTITUDE);
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0x01;
+        let mut corrupted_payload = bytes(V1_GLOBAL_POSITION_INT);
+        corrupted_payload[10] ^= 0x40;
+        let mut stream = vec![0x00, 0x42, 0x13];
+        stream.extend(corrupted);
+        stream.extend(corrupted_payload);
+        stream.extend(bytes(V1_GLOBAL_POSITION_INT));
+        assert_eq!(Parser::default().push(&stream), vec![packet(7, 1, global_position_int())]);
+    }
+
+    #[test]
+    fn frames_split_across_chunks_and_unknown_messages() {
+        let mut stream = bytes(V2_HEARTBEAT);
+        stream.extend(bytes(V2_ATTITUDE));
+        stream.extend(bytes(V1_GLOBAL_POSITION_INT));
+        let mut parser = Parser::default();
+        let mut packets = Vec::new();
+        for chunk in stream.chunks(5) {
+            packets.extend(parser.push(chunk));
+        }
+        assert_eq!(packets, vec![packet(8, 1, attitude()), packet(7, 1, global_position_int())]);
+        // a frame cut off inside its payload waits for the rest
+        let frame = bytes(V2_ATTITUDE);
+        assert!(parser.push(&frame[..12]).is_empty());
+        assert_eq!(parser.push(&frame[12..]), vec![packet(8, 1, attitude())]);
+    }
+
+    #[test]
+    fn tracker_follows_the_configured_system() {
+        let mut parser = Parser::default();
+        let other = parser.push(&bytes(V2_GLOBAL_POSITION_INT_SYSTEM_2));
+        let own = parser.push(&bytes(V1_GLOBAL_POSITION_INT));
+        let mut tracker = Tracker::new(Some(1), PositionSource::GlobalPositionInt, [0.0; 3]);
+        assert_eq!(tracker.update(&other[0]), None);
+        let update = tracker.update(&own[0]).unwrap();
+        assert_eq!((update.uav_id, update.sequence, update.timestamp, update.frame), (1, 7, 123456000, Frame::Geodetic));
+        assert_eq!(update.position, [501109221_f64 * 1e-7, 86821267_f64 * 1e-7, 120.5]);
+    }
+
+    #[test]
+    fn tracker_locks_onto_the_first_system() {
+        let mut parser = Parser::default();
+        let first = parser.push(&bytes(V2_GLOBAL_POSITION_INT_SYSTEM_2));
+        let second = parser.push(&bytes(V1_GLOBAL_POSITION_INT));
+        let mut tracker = Tracker::new(None, PositionSource::GlobalPositionInt, [0.0; 3]);
+        assert_eq!(tracker.update(&first[0]).map(|update| update.uav_id), Some(2));
+        assert_eq!(tracker.update(&second[0]), None);
+    }
+
+    #[test]
+    fn tracker_combines_local_position_and_attitude() {
+        let mut stream = bytes(V2_ATTITUDE);
+        stream.extend(bytes(V2_LOCAL_POSITION_NED));
+        stream.extend(bytes(V1_GLOBAL_POSITION_INT));
+        let packets = Parser::default().push(&stream);
+        let mut tracker = Tracker::new(None, PositionSource::LocalPositionNed, [100.0, 200.0, 0.0]);
+        let updates: Vec<PositionUpdate> = packets.iter().filter_map(|packet| tracker.update(packet)).collect();
+        assert_eq!(updates.len(), 1);
+        let (roll, pitch, yaw) = attitude_from_ned(0.1, -0.05, 1.2);
+        assert_eq!(updates[0].frame, Frame::Local);
+        assert_eq!(updates[0].position, [95.0, 210.0, 30.0]);
+        assert_eq!(updates[0].attitude, [roll, pitch, yaw]);
+        assert_eq!(updates[0].velocity, Some([2.0, 1.0, 0.5]));
+    }
+}
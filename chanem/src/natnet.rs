@@ -0,0 +1,339 @@
+//! Client of the NatNet protocol with which OptiTrack Motive streams motion-capture data.
+//! Only rigid bodies are decoded; marker sets and skeletons are skipped.
+
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+use channel_models::State;
+
+pub const NAT_CONNECT: u16 = 0;
+pub const NAT_SERVERINFO: u16 = 1;
+pub const NAT_REQUEST_MODELDEF: u16 = 4;
+pub const NAT_MODELDEF: u16 = 5;
+pub const NAT_FRAMEOFDATA: u16 = 7;
+pub const NAT_KEEPALIVE: u16 = 10;
+
+const DATASET_MARKER_SET: i32 = 0;
+const DATASET_RIGID_BODY: i32 = 1;
+const DATASET_SKELETON: i32 = 2;
+/// bytes of a rigid body in a frame of data in the shortest layout: id, position, orientation
+const RIGID_BODY_MIN_SIZE: usize = 32;
+
+/// NatNet version of the server as (major, minor), the layout of the packets depends on it
+pub type Version = (u8, u8);
+
+/// command packet: message id, payload length, payload
+pub fn command(message_id: u16, payload: &[u8]) -> Vec<u8> {
+    let mut packet = message_id.to_le_bytes().to_vec();
+    packet.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+    packet.extend_from_slice(payload);
+    packet
+}
+
+/// message id and payload of a received packet
+pub fn split(packet: &[u8]) -> Option<(u16, &[u8])> {
+    let message_id = u16::from_le_bytes(packet.get(0..2)?.try_into().unwrap());
+    let length = u16::from_le_bytes(packet.get(2..4)?.try_into().unwrap()) as usize;
+    Some((message_id, packet.get(4..4 + length)?))
+}
+
+/// little-endian reader that fails on truncated packets instead of panicking
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, offset: 0 }
+    }
+
+    fn bytes(&mut self, length: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.offset..self.offset + length)?;
+        self.offset += length;
+        Some(bytes)
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.offset)
+    }
+
+    fn skip(&mut self, length: usize) -> Option<()> {
+        self.bytes(length).map(|_| ())
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        self.bytes(4).map(|bytes| i32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        self.bytes(2).map(|bytes| i16::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        self.bytes(4).map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn count(&mut self) -> Option<usize> {
+        usize::try_from(self.i32()?).ok()
+    }
+
+    /// null-terminated string
+    fn string(&mut self) -> Option<String> {
+        let length = self.data.get(self.offset..)?.iter().position(|&byte| byte == 0)?;
+        let string = String::from_utf8_lossy(self.bytes(length)?).into_owned();
+        self.skip(1)?;
+        Some(string)
+    }
+}
+
+/// whether sections of the given version are preceded by their size in bytes (since 4.1)
+fn has_section_sizes(version: Version) -> bool {
+    version >= (4, 1)
+}
+
+/// NatNet version from the payload of a NAT_SERVERINFO packet
+pub fn server_version(payload: &[u8]) -> Option<Version> {
+    // application name (256 bytes) and version (4 bytes) come first
+    let version = payload.get(260..262)?;
+    Some((version[0], version[1]))
+}
+
+fn skip_rigid_body_description(reader: &mut Reader, version: Version) -> Option<(i32, String)> {
+    let name = if version.0 >= 2 { reader.string()? } else { String::new() };
+    let id = reader.i32()?;
+    // parent id and offset
+    reader.skip(4 + 12)?;
+    if version.0 >= 3 {
+        let markers = reader.count()?;
+        // marker offsets and active labels
+        reader.skip(markers * 16)?;
+        if version.0 >= 4 {
+            for _ in 0..markers {
+                reader.string()?;
+            }
+        }
+    }
+    Some((id, name))
+}
+
+/// ids and names of all rigid bodies (including those of skeletons) from the payload of a
+/// NAT_MODELDEF packet
+pub fn rigid_body_names(payload: &[u8], version: Version) -> Vec<(i32, String)> {
+    let mut reader = Reader::new(payload);
+    let mut rigid_bodies = Vec::new();
+    let Some(datasets) = reader.count() else {
+        return rigid_bodies;
+    };
+    for _ in 0..datasets {
+        let Some(dataset_type) = reader.i32() else {
+            break;
+        };
+        if has_section_sizes(version) && reader.skip(4).is_none() {
+            break;
+        }
+        let complete = match dataset_type {
+            DATASET_MARKER_SET => (|| {
+                reader.string()?;
+                for _ in 0..reader.count()? {
+                    reader.string()?;
+                }
+                Some(())
+            })(),
+            DATASET_RIGID_BODY => skip_rigid_body_description(&mut reader, version).map(|rigid_body| rigid_bodies.push(rigid_body)),
+            DATASET_SKELETON => (|| {
+                reader.string()?;
+                reader.skip(4)?;
+                for _ in 0..reader.count()? {
+                    rigid_bodies.push(skip_rigid_body_description(&mut reader, version)?);
+                }
+                Some(())
+            })(),
+            // force plates, devices and cameras follow the rigid bodies and are not needed
+            _ => None,
+        };
+        if complete.is_none() {
+            break;
+        }
+    }
+    rigid_bodies
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RigidBody {
+    pub id: i32,
+    /// in the units and axes of the motion-capture system
+    pub position: [f32; 3],
+    /// quaternion qx, qy, qz, qw
+    pub orientation: [f32; 4],
+    pub tracking_valid: bool,
+}
+
+/// rigid bodies of the payload of a NAT_FRAMEOFDATA packet, None if it is truncated
+pub fn rigid_bodies(payload: &[u8], version: Version) -> Option<Vec<RigidBody>> {
+    let mut reader = Reader::new(payload);
+    // frame number
+    reader.skip(4)?;
+    let marker_sets = reader.count()?;
+    if has_section_sizes(version) {
+        reader.skip(4)?;
+    }
+    for _ in 0..marker_sets {
+        reader.string()?;
+        let markers = reader.count()?;
+        reader.skip(markers * 12)?;
+    }
+    let unlabeled_markers = reader.count()?;
+    if has_section_sizes(version) {
+        reader.skip(4)?;
+    }
+    reader.skip(unlabeled_markers * 12)?;
+    let count = reader.count()?;
+    if has_section_sizes(version) {
+        reader.skip(4)?;
+    }
+    // the count comes from the network, reserve no more than the remaining bytes can hold
+    let mut rigid_bodies = Vec::with_capacity(count.min(reader.remaining() / RIGID_BODY_MIN_SIZE));
+    for _ in 0..count {
+        let id = reader.i32()?;
+        let position = [reader.f32()?, reader.f32()?, reader.f32()?];
+        let orientation = [reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?];
+        if version.0 < 3 {
+            let markers = reader.count()?;
+            // marker positions, and since 2.0 their ids and sizes
+            reader.skip(markers * if version.0 >= 2 { 20 } else { 12 })?;
+        }
+        if version.0 >= 2 {
+            // mean marker error
+            reader.skip(4)?;
+        }
+        let tracking_valid = if version >= (2, 6) { reader.i16()? & 0x01 != 0 } else { true };
+        rigid_bodies.push(RigidBody { id, position, orientation, tracking_valid });
+    }
+    Some(rigid_bodies)
+}
+
+/// vertical axis of the motion-capture system
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpAxis {
+    /// Motive's default: x, y up, z (right-handed)
+    #[default]
+    Y,
+    Z,
+}
+
+impl FromStr for UpAxis {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "y" => Ok(UpAxis::Y),
+            "z" => Ok(UpAxis::Z),
+            _ => Err(format!("unknown up axis '{}', available: y, z", name)),
+        }
+    }
+}
+
+impl fmt::Display for UpAxis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpAxis::Y => f.write_str("y"),
+            UpAxis::Z => f.write_str("z"),
+        }
+    }
+}
+
+/// Maps motion-capture coordinates into the local frame: the axes are made z-up, scaled by
+/// `scale` (e.g. 0.001 for millimetres), rotated by `yaw` (radians, counter-clockwise) and
+/// shifted by `origin`, the position of the motion-capture origin in the local frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub scale: f32,
+    pub up_axis: UpAxis,
+    pub origin: [f32; 3],
+    pub yaw: f32,
+}
+
+type Matrix = [[f32; 3]; 3];
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    [0, 1, 2].map(|i| [0, 1, 2].map(|j| a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j]))
+}
+
+fn transpose(a: &Matrix) -> Matrix {
+    [0, 1, 2].map(|i| [0, 1, 2].map(|j| a[j][i]))
+}
+
+impl Transform {
+    /// rotation from the motion-capture axes into the local frame
+    fn rotation(&self) -> Matrix {
+        let axes = match self.up_axis {
+            // (x, y, z) -> (x, -z, y)
+            UpAxis::Y => [[1., 0., 0.], [0., 0., -1.], [0., 1., 0.]],
+            UpAxis::Z => [[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]],
+        };
+        let (sin, cos) = self.yaw.sin_cos();
+        multiply(&[[cos, -sin, 0.], [sin, cos, 0.], [0., 0., 1.]], &axes)
+    }
+
+    /// Pose of the UAV carrying the rigid body. The body axes of the rigid body are mapped like
+    /// the motion-capture axes, so a rigid body defined level with its x axis pointing forward
+    /// gives roll and pitch 0.
+    pub fn state(&self, rigid_body: &RigidBody) -> State {
+        let rotation = self.rotation();
+        let p = rigid_body.position.map(|coordinate| coordinate * self.scale);
+        let [x, y, z] = [0, 1, 2].map(|i| self.origin[i] + rotation[i][0] * p[0] + rotation[i][1] * p[1] + rotation[i][2] * p[2]);
+        let [qx, qy, qz, qw] = rigid_body.orientation;
+        let body: Matrix = [
+            [1. - 2. * (qy * qy + qz * qz), 2. * (qx * qy - qz * qw), 2. * (qx * qz + qy * qw)],
+            [2. * (qx * qy + qz * qw), 1. - 2. * (qx * qx + qz * qz), 2. * (qy * qz - qx * qw)],
+            [2. * (qx * qz - qy * qw), 2. * (qy * qz + qx * qw), 1. - 2. * (qx * qx + qy * qy)],
+        ];
+        let axes = self.rotation_without_yaw();
+        let attitude = multiply(&multiply(&rotation, &body), &transpose(&axes));
+        // z-y'-x'' Tait-Bryan angles of the local attitude
+        let roll = attitude[2][1].atan2(attitude[2][2]);
+        let pitch = -attitude[2][0].clamp(-1., 1.).asin();
+        let yaw = attitude[1][0].atan2(attitude[0][0]);
+        State::new(x, y, z, roll, pitch, yaw)
+    }
+
+    fn rotation_without_yaw(&self) -> Matrix {
+        Transform { yaw: 0., ..*self }.rotation()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// frame of data of version 3.0 with the given rigid bodies, no markers
+    fn frame(count: i32, bodies: &[(i32, [f32; 3])]) -> Vec<u8> {
+        let mut payload = vec![0; 4];
+        payload.extend(0_i32.to_le_bytes());
+        payload.extend(0_i32.to_le_bytes());
+        payload.extend(count.to_le_bytes());
+        for (id, position) in bodies {
+            payload.extend(id.to_le_bytes());
+            payload.extend(position.iter().chain(&[0.0, 0.0, 0.0, 1.0]).flat_map(|v| v.to_le_bytes()));
+            // mean marker error and the tracking-valid flag
+            payload.extend(0_f32.to_le_bytes());
+            payload.extend(1_i16.to_le_bytes());
+        }
+        payload
+    }
+
+    #[test]
+    fn decodes_rigid_bodies() {
+        let bodies = rigid_bodies(&frame(2, &[(1, [1.0, 2.0, 3.0]), (4, [-1.0, 0.5, 0.0])]), (3, 0)).unwrap();
+        assert_eq!(bodies.iter().map(|body| (body.id, body.position, body.tracking_valid)).collect::<Vec<_>>(), vec![(1, [1.0, 2.0, 3.0], true), (4, [-1.0, 0.5, 0.0], true)]);
+    }
+
+    #[test]
+    fn huge_rigid_body_count_is_rejected_without_allocating_it() {
+        assert_eq!(rigid_bodies(&frame(i32::MAX, &[(1, [1.0, 2.0, 3.0])]), (3, 0)), None);
+        assert_eq!(rigid_bodies(&frame(-1, &[]), (3, 0)), None);
+    }
+}
@@ -0,0 +1,335 @@
+//! Framed binary protocol of the position updates, all fields big-endian.
+//!
+//! Header (16 bytes):
+//!
+//! | offset | size | field                                                         |
+//! |--------|------|---------------------------------------------------------------|
+//! | 0      | 4    | magic `CHEM`                                                  |
+//! | 4      | 1    | protocol version, 1                                           |
+//! | 5      | 1    | message type, 1 = position update                             |
+//! | 6      | 2    | flags: bit 0 CRC appended, bit 1 velocity present             |
+//! | 8      | 4    | sequence number, incremented per message and UAV, wrapping    |
+//! | 12     | 2    | payload length in bytes                                       |
+//! | 14     | 2    | UAV id                                                        |
+//!
+//! Position update payload (48 bytes, 60 with velocity):
+//!
+//! | offset | size | field                                                               |
+//! |--------|------|---------------------------------------------------------------------|
+//! | 0      | 8    | timestamp of the sender in microseconds (u64)                       |
+//! | 8      | 1    | coordinate frame: 0 local (x east, y north, z up), 1 WGS84 geodetic |
+//! | 9      | 3    | reserved, 0                                                         |
+//! | 12     | 24   | x, y, z in metres or latitude, longitude (degrees), altitude (f64)  |
+//! | 36     | 12   | roll, pitch, yaw in radians (f32), NED convention if geodetic       |
+//! | 48     | 12   | velocity (f32, m/s) east, north, up or north, east, down if geodetic |
+//!
+//! With bit 0 of the flags, the CRC-32 (IEEE 802.3) of header and payload follows the
+//! payload. Receivers skip payload bytes after the fields they know, so later versions of a
+//! message can append fields without breaking older receivers.
+
+use std::collections::HashMap;
+use std::fmt;
+
+pub const MAGIC: [u8; 4] = *b"CHEM";
+pub const VERSION: u8 = 1;
+pub const HEADER_LENGTH: usize = 16;
+pub const MESSAGE_TYPE_POSITION: u8 = 1;
+pub const FLAG_CRC: u16 = 1;
+pub const FLAG_VELOCITY: u16 = 1 << 1;
+const POSITION_PAYLOAD_LENGTH: usize = 48;
+const VELOCITY_LENGTH: usize = 12;
+const CRC_LENGTH: usize = 4;
+/// sequence numbers up to this far behind the last accepted one are rejected as reordered or
+/// duplicated, anything further behind is taken as a restart of the sender
+pub const REORDER_WINDOW: u32 = 1024;
+
+/// coordinate frame of a position update
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Frame {
+    /// x east, y north, z up in metres, attitude and velocity in the same frame
+    Local,
+    /// WGS84 latitude, longitude, altitude, attitude and velocity in the NED convention
+    Geodetic,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PositionUpdate {
+    pub uav_id: u16,
+    pub sequence: u32,
+    /// microseconds, clock of the sender
+    pub timestamp: u64,
+    pub frame: Frame,
+    pub position: [f64; 3],
+    pub attitude: [f32; 3],
+    pub velocity: Option<[f32; 3]>,
+}
+
+/// Why a datagram was not accepted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// does not start with the magic, e.g. a stray packet of another program
+    NotFramed,
+    TooShort(usize),
+    UnsupportedVersion(u8),
+    UnknownMessageType(u8),
+    /// datagram length does not match the payload length of the header
+    LengthMismatch { expected: usize, received: usize },
+    /// payload too short for the fields of the message
+    PayloadTooShort(usize),
+    UnknownFrame(u8),
+    CrcMismatch,
+    /// sequence number not newer than the last accepted one of the UAV
+    OutOfOrder { uav_id: u16, sequence: u32, last: u32 },
+    /// from a UAV other than the emulated one
+    OtherUav(u16),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::NotFramed => write!(f, "no frame magic"),
+            Rejection::TooShort(length) => write!(f, "{} bytes are too short for a header", length),
+            Rejection::UnsupportedVersion(version) => write!(f, "unsupported protocol version {}", version),
+            Rejection::UnknownMessageType(message_type) => write!(f, "unknown message type {}", message_type),
+            Rejection::LengthMismatch { expected, received } => write!(f, "expected {} bytes, received {}", expected, received),
+            Rejection::PayloadTooShort(length) => write!(f, "payload of {} bytes too short for a position update", length),
+            Rejection::UnknownFrame(frame) => write!(f, "unknown coordinate frame {}", frame),
+            Rejection::CrcMismatch => write!(f, "CRC mismatch"),
+            Rejection::OutOfOrder { uav_id, sequence, last } => write!(f, "sequence number {} of UAV {} is not newer than {}", sequence, uav_id, last),
+            Rejection::OtherUav(uav_id) => write!(f, "from UAV {}, which is not emulated", uav_id),
+        }
+    }
+}
+
+/// CRC-32 as used by Ethernet and zlib (reflected polynomial 0xEDB88320)
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn f32_at(buf: &[u8], offset: usize) -> f32 {
+    f32::from_be_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+fn f64_at(buf: &[u8], offset: usize) -> f64 {
+    f64::from_be_bytes(buf[offset..offset + 8].try_into().unwrap())
+}
+
+/// Parses a framed datagram. The checks are done in a fixed order, so every datagram is
+/// either accepted or rejected for the same reason.
+pub fn parse(buf: &[u8]) -> Result<PositionUpdate, Rejection> {
+    if !buf.starts_with(&MAGIC) {
+        return Err(Rejection::NotFramed);
+    }
+    if buf.len() < HEADER_LENGTH {
+        return Err(Rejection::TooShort(buf.len()));
+    }
+    if buf[4] != VERSION {
+        return Err(Rejection::UnsupportedVersion(buf[4]));
+    }
+    if buf[5] != MESSAGE_TYPE_POSITION {
+        return Err(Rejection::UnknownMessageType(buf[5]));
+    }
+    let flags = u16::from_be_bytes([buf[6], buf[7]]);
+    let sequence = u32::from_be_bytes(buf[8..12].try_into().unwrap());
+    let payload_length = u16::from_be_bytes([buf[12], buf[13]]) as usize;
+    let uav_id = u16::from_be_bytes([buf[14], buf[15]]);
+    let crc_length = if flags & FLAG_CRC != 0 { CRC_LENGTH } else { 0 };
+    let expected = HEADER_LENGTH + payload_length + crc_length;
+    if buf.len() != expected {
+        return Err(Rejection::LengthMismatch { expected, received: buf.len() });
+    }
+    if crc_length != 0 {
+        let (framed, crc) = buf.split_at(HEADER_LENGTH + payload_length);
+        if crc32(framed) != u32::from_be_bytes(crc.try_into().unwrap()) {
+            return Err(Rejection::CrcMismatch);
+        }
+    }
+    let has_velocity = flags & FLAG_VELOCITY != 0;
+    if payload_length < POSITION_PAYLOAD_LENGTH + if has_velocity { VELOCITY_LENGTH } else { 0 } {
+        return Err(Rejection::PayloadTooShort(payload_length));
+    }
+    let payload = &buf[HEADER_LENGTH..HEADER_LENGTH + payload_length];
+    let frame = match payload[8] {
+        0 => Frame::Local,
+        1 => Frame::Geodetic,
+        frame => return Err(Rejection::UnknownFrame(frame)),
+    };
+    Ok(PositionUpdate {
+        uav_id,
+        sequence,
+        timestamp: u64::from_be_bytes(payload[0..8].try_into().unwrap()),
+        frame,
+        position: [0, 1, 2].map(|i| f64_at(payload, 12 + 8 * i)),
+        attitude: [0, 1, 2].map(|i| f32_at(payload, 36 + 4 * i)),
+        velocity: has_velocity.then(|| [0, 1, 2].map(|i| f32_at(payload, 48 + 4 * i))),
+    })
+}
+
+/// Accepts only updates of the emulated UAV (any if `uav_id` is None) with a sequence number
+/// newer than the last accepted one of the same UAV.
+#[derive(Debug, Default)]
+pub struct SequenceFilter {
+    uav_id: Option<u16>,
+    last: HashMap<u16, u32>,
+}
+
+impl SequenceFilter {
+    pub fn new(uav_id: Option<u16>) -> Self {
+        SequenceFilter { uav_id, last: HashMap::new() }
+    }
+
+    pub fn accept(&mut self, update: &PositionUpdate) -> Result<(), Rejection> {
+        if self.uav_id.is_some_and(|uav_id| uav_id != update.uav_id) {
+            return Err(Rejection::OtherUav(update.uav_id));
+        }
+        if let Some(&last) = self.last.get(&update.uav_id) {
+            // serial number arithmetic, so the sequence number may wrap
+            let behind = last.wrapping_sub(update.sequence);
+            if behind <= REORDER_WINDOW {
+                return Err(Rejection::OutOfOrder { uav_id: update.uav_id, sequence: update.sequence, last });
+            }
+        }
+        self.last.insert(update.uav_id, update.sequence);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// datagram as a sender following the table above builds it
+    fn encode(update: &PositionUpdate, with_crc: bool) -> Vec<u8> {
+        let mut payload = update.timestamp.to_be_bytes().to_vec();
+        payload.extend([if update.frame == Frame::Geodetic { 1 } else { 0 }, 0, 0, 0]);
+        payload.extend(update.position.iter().flat_map(|v| v.to_be_bytes()));
+        payload.extend(update.attitude.iter().flat_map(|v| v.to_be_bytes()));
+        let mut flags = if with_crc { FLAG_CRC } else { 0 };
+        if let Some(velocity) = update.velocity {
+            payload.extend(velocity.iter().flat_map(|v| v.to_be_bytes()));
+            flags |= FLAG_VELOCITY;
+        }
+        let mut buf = MAGIC.to_vec();
+        buf.extend([VERSION, MESSAGE_TYPE_POSITION]);
+        buf.extend(flags.to_be_bytes());
+        buf.extend(update.sequence.to_be_bytes());
+        buf.extend((payload.len() as u16).to_be_bytes());
+        buf.extend(update.uav_id.to_be_bytes());
+        buf.extend(payload);
+        if with_crc {
+            let crc = crc32(&buf);
+            buf.extend(crc.to_be_bytes());
+        }
+        buf
+    }
+
+    fn update(uav_id: u16, sequence: u32) -> PositionUpdate {
+        PositionUpdate {
+            uav_id,
+            sequence,
+            timestamp: 1_700_000_000_000_000,
+            frame: Frame::Local,
+            position: [12.5, -3.25, 100.0],
+            attitude: [0.1, -0.2, 1.5],
+            velocity: None,
+        }
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn round_trip() {
+        let local = update(3, 7);
+        assert_eq!(parse(&encode(&local, false)), Ok(local.clone()));
+        assert_eq!(parse(&encode(&local, true)), Ok(local));
+        let geodetic = PositionUpdate { frame: Frame::Geodetic, position: [50.1, 8.6, 120.0], velocity: Some([1.0, -2.0, 0.5]), ..update(3, 8) };
+        assert_eq!(parse(&encode(&geodetic, true)), Ok(geodetic));
+    }
+
+    #[test]
+    fn unknown_trailing_payload_fields_are_skipped() {
+        let update = update(1, 1);
+        let mut buf = encode(&update, false);
+        buf.extend([0xAA; 8]);
+        buf[12..14].copy_from_slice(&(POSITION_PAYLOAD_LENGTH as u16 + 8).to_be_bytes());
+        assert_eq!(parse(&buf), Ok(update));
+    }
+
+    #[test]
+    fn bad_crc_is_rejected() {
+        let mut buf = encode(&update(1, 1), true);
+        buf[HEADER_LENGTH + 20] ^= 0x01;
+        assert_eq!(parse(&buf), Err(Rejection::CrcMismatch));
+        let mut buf = encode(&update(1, 1), true);
+        let last = buf.len() - 1;
+        buf[last] ^= 0x80;
+        assert_eq!(parse(&buf), Err(Rejection::CrcMismatch));
+    }
+
+    #[test]
+    fn malformed_datagrams_are_rejected() {
+        let buf = encode(&update(1, 1), false);
+        assert_eq!(parse(&[0; 24]), Err(Rejection::NotFramed));
+        assert_eq!(parse(&buf[..10]), Err(Rejection::TooShort(10)));
+        let mut version = buf.clone();
+        version[4] = 2;
+        assert_eq!(parse(&version), Err(Rejection::UnsupportedVersion(2)));
+        let mut message_type = buf.clone();
+        message_type[5] = 9;
+        assert_eq!(parse(&message_type), Err(Rejection::UnknownMessageType(9)));
+        assert_eq!(parse(&buf[..buf.len() - 1]), Err(Rejection::LengthMismatch { expected: buf.len(), received: buf.len() - 1 }));
+        let mut frame = buf.clone();
+        frame[HEADER_LENGTH + 8] = 2;
+        assert_eq!(parse(&frame), Err(Rejection::UnknownFrame(2)));
+        let mut short = buf[..HEADER_LENGTH + 40].to_vec();
+        short[12..14].copy_from_slice(&40_u16.to_be_bytes());
+        assert_eq!(parse(&short), Err(Rejection::PayloadTooShort(40)));
+        let mut no_velocity = buf.clone();
+        no_velocity[7] |= FLAG_VELOCITY as u8;
+        assert_eq!(parse(&no_velocity), Err(Rejection::PayloadTooShort(POSITION_PAYLOAD_LENGTH)));
+    }
+
+    #[test]
+    fn sequence_filter_rejects_duplicates_and_reordered_updates() {
+        let mut filter = SequenceFilter::new(None);
+        assert_eq!(filter.accept(&update(1, 100)), Ok(()));
+        assert_eq!(filter.accept(&update(1, 100)), Err(Rejection::OutOfOrder { uav_id: 1, sequence: 100, last: 100 }));
+        assert_eq!(filter.accept(&update(1, 99)), Err(Rejection::OutOfOrder { uav_id: 1, sequence: 99, last: 100 }));
+        assert_eq!(filter.accept(&update(1, 101)), Ok(()));
+        // sequence numbers are tracked per UAV
+        assert_eq!(filter.accept(&update(2, 5)), Ok(()));
+        assert_eq!(filter.accept(&update(1, 102)), Ok(()));
+    }
+
+    #[test]
+    fn sequence_filter_window_and_wrap_around() {
+        let mut filter = SequenceFilter::new(None);
+        assert_eq!(filter.accept(&update(1, u32::MAX - 1)), Ok(()));
+        assert_eq!(filter.accept(&update(1, u32::MAX)), Ok(()));
+        assert_eq!(filter.accept(&update(1, 0)), Ok(()));
+        assert_eq!(filter.accept(&update(1, 1)), Ok(()));
+        assert!(filter.accept(&update(1, u32::MAX)).is_err());
+        // the oldest sequence number still inside the window is rejected, one further behind
+        // is taken as a restart of the sender
+        let last = 1_u32;
+        assert!(filter.accept(&update(1, last.wrapping_sub(REORDER_WINDOW))).is_err());
+        assert_eq!(filter.accept(&update(1, last.wrapping_sub(REORDER_WINDOW + 1))), Ok(()));
+    }
+
+    #[test]
+    fn sequence_filter_ignores_other_uavs() {
+        let mut filter = SequenceFilter::new(Some(4));
+        assert_eq!(filter.accept(&update(5, 1)), Err(Rejection::OtherUav(5)));
+        assert_eq!(filter.accept(&update(4, 1)), Ok(()));
+    }
+}
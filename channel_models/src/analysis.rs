@@ -0,0 +1,325 @@
+use num::complex::Complex32;
+use pyo3::prelude::*;
+use std::f32::consts::PI;
+use std::fmt;
+
+use crate::taps::tap_gain;
+use crate::PropagationPath;
+
+/// frequency correlation at which the coherence bandwidth is usually given
+pub const COHERENCE_CORRELATION: f32 = 0.5;
+/// most coarse steps of the coherence bandwidth search before it is refined by bisection
+const COHERENCE_SEARCH_STEPS: usize = 1000;
+/// coarse steps per lower bound of the coherence bandwidth, see `coherence_bandwidth`
+const COHERENCE_STEPS_PER_BOUND: f32 = 8.;
+/// bisection steps refining the coherence bandwidth to 1/4096 of a coarse step
+const COHERENCE_BISECTION_STEPS: usize = 12;
+
+/// Discrete channel impulse response: complex gains at delays in seconds, built either from the
+/// paths of a model or from the taps of the FIR filter.
+#[pyclass]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImpulseResponse {
+    #[pyo3(get)]
+    pub delays: Vec<f32>,
+    #[pyo3(get)]
+    pub gains: Vec<Complex32>,
+}
+
+impl ImpulseResponse {
+    /// the paths with their gains including the carrier phase of their delay at `frequency`,
+    /// as they are placed on the taps
+    pub fn from_paths(paths: &[PropagationPath], frequency: f32) -> Self {
+        ImpulseResponse {
+            delays: paths.iter().map(|path| path.delay).collect(),
+            gains: paths.iter().map(|path| tap_gain(path, frequency)).collect(),
+        }
+    }
+
+    /// complex taps spaced one sample apart; the delays include the latency of the tap kernel
+    pub fn from_taps(taps: &[Complex32], sample_rate: f32) -> Self {
+        ImpulseResponse {
+            delays: (0..taps.len()).map(|n| n as f32 / sample_rate).collect(),
+            gains: taps.to_vec(),
+        }
+    }
+
+    /// (delay, power) of every component in order of delay
+    pub fn power_delay_profile(&self) -> Vec<(f32, f32)> {
+        let mut profile: Vec<(f32, f32)> = self.delays.iter().zip(&self.gains).map(|(&delay, gain)| (delay, gain.norm_sqr())).collect();
+        profile.sort_by(|a, b| a.0.total_cmp(&b.0));
+        profile
+    }
+
+    /// total power gain of all components
+    pub fn power(&self) -> f32 {
+        self.gains.iter().map(Complex32::norm_sqr).sum()
+    }
+
+    /// power-weighted mean delay in seconds
+    pub fn mean_delay(&self) -> f32 {
+        let power = self.power();
+        if power == 0. {
+            return 0.;
+        }
+        self.delays.iter().zip(&self.gains).map(|(delay, gain)| delay * gain.norm_sqr()).sum::<f32>() / power
+    }
+
+    /// RMS delay spread in seconds, i.e. the power-weighted standard deviation of the delays
+    pub fn rms_delay_spread(&self) -> f32 {
+        let power = self.power();
+        if power == 0. {
+            return 0.;
+        }
+        let mean_delay = self.mean_delay();
+        let variance = self.delays.iter().zip(&self.gains).map(|(delay, gain)| (delay - mean_delay).powi(2) * gain.norm_sqr()).sum::<f32>() / power;
+        variance.max(0.).sqrt()
+    }
+
+    /// Rician K-factor in dB: power of the strongest component over the power of all others,
+    /// +inf for a single component
+    pub fn k_factor(&self) -> f32 {
+        let power = self.power();
+        let strongest = self.gains.iter().map(Complex32::norm_sqr).fold(0., f32::max);
+        if strongest == 0. {
+            return f32::NEG_INFINITY;
+        }
+        10. * (strongest / (power - strongest).max(0.)).log10()
+    }
+
+    /// channel transfer function at `frequency` relative to the carrier
+    pub fn transfer_function(&self, frequency: f32) -> Complex32 {
+        self.delays.iter().zip(&self.gains).map(|(delay, gain)| gain * Complex32::from_polar(1., -2. * PI * frequency * delay)).sum()
+    }
+
+    /// (frequency relative to the carrier, transfer function) at `points` frequencies evenly
+    /// spaced over `bandwidth` around the carrier
+    pub fn frequency_response(&self, bandwidth: f32, points: usize) -> Vec<(f32, Complex32)> {
+        (0..points)
+            .map(|i| {
+                let frequency = bandwidth * (i as f32 / points as f32 - 0.5);
+                (frequency, self.transfer_function(frequency))
+            })
+            .collect()
+    }
+
+    /// magnitude of the normalized frequency correlation of the transfer function at a
+    /// frequency separation of `separation`, determined by the power-delay profile
+    pub fn frequency_correlation(&self, separation: f32) -> f32 {
+        let power = self.power();
+        if power == 0. {
+            return 0.;
+        }
+        let correlation: Complex32 = self
+            .delays
+            .iter()
+            .zip(&self.gains)
+            .map(|(delay, gain)| Complex32::from_polar(gain.norm_sqr(), -2. * PI * separation * delay))
+            .sum();
+        correlation.norm() / power
+    }
+
+    /// Smallest frequency separation in Hz at which the frequency correlation drops below
+    /// `correlation`, searched up to `max_bandwidth`; +inf if it stays above, e.g. for a
+    /// single path.
+    ///
+    /// By Fleury's uncertainty relation the correlation stays above `correlation` up to
+    /// arccos(correlation) / (2 pi rms delay spread), so the search starts there and steps by
+    /// an eighth of that bound, which usually finds the crossing within a few steps.
+    pub fn coherence_bandwidth(&self, max_bandwidth: f32, correlation: f32) -> f32 {
+        let rms_delay_spread = self.rms_delay_spread();
+        if rms_delay_spread == 0. {
+            return f32::INFINITY;
+        }
+        let lower_bound = correlation.clamp(0., 1.).acos() / (2. * PI * rms_delay_spread);
+        let step = (lower_bound / COHERENCE_STEPS_PER_BOUND).max((max_bandwidth - lower_bound) / COHERENCE_SEARCH_STEPS as f32);
+        let Some(i) = (1..=COHERENCE_SEARCH_STEPS)
+            .take_while(|&i| lower_bound + (i - 1) as f32 * step < max_bandwidth)
+            .find(|&i| self.frequency_correlation((lower_bound + i as f32 * step).min(max_bandwidth)) < correlation)
+        else {
+            return f32::INFINITY;
+        };
+        let (mut low, mut high) = (lower_bound + (i - 1) as f32 * step, (lower_bound + i as f32 * step).min(max_bandwidth));
+        for _ in 0..COHERENCE_BISECTION_STEPS {
+            let middle = (low + high) / 2.;
+            if self.frequency_correlation(middle) < correlation {
+                high = middle;
+            } else {
+                low = middle;
+            }
+        }
+        high
+    }
+
+    /// all statistics at once, the coherence bandwidth searched over `bandwidth`
+    pub fn statistics(&self, bandwidth: f32) -> ChannelStatistics {
+        ChannelStatistics {
+            power: self.power(),
+            mean_delay: self.mean_delay(),
+            rms_delay_spread: self.rms_delay_spread(),
+            k_factor: self.k_factor(),
+            coherence_bandwidth: self.coherence_bandwidth(bandwidth, COHERENCE_CORRELATION),
+        }
+    }
+}
+
+#[pymethods]
+impl ImpulseResponse {
+    #[staticmethod]
+    #[pyo3(name = "from_paths")]
+    fn py_from_paths(paths: Vec<PropagationPath>, frequency: f32) -> Self {
+        ImpulseResponse::from_paths(&paths, frequency)
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "from_taps")]
+    fn py_from_taps(taps: Vec<Complex32>, sample_rate: f32) -> Self {
+        ImpulseResponse::from_taps(&taps, sample_rate)
+    }
+
+    /// delays and powers in order of delay
+    #[pyo3(name = "power_delay_profile")]
+    fn py_power_delay_profile(&self) -> (Vec<f32>, Vec<f32>) {
+        self.power_delay_profile().into_iter().unzip()
+    }
+
+    #[pyo3(name = "power")]
+    fn py_power(&self) -> f32 {
+        self.power()
+    }
+
+    #[pyo3(name = "mean_delay")]
+    fn py_mean_delay(&self) -> f32 {
+        self.mean_delay()
+    }
+
+    #[pyo3(name = "rms_delay_spread")]
+    fn py_rms_delay_spread(&self) -> f32 {
+        self.rms_delay_spread()
+    }
+
+    #[pyo3(name = "k_factor")]
+    fn py_k_factor(&self) -> f32 {
+        self.k_factor()
+    }
+
+    /// frequencies relative to the carrier and the transfer function at them
+    #[pyo3(name = "frequency_response", signature = (bandwidth, points = 1024))]
+    fn py_frequency_response(&self, bandwidth: f32, points: usize) -> (Vec<f32>, Vec<Complex32>) {
+        self.frequency_response(bandwidth, points).into_iter().unzip()
+    }
+
+    #[pyo3(name = "coherence_bandwidth", signature = (max_bandwidth, correlation = COHERENCE_CORRELATION))]
+    fn py_coherence_bandwidth(&self, max_bandwidth: f32, correlation: f32) -> f32 {
+        self.coherence_bandwidth(max_bandwidth, correlation)
+    }
+
+    #[pyo3(name = "statistics")]
+    fn py_statistics(&self, bandwidth: f32) -> ChannelStatistics {
+        self.statistics(bandwidth)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ImpulseResponse(components={}, power={}, rms_delay_spread={})", self.gains.len(), self.power(), self.rms_delay_spread())
+    }
+}
+
+/// Summary of an impulse response as logged by the emulator.
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChannelStatistics {
+    /// total power gain, linear
+    #[pyo3(get)]
+    pub power: f32,
+    /// in seconds
+    #[pyo3(get)]
+    pub mean_delay: f32,
+    /// in seconds
+    #[pyo3(get)]
+    pub rms_delay_spread: f32,
+    /// in dB
+    #[pyo3(get)]
+    pub k_factor: f32,
+    /// in Hz, at a frequency correlation of 0.5
+    #[pyo3(get)]
+    pub coherence_bandwidth: f32,
+}
+
+impl fmt::Display for ChannelStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "power {:.1} dB, mean delay {:.2} ns, rms delay spread {:.2} ns, K-factor {:.1} dB, coherence bandwidth {:.3} MHz",
+            10. * self.power.log10(),
+            self.mean_delay * 1e9,
+            self.rms_delay_spread * 1e9,
+            self.k_factor,
+            self.coherence_bandwidth / 1e6
+        )
+    }
+}
+
+#[pymethods]
+impl ChannelStatistics {
+    fn __repr__(&self) -> String {
+        format!("ChannelStatistics({})", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PathKind;
+
+    /// line of sight and a path 100 ns later with `relative_power` of its power
+    fn two_paths(relative_power: f32) -> ImpulseResponse {
+        let paths = [
+            PropagationPath::new(PathKind::LineOfSight, 1., 0., 0.),
+            PropagationPath::new(PathKind::GroundReflection, 1. / relative_power.sqrt(), 100e-9, 0.),
+        ];
+        ImpulseResponse::from_paths(&paths, 0.)
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() <= 1e-4 * expected.abs(), "{} instead of {}", actual, expected);
+    }
+
+    #[test]
+    fn two_path_delay_spread() {
+        // powers 1 and a^2: mean delay a^2 / (1 + a^2) tau, spread a / (1 + a^2) tau
+        for relative_power in [1.0_f32, 0.5, 0.1] {
+            let response = two_paths(relative_power);
+            let a = relative_power.sqrt();
+            assert_close(response.power(), 1. + relative_power);
+            assert_close(response.mean_delay(), relative_power / (1. + relative_power) * 100e-9);
+            assert_close(response.rms_delay_spread(), a / (1. + relative_power) * 100e-9);
+        }
+        assert_eq!(ImpulseResponse::from_paths(&[PropagationPath::new(PathKind::LineOfSight, 2., 0., 0.)], 0.).rms_delay_spread(), 0.);
+    }
+
+    #[test]
+    fn k_factor_is_strongest_over_the_rest() {
+        assert_close(two_paths(0.5).k_factor(), 10. * 2.0_f32.log10());
+        assert_close(two_paths(0.1).k_factor(), 10.);
+        assert_eq!(two_paths(1.).k_factor(), 0.);
+        assert_eq!(ImpulseResponse::from_paths(&[PropagationPath::new(PathKind::LineOfSight, 2., 0., 0.)], 0.).k_factor(), f32::INFINITY);
+    }
+
+    #[test]
+    fn two_tap_coherence_bandwidth() {
+        // |1 + a^2 exp(-2 pi i f tau)| / (1 + a^2) falls to c where
+        // cos(2 pi f tau) = (c^2 (1 + a^2)^2 - 1 - a^4) / (2 a^2)
+        for (relative_power, correlation) in [(1.0_f32, 0.5_f32), (0.5, 0.5), (0.5, 0.9), (0.3, 0.7)] {
+            let cos = (correlation.powi(2) * (1. + relative_power).powi(2) - 1. - relative_power.powi(2)) / (2. * relative_power);
+            let expected = cos.acos() / (2. * PI * 100e-9);
+            let bandwidth = two_paths(relative_power).coherence_bandwidth(200e6, correlation);
+            assert!((bandwidth - expected).abs() < 1e-3 * expected, "{} {}: {} instead of {}", relative_power, correlation, bandwidth, expected);
+        }
+        // equal powers give 1 / (3 tau) at a correlation of 0.5
+        assert!((two_paths(1.).coherence_bandwidth(200e6, COHERENCE_CORRELATION) - 1. / 300e-9).abs() < 1e3);
+        // a weak second path never decorrelates the channel that far, nor does a single path
+        assert_eq!(two_paths(0.1).coherence_bandwidth(200e6, 0.5), f32::INFINITY);
+        assert_eq!(two_paths(1.).coherence_bandwidth(1e6, 0.5), f32::INFINITY);
+        assert_eq!(ImpulseResponse::from_paths(&[PropagationPath::new(PathKind::LineOfSight, 2., 0., 0.)], 0.).coherence_bandwidth(200e6, 0.5), f32::INFINITY);
+    }
+}
@@ -0,0 +1,457 @@
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+use serde::{Deserialize, Deserializer};
+use std::f32::consts::PI;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use crate::geometry::{from_direction, to_rotated_frame, Vec3};
+use crate::models::{ChannelModel, UnknownParameter};
+use crate::{Direction, PropagationPath, Scenario, State};
+
+/// gain of the built-in patterns in their nulls (-40 dBi), keeps the path loss finite
+const MIN_GAIN: f32 = 1.0e-4;
+/// back lobe of the built-in patch pattern (-20 dBi)
+const PATCH_BACK_LOBE: f32 = 0.01;
+/// gain of a half-wave dipole over an isotropic radiator (2.15 dBi)
+const DIPOLE_GAIN: f32 = 1.64;
+
+/// Power gain pattern of an antenna over the directions of its own frame: boresight along
+/// +x, +z up (i.e. the axis of a dipole).
+#[derive(Clone, PartialEq)]
+pub enum AntennaPattern {
+    Isotropic,
+    /// half-wave dipole along the z axis
+    Dipole,
+    /// microstrip patch radiating along +x, a cos² main lobe (7.8 dBi) and a -20 dBi back lobe
+    Patch,
+    /// measured pattern read from a file
+    Table(PatternTable),
+}
+
+/// Gains in dBi on a rectilinear azimuth/elevation grid (degrees), interpolated bilinearly.
+#[derive(Clone, PartialEq)]
+pub struct PatternTable {
+    source: String,
+    /// ascending, in [0, 360)
+    azimuths: Vec<f32>,
+    /// ascending, in [-90, 90]
+    elevations: Vec<f32>,
+    /// `gains[elevation_index * azimuths.len() + azimuth_index]`
+    gains: Vec<f32>,
+}
+
+impl AntennaPattern {
+    /// names of the built-in patterns, accepted by `AntennaPattern::load`
+    pub const BUILT_IN: [&'static str; 3] = ["isotropic", "dipole", "patch"];
+
+    /// A built-in pattern if `name_or_path` names one, else the pattern in the given file:
+    /// `.csv` files hold `azimuth,elevation,gain` rows (degrees, dBi) on a full grid, all
+    /// other files are read as MSI/Planet pattern files.
+    pub fn load(name_or_path: &str) -> io::Result<AntennaPattern> {
+        match name_or_path {
+            "isotropic" => Ok(AntennaPattern::Isotropic),
+            "dipole" => Ok(AntennaPattern::Dipole),
+            "patch" => Ok(AntennaPattern::Patch),
+            path => {
+                let content = std::fs::read_to_string(path)?;
+                let table = if Path::new(path).extension().is_some_and(|extension| extension.eq_ignore_ascii_case("csv")) {
+                    PatternTable::from_csv(path, &content)
+                } else {
+                    PatternTable::from_msi(path, &content)
+                };
+                table.map(AntennaPattern::Table).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            AntennaPattern::Isotropic => "isotropic",
+            AntennaPattern::Dipole => "dipole",
+            AntennaPattern::Patch => "patch",
+            AntennaPattern::Table(table) => &table.source,
+        }
+    }
+
+    /// linear power gain towards the unit vector `v` in the antenna frame
+    fn gain(&self, v: Vec3) -> f32 {
+        match self {
+            AntennaPattern::Isotropic => 1.0,
+            AntennaPattern::Dipole => {
+                let cos_theta = v[2].clamp(-1.0, 1.0);
+                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                if sin_theta < 1.0e-3 {
+                    return MIN_GAIN;
+                }
+                (DIPOLE_GAIN * ((PI / 2.0 * cos_theta).cos() / sin_theta).powi(2)).max(MIN_GAIN)
+            }
+            AntennaPattern::Patch => (6.0 * v[0].max(0.0).powi(2)).max(PATCH_BACK_LOBE),
+            AntennaPattern::Table(table) => {
+                let azimuth = v[1].atan2(v[0]).to_degrees();
+                let elevation = v[2].clamp(-1.0, 1.0).asin().to_degrees();
+                10.0_f32.powf(table.gain_db(azimuth, elevation) / 10.0)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for AntennaPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// index pair and interpolation weight of `x` between the ascending angles `values` that wrap
+/// around at 360 degrees
+fn bracket_wrapped(values: &[f32], x: f32) -> (usize, usize, f32) {
+    let n = values.len();
+    let x = x.rem_euclid(360.0);
+    let index = values.partition_point(|&value| value <= x);
+    if index == 0 || index == n {
+        let lower = values[n - 1];
+        let upper = values[0] + 360.0;
+        let x = if x < values[0] { x + 360.0 } else { x };
+        let span = upper - lower;
+        let t = if span > 0.0 { (x - lower) / span } else { 0.0 };
+        (n - 1, 0, t)
+    } else {
+        (index - 1, index, (x - values[index - 1]) / (values[index] - values[index - 1]))
+    }
+}
+
+/// index pair and interpolation weight of `x` between the ascending `values`, clamped at the ends
+fn bracket_clamped(values: &[f32], x: f32) -> (usize, usize, f32) {
+    let n = values.len();
+    if x <= values[0] {
+        (0, 0, 0.0)
+    } else if x >= values[n - 1] {
+        (n - 1, n - 1, 0.0)
+    } else {
+        let index = values.partition_point(|&value| value <= x);
+        (index - 1, index, (x - values[index - 1]) / (values[index] - values[index - 1]))
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// sorted angles without (near) duplicates
+fn grid_axis(mut values: Vec<f32>) -> Vec<f32> {
+    values.sort_by(|a, b| a.total_cmp(b));
+    values.dedup_by(|a, b| (*a - *b).abs() < 1.0e-3);
+    values
+}
+
+fn axis_index(axis: &[f32], value: f32) -> usize {
+    axis.iter().position(|&a| (a - value).abs() < 1.0e-3).unwrap()
+}
+
+impl PatternTable {
+    fn gain_db(&self, azimuth: f32, elevation: f32) -> f32 {
+        let (a_0, a_1, t_a) = bracket_wrapped(&self.azimuths, azimuth);
+        let (e_0, e_1, t_e) = bracket_clamped(&self.elevations, elevation);
+        let at = |e: usize, a: usize| self.gains[e * self.azimuths.len() + a];
+        lerp(lerp(at(e_0, a_0), at(e_0, a_1), t_a), lerp(at(e_1, a_0), at(e_1, a_1), t_a), t_e)
+    }
+
+    /// `azimuth,elevation,gain` rows (`;`, tabs and spaces also separate), a leading header
+    /// line and lines starting with `#` are skipped
+    fn from_csv(source: &str, content: &str) -> Result<PatternTable, String> {
+        let mut rows = Vec::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Result<Vec<f32>, _> = line
+                .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+                .filter(|field| !field.is_empty())
+                .map(str::parse)
+                .collect();
+            match fields {
+                Ok(fields) if fields.len() == 3 => rows.push((fields[0].rem_euclid(360.0), fields[1], fields[2])),
+                _ if rows.is_empty() => continue,
+                _ => return Err(format!("{}:{}: expected 'azimuth,elevation,gain'", source, number + 1)),
+            }
+        }
+        if rows.is_empty() {
+            return Err(format!("{}: no pattern rows", source));
+        }
+        if rows.iter().any(|&(_, elevation, _)| !(-90.0..=90.0).contains(&elevation)) {
+            return Err(format!("{}: elevations must be within [-90, 90] degrees", source));
+        }
+        let azimuths = grid_axis(rows.iter().map(|row| row.0).collect());
+        let elevations = grid_axis(rows.iter().map(|row| row.1).collect());
+        let mut gains = vec![None; azimuths.len() * elevations.len()];
+        for &(azimuth, elevation, gain) in &rows {
+            gains[axis_index(&elevations, elevation) * azimuths.len() + axis_index(&azimuths, azimuth)] = Some(gain);
+        }
+        let gains: Option<Vec<f32>> = gains.into_iter().collect();
+        let gains = gains.ok_or_else(|| format!("{}: the rows do not cover a full azimuth/elevation grid", source))?;
+        Ok(PatternTable { source: source.to_string(), azimuths, elevations, gains })
+    }
+
+    /// MSI/Planet file: `GAIN` (dBd unless followed by `dBi`) plus `HORIZONTAL` and `VERTICAL`
+    /// cuts of attenuation in dB. Horizontal angles run clockwise seen from above, vertical
+    /// angles downwards from the horizon in boresight direction. The cuts are combined by
+    /// adding their attenuations.
+    fn from_msi(source: &str, content: &str) -> Result<PatternTable, String> {
+        let mut gain = None;
+        let mut horizontal = Vec::new();
+        let mut vertical = Vec::new();
+        let mut lines = content.lines().enumerate();
+        while let Some((number, line)) = lines.next() {
+            let mut fields = line.split_whitespace();
+            let keyword = fields.next().unwrap_or("").to_ascii_uppercase();
+            let cut = match keyword.as_str() {
+                "GAIN" => {
+                    let value: f32 = fields.next().and_then(|value| value.parse().ok())
+                        .ok_or_else(|| format!("{}:{}: invalid GAIN", source, number + 1))?;
+                    let dbi = fields.next().is_some_and(|unit| unit.eq_ignore_ascii_case("dbi"));
+                    gain = Some(if dbi { value } else { value + 2.15 });
+                    continue;
+                }
+                "HORIZONTAL" => &mut horizontal,
+                "VERTICAL" => &mut vertical,
+                _ => continue,
+            };
+            let count: usize = fields.next().and_then(|count| count.parse().ok())
+                .ok_or_else(|| format!("{}:{}: invalid {} count", source, number + 1, keyword))?;
+            for _ in 0..count {
+                let (number, line) = lines.next().ok_or_else(|| format!("{}: {} cut is too short", source, keyword))?;
+                let values: Vec<f32> = line.split_whitespace().filter_map(|value| value.parse().ok()).collect();
+                if values.len() != 2 {
+                    return Err(format!("{}:{}: expected 'angle attenuation'", source, number + 1));
+                }
+                cut.push((values[0].rem_euclid(360.0), values[1]));
+            }
+        }
+        let gain = gain.ok_or_else(|| format!("{}: GAIN is missing", source))?;
+        if horizontal.is_empty() || vertical.is_empty() {
+            return Err(format!("{}: HORIZONTAL and VERTICAL cuts are required", source));
+        }
+        horizontal.sort_by(|a, b| a.0.total_cmp(&b.0));
+        vertical.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let attenuation = |cut: &[(f32, f32)], angle: f32| {
+            let angles: Vec<f32> = cut.iter().map(|entry| entry.0).collect();
+            let (i_0, i_1, t) = bracket_wrapped(&angles, angle);
+            lerp(cut[i_0].1, cut[i_1].1, t)
+        };
+        let azimuths: Vec<f32> = (0..360).map(|azimuth| azimuth as f32).collect();
+        let elevations: Vec<f32> = (-90..=90).map(|elevation| elevation as f32).collect();
+        let mut gains = Vec::with_capacity(azimuths.len() * elevations.len());
+        for &elevation in &elevations {
+            let vertical_attenuation = attenuation(&vertical, -elevation);
+            for &azimuth in &azimuths {
+                gains.push(gain - attenuation(&horizontal, -azimuth) - vertical_attenuation);
+            }
+        }
+        Ok(PatternTable { source: source.to_string(), azimuths, elevations, gains })
+    }
+}
+
+/// An antenna pattern and how the antenna is mounted. The mounting angles (degrees, same
+/// z-y'-x'' order as the UAV attitude) rotate the antenna frame relative to the local frame
+/// for the ground station and relative to the body frame for the UAV. Unrotated, the
+/// boresight points along x and the dipole axis along z.
+#[pyclass]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Antenna {
+    /// a built-in pattern name or a pattern file, see `AntennaPattern::load`
+    #[serde(deserialize_with = "deserialize_pattern")]
+    pub pattern: AntennaPattern,
+    #[pyo3(get, set)]
+    pub yaw: f32,
+    #[pyo3(get, set)]
+    pub pitch: f32,
+    #[pyo3(get, set)]
+    pub roll: f32,
+}
+
+impl Default for Antenna {
+    fn default() -> Self {
+        Antenna { pattern: AntennaPattern::Isotropic, yaw: 0.0, pitch: 0.0, roll: 0.0 }
+    }
+}
+
+impl Antenna {
+    pub fn is_isotropic(&self) -> bool {
+        self.pattern == AntennaPattern::Isotropic
+    }
+
+    /// linear power gain towards `direction`, given in the frame the antenna is mounted in
+    pub fn gain(&self, direction: &Direction) -> f32 {
+        let v = to_rotated_frame(from_direction(direction), self.yaw.to_radians(), self.pitch.to_radians(), self.roll.to_radians());
+        self.pattern.gain(v)
+    }
+}
+
+fn deserialize_pattern<'de, D: Deserializer<'de>>(deserializer: D) -> Result<AntennaPattern, D::Error> {
+    let name_or_path = String::deserialize(deserializer)?;
+    AntennaPattern::load(&name_or_path).map_err(|e| {
+        serde::de::Error::custom(format!(
+            "antenna pattern '{}' is neither one of {:?} nor a readable pattern file ({})",
+            name_or_path,
+            AntennaPattern::BUILT_IN,
+            e
+        ))
+    })
+}
+
+#[pymethods]
+impl Antenna {
+    #[new]
+    #[pyo3(signature = (pattern = "isotropic", yaw = 0.0, pitch = 0.0, roll = 0.0))]
+    fn py_new(pattern: &str, yaw: f32, pitch: f32, roll: f32) -> PyResult<Self> {
+        let pattern = AntennaPattern::load(pattern).map_err(|e| PyIOError::new_err(format!("{}: {}", pattern, e)))?;
+        Ok(Antenna { pattern, yaw, pitch, roll })
+    }
+
+    #[getter(pattern)]
+    fn py_pattern(&self) -> String {
+        self.pattern.name().to_string()
+    }
+
+    /// linear power gain towards the direction (radians) in the frame the antenna is mounted in
+    #[pyo3(name = "gain")]
+    fn py_gain(&self, azimuth: f32, elevation: f32) -> f32 {
+        self.gain(&Direction::new(azimuth, elevation))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Antenna(pattern={:?}, yaw={}, pitch={}, roll={})", self.pattern.name(), self.yaw, self.pitch, self.roll)
+    }
+}
+
+/// Applies the antenna gains of both ends to the paths of a model. Paths without known
+/// departure or arrival direction get unity gain at that end.
+pub struct WithAntennas {
+    model: Box<dyn ChannelModel>,
+    station: Antenna,
+    uav: Antenna,
+}
+
+impl WithAntennas {
+    pub fn new(model: Box<dyn ChannelModel>, scenario: &Scenario) -> Self {
+        WithAntennas { model, station: scenario.station_antenna.clone(), uav: scenario.uav_antenna.clone() }
+    }
+}
+
+impl ChannelModel for WithAntennas {
+    fn name(&self) -> &'static str {
+        self.model.name()
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f64)> {
+        self.model.parameters()
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f64) -> Result<(), UnknownParameter> {
+        self.model.set_parameter(name, value)
+    }
+
+    fn paths(&mut self, state: &State) -> Vec<PropagationPath> {
+        let mut paths = self.model.paths(state);
+        for path in &mut paths {
+            let station_gain = path.departure.map_or(1.0, |direction| self.station.gain(&direction));
+            let uav_gain = path.arrival.map_or(1.0, |direction| self.uav.gain(&direction));
+            path.loss /= (station_gain * uav_gain).sqrt();
+        }
+        paths
+    }
+
+    fn reset(&mut self) {
+        self.model.reset()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PathKind;
+
+    const CSV: &str = "azimuth,elevation,gain\n\
+        # 4 x 3 grid\n\
+        0,-45,0\n90,-45,-3\n180,-45,-10\n270,-45,-3\n\
+        0,0,6\n90,0,0\n180,0,-14\n270,0,0\n\
+        0,45,2\n90,45,-2\n180,45,-10\n270,45,-2\n";
+
+    const MSI: &str = "NAME test panel\nFREQUENCY 2450\nGAIN 10 dBd\n\
+        HORIZONTAL 4\n0 0\n90 10\n180 20\n270 10\n\
+        VERTICAL 4\n0 0\n90 30\n180 0\n270 30\n";
+
+    #[test]
+    fn csv_pattern_is_read_and_interpolated() {
+        let table = PatternTable::from_csv("test.csv", CSV).unwrap();
+        assert_eq!(table.azimuths, vec![0.0, 90.0, 180.0, 270.0]);
+        assert_eq!(table.elevations, vec![-45.0, 0.0, 45.0]);
+        assert_eq!(table.gain_db(0.0, 0.0), 6.0);
+        assert_eq!(table.gain_db(-90.0, 0.0), 0.0);
+        assert_eq!(table.gain_db(45.0, 0.0), 3.0);
+        assert_eq!(table.gain_db(315.0, 0.0), 3.0);
+        assert_eq!(table.gain_db(0.0, 22.5), 4.0);
+        // clamped beyond the outermost elevations
+        assert_eq!(table.gain_db(0.0, 90.0), 2.0);
+        assert!(PatternTable::from_csv("test.csv", "0,0,1\n90,0,2\n0,10,1\n").is_err());
+        assert!(PatternTable::from_csv("test.csv", "0,95,1\n").is_err());
+    }
+
+    #[test]
+    fn msi_pattern_is_read() {
+        let table = PatternTable::from_msi("test.msi", MSI).unwrap();
+        // 10 dBd are 12.15 dBi on boresight
+        assert!((table.gain_db(0.0, 0.0) - 12.15).abs() < 1e-4);
+        // horizontal angles run clockwise, so azimuth 90° (counterclockwise) is the 270° entry
+        assert!((table.gain_db(90.0, 0.0) - 2.15).abs() < 1e-4);
+        assert!((table.gain_db(180.0, 0.0) + 7.85).abs() < 1e-4);
+        // vertical angles run downwards, the attenuations of both cuts add up
+        assert!((table.gain_db(90.0, -90.0) + 27.85).abs() < 1e-4);
+        assert!(PatternTable::from_msi("test.msi", "HORIZONTAL 1\n0 0\nVERTICAL 1\n0 0\n").is_err());
+        assert!(PatternTable::from_msi("test.msi", "GAIN 3\nHORIZONTAL 2\n0 0\n").is_err());
+    }
+
+    #[test]
+    fn dipole_has_its_nulls_along_the_axis() {
+        let dipole = Antenna { pattern: AntennaPattern::Dipole, ..Antenna::default() };
+        assert_eq!(dipole.gain(&Direction::new(0.0, PI / 2.0)), MIN_GAIN);
+        assert_eq!(dipole.gain(&Direction::new(1.0, -PI / 2.0)), MIN_GAIN);
+        assert!((dipole.gain(&Direction::new(0.7, 0.0)) - DIPOLE_GAIN).abs() < 1e-5);
+        // mounted horizontally along y, the nulls point sideways
+        let horizontal = Antenna { pattern: AntennaPattern::Dipole, roll: 90.0, ..Antenna::default() };
+        assert!(horizontal.gain(&Direction::new(PI / 2.0, 0.0)) < 1e-3);
+        assert!((horizontal.gain(&Direction::new(0.0, 0.0)) - DIPOLE_GAIN).abs() < 1e-4);
+    }
+
+    struct FixedPaths(Vec<PropagationPath>);
+
+    impl ChannelModel for FixedPaths {
+        fn name(&self) -> &'static str {
+            "fixed_paths"
+        }
+
+        fn paths(&mut self, _state: &State) -> Vec<PropagationPath> {
+            self.0.clone()
+        }
+    }
+
+    #[test]
+    fn with_antennas_applies_the_gains_of_both_ends() {
+        let path = |departure: Option<Direction>, arrival: Option<Direction>| PropagationPath { departure, arrival, ..PropagationPath::new(PathKind::LineOfSight, 10.0, 0.0, 0.0) };
+        let model = FixedPaths(vec![
+            path(Some(Direction::new(0.0, PI / 2.0)), None),
+            path(Some(Direction::new(0.0, 0.0)), Some(Direction::new(PI, 0.0))),
+            path(None, None),
+        ]);
+        let dipole = Antenna { pattern: AntennaPattern::Dipole, ..Antenna::default() };
+        let scenario = Scenario { station_antenna: dipole.clone(), uav_antenna: dipole, ..Scenario::default() };
+        let losses: Vec<f32> = WithAntennas::new(Box::new(model), &scenario).paths(&State::at(0.0, 0.0, 0.0)).iter().map(|path| path.loss).collect();
+        // amplitude loss divided by the square root of the power gains
+        assert!((losses[0] - 10.0 / MIN_GAIN.sqrt()).abs() < 1e-2);
+        assert!((losses[1] - 10.0 / DIPOLE_GAIN).abs() < 1e-4);
+        assert_eq!(losses[2], 10.0);
+    }
+}
@@ -0,0 +1,553 @@
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::geometry::{add, cross, dot, norm, scale, sub, Vec3};
+use crate::{GeodeticPosition, GroundMaterial};
+
+/// height of a storey in metres, used for buildings that only give `building:levels`
+const LEVEL_HEIGHT: f32 = 3.0;
+/// relative tolerance along a segment for intersections at its end points
+const SEGMENT_TOLERANCE: f32 = 1e-4;
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Flat surface of the scene with all its rings of vertices (outer boundary and holes), an
+/// unbounded plane if it has no rings.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Facet {
+    rings: Vec<Vec<Vec3>>,
+    /// smallest and largest coordinates of the vertices, infinite for an unbounded plane
+    bounds: [Vec3; 2],
+    point: Vec3,
+    /// unit normal, facets reflect on both sides
+    pub normal: Vec3,
+    /// None for the ground, whose properties come from the scenario
+    pub material: Option<GroundMaterial>,
+}
+
+impl Facet {
+    /// polygon with the normal of its first ring (Newell's method), None if degenerate
+    fn polygon(rings: Vec<Vec<Vec3>>, material: GroundMaterial) -> Option<Facet> {
+        let outer = rings.first()?;
+        let mut normal = [0.0; 3];
+        for (a, b) in outer.iter().zip(outer.iter().cycle().skip(1)) {
+            normal = add(normal, cross(*a, *b));
+        }
+        let (point, length) = (outer[0], norm(normal));
+        let mut bounds = [point, point];
+        for vertex in outer {
+            bounds = [[0, 1, 2].map(|i| bounds[0][i].min(vertex[i])), [0, 1, 2].map(|i| bounds[1][i].max(vertex[i]))];
+        }
+        (length > 1e-6).then(|| Facet { point, normal: scale(normal, 1.0 / length), bounds, rings, material: Some(material) })
+    }
+
+    /// the ground plane z = 0 of the local frame
+    pub(crate) fn ground() -> Facet {
+        Facet { rings: Vec::new(), bounds: [[f32::NEG_INFINITY; 3], [f32::INFINITY; 3]], point: [0.0; 3], normal: [0.0, 0.0, 1.0], material: None }
+    }
+
+    /// whether a point on the plane of the facet lies within its rings (even-odd rule)
+    fn contains(&self, point: Vec3) -> bool {
+        if self.rings.is_empty() {
+            return true;
+        }
+        // project onto the coordinate plane the facet is least inclined to
+        let dominant = (0..3).max_by(|&i, &j| self.normal[i].abs().total_cmp(&self.normal[j].abs())).unwrap();
+        let (u, v) = ((dominant + 1) % 3, (dominant + 2) % 3);
+        let mut inside = false;
+        for ring in &self.rings {
+            for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+                if (a[v] > point[v]) != (b[v] > point[v]) && point[u] < a[u] + (point[v] - a[v]) * (b[u] - a[u]) / (b[v] - a[v]) {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+
+    fn signed_distance(&self, point: Vec3) -> f32 {
+        dot(sub(point, self.point), self.normal)
+    }
+
+    /// mirror image of a point at the plane of the facet
+    fn mirror(&self, point: Vec3) -> Vec3 {
+        sub(point, scale(self.normal, 2.0 * self.signed_distance(point)))
+    }
+
+    /// whether part of `other` lies on the same side of the plane of the facet as `point`
+    fn sees(&self, other: &Facet, point: Vec3) -> bool {
+        let side = self.signed_distance(point);
+        other.rings.is_empty() || other.rings[0].iter().any(|&vertex| self.signed_distance(vertex) * side > 1e-6)
+    }
+
+    /// point where the segment from `a` to `b` passes through the facet, if it does
+    fn intersection(&self, a: Vec3, b: Vec3) -> Option<Vec3> {
+        // segments whose bounding box misses the facet are rejected before the plane test
+        if (0..3).any(|i| a[i].max(b[i]) < self.bounds[0][i] - 1e-3 || a[i].min(b[i]) > self.bounds[1][i] + 1e-3) {
+            return None;
+        }
+        let (distance_a, distance_b) = (self.signed_distance(a), self.signed_distance(b));
+        if distance_a == distance_b {
+            return None;
+        }
+        let t = distance_a / (distance_a - distance_b);
+        if !(SEGMENT_TOLERANCE..=1.0 - SEGMENT_TOLERANCE).contains(&t) {
+            return None;
+        }
+        let point = add(a, scale(sub(b, a), t));
+        self.contains(point).then_some(point)
+    }
+}
+
+/// whether the segment from `a` to `b` passes none of the facets except the skipped ones
+pub(crate) fn is_clear(facets: &[Facet], a: Vec3, b: Vec3, skip: &[usize]) -> bool {
+    facets.iter().enumerate().all(|(index, facet)| skip.contains(&index) || facet.intersection(a, b).is_none())
+}
+
+/// Chain of specular reflections from source to target: the reflection points and the
+/// indices of the facets they lie on.
+pub(crate) struct SpecularPath {
+    pub points: Vec<Vec3>,
+    pub facets: Vec<usize>,
+}
+
+/// Specular reflections with up to `max_order` bounces found by the image method: the source
+/// is mirrored successively at the facets of a sequence, then the reflection points are found
+/// backwards from the target. Only paths with all legs unobstructed are returned. Consecutive
+/// facets of a sequence must lie at least partly in front of each other, on the side they
+/// reflect towards.
+pub(crate) fn specular_paths(facets: &[Facet], source: Vec3, target: Vec3, max_order: usize) -> Vec<SpecularPath> {
+    let mut paths = Vec::new();
+    let mut sequence = Vec::new();
+    let mut images = vec![source];
+    extend_sequence(facets, target, max_order, &mut sequence, &mut images, &mut paths);
+    paths
+}
+
+fn extend_sequence(
+    facets: &[Facet],
+    target: Vec3,
+    max_order: usize,
+    sequence: &mut Vec<usize>,
+    images: &mut Vec<Vec3>,
+    paths: &mut Vec<SpecularPath>,
+) {
+    if sequence.len() == max_order {
+        return;
+    }
+    let image = *images.last().unwrap();
+    for (index, facet) in facets.iter().enumerate() {
+        if sequence.last() == Some(&index) || facet.signed_distance(image).abs() < 1e-6 {
+            continue;
+        }
+        if let Some(&previous) = sequence.last() {
+            // the rays mirrored at the previous facet leave on the side of its source image and
+            // arrive at this facet on the side of its source image
+            let previous = &facets[previous];
+            if !previous.sees(facet, images[images.len() - 2]) || !facet.sees(previous, image) {
+                continue;
+            }
+        }
+        sequence.push(index);
+        images.push(facet.mirror(image));
+        paths.extend(trace_back(facets, target, sequence, images));
+        extend_sequence(facets, target, max_order, sequence, images, paths);
+        sequence.pop();
+        images.pop();
+    }
+}
+
+fn trace_back(facets: &[Facet], target: Vec3, sequence: &[usize], images: &[Vec3]) -> Option<SpecularPath> {
+    let mut points = Vec::with_capacity(sequence.len());
+    let mut next = target;
+    for (k, &index) in sequence.iter().enumerate().rev() {
+        next = facets[index].intersection(images[k + 1], next)?;
+        points.push(next);
+    }
+    points.reverse();
+    let source = images[0];
+    let first = sequence[0];
+    let last = sequence[sequence.len() - 1];
+    if !is_clear(facets, source, points[0], &[first]) || !is_clear(facets, points[points.len() - 1], target, &[last]) {
+        return None;
+    }
+    for k in 1..points.len() {
+        if !is_clear(facets, points[k - 1], points[k], &[sequence[k - 1], sequence[k]]) {
+            return None;
+        }
+    }
+    Some(SpecularPath { points, facets: sequence.to_vec() })
+}
+
+/// Building prism from a GeoJSON footprint, coordinates as given in the file.
+#[derive(Clone, Debug, PartialEq)]
+struct Footprint {
+    rings: Vec<Vec<[f64; 2]>>,
+    base: f32,
+    height: f32,
+    material: GroundMaterial,
+}
+
+impl Footprint {
+    /// walls, roof and, for buildings not standing on the ground, floor in the local frame
+    fn facets(&self, origin: Option<&GeodeticPosition>) -> Vec<Facet> {
+        let to_local = |&[a, b]: &[f64; 2], z: f32| match origin {
+            Some(origin) => {
+                let [x, y, _] = origin.local(&GeodeticPosition::new(b, a, origin.altitude));
+                [x, y, z]
+            }
+            None => [a as f32, b as f32, z],
+        };
+        let mut facets = Vec::new();
+        for ring in &self.rings {
+            for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+                let wall = vec![to_local(a, self.base), to_local(b, self.base), to_local(b, self.height), to_local(a, self.height)];
+                facets.extend(Facet::polygon(vec![wall], self.material));
+            }
+        }
+        let mut levels = vec![self.height];
+        if self.base > 0.0 {
+            levels.push(self.base);
+        }
+        for z in levels {
+            let rings = self.rings.iter().map(|ring| ring.iter().map(|point| to_local(point, z)).collect()).collect();
+            facets.extend(Facet::polygon(rings, self.material));
+        }
+        facets
+    }
+}
+
+fn material_named(name: &str) -> Option<GroundMaterial> {
+    GroundMaterial::deserialize(serde::de::value::StrDeserializer::<serde::de::value::Error>::new(name)).ok()
+}
+
+/// numeric GeoJSON property, OpenStreetMap exports often give numbers as strings ("12 m")
+fn number_property(properties: &Value, key: &str) -> Option<f32> {
+    match properties.get(key)? {
+        Value::Number(number) => number.as_f64().map(|number| number as f32),
+        Value::String(text) => text.trim().trim_end_matches('m').trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn ring(value: &Value) -> Option<Vec<[f64; 2]>> {
+    let mut ring: Vec<[f64; 2]> = value
+        .as_array()?
+        .iter()
+        .map(|position| Some([position.get(0)?.as_f64()?, position.get(1)?.as_f64()?]))
+        .collect::<Option<_>>()?;
+    // GeoJSON rings repeat the first position at the end
+    if ring.len() > 1 && ring.first() == ring.last() {
+        ring.pop();
+    }
+    (ring.len() >= 3).then_some(ring)
+}
+
+fn polygon_rings(value: &Value) -> Option<Vec<Vec<[f64; 2]>>> {
+    value.as_array()?.iter().map(ring).collect()
+}
+
+/// footprints of all Polygon and MultiPolygon features; heights from `height` or
+/// `building:levels`, optional `min_height` and `material`
+fn parse_geojson(content: &str, default_material: GroundMaterial) -> io::Result<Vec<Footprint>> {
+    let root: Value = serde_json::from_str(content).map_err(|e| invalid_data(e.to_string()))?;
+    let features = match root.get("type").and_then(Value::as_str) {
+        Some("FeatureCollection") => root.get("features").and_then(Value::as_array).cloned().unwrap_or_default(),
+        Some("Feature") => vec![root],
+        _ => return Err(invalid_data("expected a GeoJSON Feature or FeatureCollection".to_string())),
+    };
+    let mut footprints = Vec::new();
+    for (index, feature) in features.iter().enumerate() {
+        let properties = feature.get("properties").cloned().unwrap_or(Value::Null);
+        let geometry = feature.get("geometry").unwrap_or(&Value::Null);
+        let coordinates = geometry.get("coordinates").unwrap_or(&Value::Null);
+        let polygons = match geometry.get("type").and_then(Value::as_str) {
+            Some("Polygon") => vec![polygon_rings(coordinates)],
+            Some("MultiPolygon") => coordinates.as_array().map(|polygons| polygons.iter().map(polygon_rings).collect()).unwrap_or_default(),
+            // points, lines and the like carry no building volume
+            _ => continue,
+        };
+        let height = number_property(&properties, "height")
+            .or_else(|| number_property(&properties, "building:levels").map(|levels| levels * LEVEL_HEIGHT))
+            .ok_or_else(|| invalid_data(format!("feature {} has neither height nor building:levels", index)))?;
+        let material = match properties.get("material").and_then(Value::as_str) {
+            Some(name) => material_named(name).ok_or_else(|| invalid_data(format!("feature {}: unknown material '{}'", index, name)))?,
+            None => default_material,
+        };
+        let base = number_property(&properties, "min_height").unwrap_or(0.0);
+        for rings in polygons {
+            let rings = rings.ok_or_else(|| invalid_data(format!("feature {} has malformed coordinates", index)))?;
+            footprints.push(Footprint { rings, base, height, material });
+        }
+    }
+    Ok(footprints)
+}
+
+/// faces of a Wavefront OBJ mesh, materials from `usemtl` where they name a known material
+fn parse_obj(content: &str, default_material: GroundMaterial) -> io::Result<Vec<Facet>> {
+    let mut vertices: Vec<Vec3> = Vec::new();
+    let mut material = default_material;
+    let mut facets = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let error = || invalid_data(format!("line {}: malformed '{}'", number + 1, line.trim()));
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("v") => {
+                let coordinates: Vec<f32> = fields.take(3).map(str::parse).collect::<Result<_, _>>().map_err(|_| error())?;
+                vertices.push(coordinates.try_into().map_err(|_| error())?);
+            }
+            Some("f") => {
+                let face = fields
+                    .map(|vertex| {
+                        let index: i64 = vertex.split('/').next()?.parse().ok()?;
+                        // indices are one based, negative ones count back from the last vertex
+                        let index = if index < 0 { vertices.len() as i64 + index } else { index - 1 };
+                        vertices.get(usize::try_from(index).ok()?).copied()
+                    })
+                    .collect::<Option<Vec<Vec3>>>()
+                    .ok_or_else(error)?;
+                facets.extend(Facet::polygon(vec![face], material));
+            }
+            Some("usemtl") => material = fields.next().and_then(material_named).unwrap_or(default_material),
+            _ => {}
+        }
+    }
+    Ok(facets)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Surfaces {
+    Footprints(Vec<Footprint>),
+    Mesh(Vec<Facet>),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BuildingsConfig {
+    file: String,
+    #[serde(default = "default_material")]
+    material: GroundMaterial,
+    #[serde(default = "default_max_order")]
+    max_order: usize,
+    #[serde(default = "default_max_paths")]
+    max_paths: usize,
+}
+
+fn default_material() -> GroundMaterial {
+    GroundMaterial::Concrete
+}
+
+fn default_max_order() -> usize {
+    1
+}
+
+fn default_max_paths() -> usize {
+    20
+}
+
+/// Building scene for the ray-tracing model, loaded from a GeoJSON file of footprints
+/// (`.geojson`, `.json`) or a Wavefront OBJ mesh (`.obj`).
+///
+/// GeoJSON footprints are extruded from `min_height` (default 0) to `height` (or
+/// `building:levels` times 3 m); their coordinates are longitude and latitude if the scenario
+/// has a geodetic origin, else x and y in the local frame. OBJ vertices are always local
+/// coordinates (x east, y north, z up). Surfaces take their material from the GeoJSON
+/// `material` property or the OBJ `usemtl` name if it is a known material, else `material`.
+#[pyclass]
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(try_from = "BuildingsConfig")]
+pub struct Buildings {
+    #[pyo3(get)]
+    pub file: String,
+    #[pyo3(get)]
+    pub material: GroundMaterial,
+    /// highest number of reflections per path; the cost grows with the number of surfaces to
+    /// the power of the order, so above 1 it is only suited to small scenes
+    #[pyo3(get, set)]
+    pub max_order: usize,
+    /// only the strongest reflected paths are kept
+    #[pyo3(get, set)]
+    pub max_paths: usize,
+    surfaces: Arc<Surfaces>,
+}
+
+impl TryFrom<BuildingsConfig> for Buildings {
+    type Error = io::Error;
+
+    fn try_from(config: BuildingsConfig) -> io::Result<Buildings> {
+        let mut buildings = Buildings::load(&config.file, config.material)?;
+        buildings.max_order = config.max_order;
+        buildings.max_paths = config.max_paths;
+        Ok(buildings)
+    }
+}
+
+impl fmt::Debug for Buildings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Buildings {{ file: {:?}, material: {:?}, max_order: {}, max_paths: {} }}",
+            self.file, self.material, self.max_order, self.max_paths
+        )
+    }
+}
+
+impl Buildings {
+    pub fn load(file: &str, material: GroundMaterial) -> io::Result<Buildings> {
+        let content = std::fs::read_to_string(file)?;
+        let extension = Path::new(file).extension().and_then(|extension| extension.to_str()).unwrap_or("").to_ascii_lowercase();
+        let in_file = |e: io::Error| invalid_data(format!("{}: {}", file, e));
+        let surfaces = match extension.as_str() {
+            "obj" => Surfaces::Mesh(parse_obj(&content, material).map_err(in_file)?),
+            "geojson" | "json" => Surfaces::Footprints(parse_geojson(&content, material).map_err(in_file)?),
+            _ => return Err(invalid_data(format!("{}: expected a .geojson, .json or .obj file", file))),
+        };
+        Ok(Buildings {
+            file: file.to_string(),
+            material,
+            max_order: default_max_order(),
+            max_paths: default_max_paths(),
+            surfaces: Arc::new(surfaces),
+        })
+    }
+
+    /// all surfaces in the local frame whose geodetic origin is `origin`
+    pub(crate) fn facets(&self, origin: Option<&GeodeticPosition>) -> Vec<Facet> {
+        match self.surfaces.as_ref() {
+            Surfaces::Footprints(footprints) => footprints.iter().flat_map(|footprint| footprint.facets(origin)).collect(),
+            Surfaces::Mesh(facets) => facets.clone(),
+        }
+    }
+}
+
+#[pymethods]
+impl Buildings {
+    #[new]
+    #[pyo3(signature = (file, material = GroundMaterial::Concrete, max_order = 1, max_paths = 20))]
+    fn py_new(file: &str, material: GroundMaterial, max_order: usize, max_paths: usize) -> PyResult<Buildings> {
+        let mut buildings = Buildings::load(file, material).map_err(|e| PyIOError::new_err(e.to_string()))?;
+        buildings.max_order = max_order;
+        buildings.max_paths = max_paths;
+        Ok(buildings)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GEOJSON: &str = r#"{
+        "type": "FeatureCollection",
+        "features": [
+            {"type": "Feature", "properties": {"height": "12 m"},
+             "geometry": {"type": "Polygon", "coordinates": [[[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]]]}},
+            {"type": "Feature", "properties": {"building:levels": 4, "min_height": 3, "material": "glass"},
+             "geometry": {"type": "MultiPolygon", "coordinates": [
+                 [[[20, 0], [30, 0], [30, 10], [20, 0]]],
+                 [[[40, 0], [50, 0], [50, 10], [40, 10]]]]}},
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [5, 5]}}
+        ]
+    }"#;
+
+    fn wall(x: f32, y: (f32, f32), height: f32) -> Facet {
+        Facet::polygon(vec![vec![[x, y.0, 0.0], [x, y.1, 0.0], [x, y.1, height], [x, y.0, height]]], GroundMaterial::Concrete).unwrap()
+    }
+
+    fn length(source: Vec3, path: &SpecularPath, target: Vec3) -> f32 {
+        let points: Vec<Vec3> = std::iter::once(source).chain(path.points.iter().copied()).chain(std::iter::once(target)).collect();
+        points.windows(2).map(|leg| norm(sub(leg[1], leg[0]))).sum()
+    }
+
+    #[test]
+    fn geojson_footprints_are_extruded() {
+        let footprints = parse_geojson(GEOJSON, GroundMaterial::Concrete).unwrap();
+        assert_eq!(footprints.len(), 3);
+        assert_eq!((footprints[0].base, footprints[0].height, footprints[0].material), (0.0, 12.0, GroundMaterial::Concrete));
+        // the closing position is dropped, the triangle keeps its three corners
+        assert_eq!(footprints[0].rings[0].len(), 4);
+        assert_eq!(footprints[1].rings[0].len(), 3);
+        assert_eq!((footprints[2].base, footprints[2].height, footprints[2].material), (3.0, 12.0, GroundMaterial::Glass));
+
+        // four walls and the roof, plus the floor of the raised building
+        let facets = footprints[0].facets(None);
+        assert_eq!(facets.len(), 5);
+        assert_eq!(facets[4].normal[2].abs(), 1.0);
+        assert!(facets[4].contains([5.0, 5.0, 12.0]) && !facets[4].contains([15.0, 5.0, 12.0]));
+        assert_eq!(footprints[2].facets(None).len(), 6);
+
+        assert!(parse_geojson(r#"{"type": "Feature", "properties": {}, "geometry": {"type": "Polygon", "coordinates": [[[0, 0], [1, 0], [1, 1]]]}}"#, GroundMaterial::Concrete).is_err());
+        assert!(parse_geojson(&GEOJSON.replace("glass", "cardboard"), GroundMaterial::Concrete).is_err());
+        assert!(parse_geojson(r#"{"type": "Point", "coordinates": [0, 0]}"#, GroundMaterial::Concrete).is_err());
+    }
+
+    #[test]
+    fn obj_faces_are_read() {
+        let content = "# wall\nv 0 0 0\nv 10 0 0\nv 10 0 5\nv 0 0 5\nusemtl metal\nf 1/1/1 2/2/1 3/3/1 4/4/1\nusemtl paint\nf -4 -3 -2\n";
+        let facets = parse_obj(content, GroundMaterial::Brick).unwrap();
+        assert_eq!(facets.len(), 2);
+        assert_eq!(facets[0].material, Some(GroundMaterial::Metal));
+        assert_eq!(facets[0].normal[1].abs(), 1.0);
+        assert_eq!(facets[0].bounds, [[0.0, 0.0, 0.0], [10.0, 0.0, 5.0]]);
+        assert_eq!(facets[1].material, Some(GroundMaterial::Brick));
+        assert!(parse_obj("v 0 0 0\nv 1 0 0\nf 1 2 3\n", GroundMaterial::Brick).is_err());
+        assert!(parse_obj("v 0 0\n", GroundMaterial::Brick).is_err());
+    }
+
+    #[test]
+    fn single_wall_reflects_at_the_image_point() {
+        let (source, target) = ([0.0, 0.0, 10.0], [0.0, 40.0, 30.0]);
+        let paths = specular_paths(&[wall(50.0, (-100.0, 100.0), 50.0)], source, target, 1);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].points, vec![[50.0, 20.0, 20.0]]);
+        assert!((length(source, &paths[0], target) - norm(sub([100.0, 0.0, 10.0], target))).abs() < 1e-3);
+
+        // with the ground, the double bounce via ground and wall is as long as the line from
+        // the image mirrored at both; the other order would hit the ground behind the wall
+        let facets = [Facet::ground(), wall(50.0, (-100.0, 100.0), 50.0)];
+        let paths = specular_paths(&facets, source, target, 2);
+        let mut sequences: Vec<_> = paths.iter().map(|path| path.facets.clone()).collect();
+        sequences.sort();
+        assert_eq!(sequences, vec![vec![0], vec![0, 1], vec![1]]);
+        let double = paths.iter().find(|path| path.facets.len() == 2).unwrap();
+        assert_eq!(double.points, vec![[25.0, 10.0, 0.0], [50.0, 20.0, 10.0]]);
+        assert!((length(source, double, target) - norm(sub([100.0, 0.0, -10.0], target))).abs() < 1e-3);
+
+        // a small wall across the incident leg blocks the reflection
+        let blocked = [wall(50.0, (-100.0, 100.0), 50.0), wall(25.0, (5.0, 15.0), 20.0)];
+        assert!(specular_paths(&blocked, source, target, 1).is_empty());
+    }
+
+    #[test]
+    fn facets_behind_the_last_reflection_are_skipped() {
+        let (front, back) = (wall(50.0, (-100.0, 100.0), 50.0), wall(60.0, (-100.0, 100.0), 50.0));
+        let (source, target) = ([0.0, 0.0, 10.0], [0.0, 40.0, 10.0]);
+        // mirrored at the front wall, the rays travel back towards the source
+        assert!(!front.sees(&back, source));
+        assert!(front.sees(&Facet::ground(), source));
+        let paths = specular_paths(&[front, back], source, target, 2);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].facets, vec![0]);
+    }
+
+    #[test]
+    fn bounding_boxes_reject_distant_segments() {
+        let facet = wall(50.0, (-10.0, 10.0), 20.0);
+        assert_eq!(facet.intersection([0.0, 0.0, 5.0], [100.0, 0.0, 5.0]), Some([50.0, 0.0, 5.0]));
+        assert_eq!(facet.intersection([0.0, 30.0, 5.0], [100.0, 30.0, 5.0]), None);
+        assert!(is_clear(&[facet], [0.0, 0.0, 30.0], [100.0, 0.0, 30.0], &[]));
+    }
+}
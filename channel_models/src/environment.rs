@@ -0,0 +1,233 @@
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use serde::{Deserialize, Deserializer};
+use std::io;
+use std::path::Path;
+
+/// (intercept, slope, standard deviation) of a linear fit in log10 domain over the distance
+/// beyond `distance_offset`, see `Environment`.
+pub type RayStatistics = (f32, f32, f32);
+
+/// Statistics of the intermittent multipath components of the nine-ray model for one kind
+/// of terrain, as published per environment in the AG channel measurement campaign of
+/// DOI 10.1109/TVT.2017.2659651.
+///
+/// For ray k the on-probability, the duration of a state (metres travelled) and the excess
+/// delay (nanoseconds) are drawn as `10^(intercept + slope * max(d - distance_offset, 0) + N(0, std))`
+/// with the UAV distance `d`. The tables may describe fewer or more than seven rays.
+///
+/// Besides the built-in presets, tables can be loaded from TOML or JSON files with the keys
+/// of this struct, see `environments/near_urban_cleveland.toml`.
+#[pyclass]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Environment {
+    #[pyo3(get)]
+    pub name: String,
+    pub on_probability: Vec<RayStatistics>,
+    pub duration: Vec<RayStatistics>,
+    pub excess_delay: Vec<RayStatistics>,
+    /// mean of the additional loss of an intermittent ray relative to the direct path in dB
+    #[pyo3(get)]
+    pub additional_loss_mean: f32,
+    /// standard deviation of the additional loss in dB
+    #[pyo3(get)]
+    pub additional_loss_std: f32,
+    /// distance in metres from which on the slope of the fits applies
+    #[serde(default = "default_distance_offset")]
+    #[pyo3(get)]
+    pub distance_offset: f32,
+}
+
+fn default_distance_offset() -> f32 {
+    19_000.0
+}
+
+// parameters from 'NEAR-URBAN CLEVELAND' settings
+#[allow(clippy::excessive_precision)]
+const NEAR_URBAN_CLEVELAND_ON_PROBABILITIES: [RayStatistics; 7] = [
+    (0.4480, -0.1457, 0.906256034),
+    (-2.3302, -0.0630, 0.844452485),
+    (-2.3578, -0.1367, 0.88391176),
+    (-2.0716, -0.2233, 0.845517593),
+    (-1.9377, -0.2502, 0.500699511),
+    (-4.1835, 0.3570, 0.0),
+    (-6.2697, 0.9563, 0.0),
+];
+// −
+// -
+
+// Ma[^0-9]*([0-9.-]*) ([0-9.-]*) ([0-9.-]*)[^\n]*
+//
+
+// Med[^0-9]*([0-9.-]*) ([0-9.-]*) ([0-9.-]*)[^\n]*
+//
+
+// M[^0-9]*([0-9.-]*) ([0-9.-]*) ([0-9.-]*)[^\n]*
+// \t($1, $2, $3),\n
+#[allow(clippy::excessive_precision)]
+const NEAR_URBAN_CLEVELAND_DURATION: [RayStatistics; 7] = [
+	(0.5513, -0.0450, 0.5195190083144215),
+	(0.2883, 0.0037, 0.4635730794599704),
+	(0.1246, -0.0212, 0.5709640969448079),
+	(0.0022, 0.0036, 0.6687301398920195),
+	(0.5779, 0.1470, 0.3752332607858744),
+	(2.1444, 0.7495, 0.0),
+	(1.5143, 0.5968, 0.0),
+];
+#[allow(clippy::excessive_precision)]
+const NEAR_URBAN_CLEVELAND_EXCESS_DELAY: [RayStatistics; 7] = [
+	(2.3210, -0.0047, 0.34481879299133333),
+	(2.4248, 0.0029, 0.3590264614203248),
+	(2.4914, 0.0186, 0.31432467291003424),
+	(2.5198, 0.0253, 0.35482389998420344),
+	(2.6964, 0.0168, 0.0888819441731559),
+	(2.7381, 0.0281, 0.0),
+	(2.9929, -0.0343, 0.0),
+];
+
+impl Environment {
+    /// names of the built-in presets, accepted by `Environment::preset`; tables of other
+    /// terrains are loaded from files, see `from_file`
+    pub const PRESETS: [&'static str; 1] = ["near_urban_cleveland"];
+
+    pub fn preset(name: &str) -> Option<Environment> {
+        match name {
+            "near_urban_cleveland" => Some(Environment {
+                name: name.to_string(),
+                on_probability: NEAR_URBAN_CLEVELAND_ON_PROBABILITIES.to_vec(),
+                duration: NEAR_URBAN_CLEVELAND_DURATION.to_vec(),
+                excess_delay: NEAR_URBAN_CLEVELAND_EXCESS_DELAY.to_vec(),
+                additional_loss_mean: 30.3,
+                additional_loss_std: 4.1,
+                distance_offset: default_distance_offset(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Reads tables from a `.json` file or, for any other extension, a TOML file.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Environment> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        let environment: Environment = if path.extension().is_some_and(|extension| extension == "json") {
+            serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        } else {
+            toml::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        };
+        environment.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(environment)
+    }
+
+    /// a built-in preset if `name_or_path` names one, else the tables in the given file
+    pub fn load(name_or_path: &str) -> io::Result<Environment> {
+        match Environment::preset(name_or_path) {
+            Some(environment) => Ok(environment),
+            None => Environment::from_file(name_or_path),
+        }
+    }
+
+    pub fn ray_count(&self) -> usize {
+        self.on_probability.len()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.duration.len() != self.ray_count() || self.excess_delay.len() != self.ray_count() {
+            return Err(format!(
+                "environment '{}': on_probability, duration and excess_delay must describe the same number of rays",
+                self.name
+            ));
+        }
+        if self.ray_count() > u8::MAX as usize {
+            return Err(format!("environment '{}': at most {} rays are supported", self.name, u8::MAX));
+        }
+        let std_devs = self.on_probability.iter().chain(&self.duration).chain(&self.excess_delay).map(|statistics| statistics.2);
+        if std_devs.chain([self.additional_loss_std]).any(|std_dev| std_dev < 0.0 || std_dev.is_nan()) {
+            return Err(format!("environment '{}': standard deviations must not be negative", self.name));
+        }
+        Ok(())
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment::preset("near_urban_cleveland").unwrap()
+    }
+}
+
+/// Deserializes an environment given either by preset name / file path or as inline tables.
+pub(crate) fn deserialize_environment<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Environment, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Source {
+        NameOrPath(String),
+        Inline(Environment),
+    }
+    match Source::deserialize(deserializer)? {
+        Source::NameOrPath(name_or_path) => Environment::load(&name_or_path).map_err(|e| {
+            serde::de::Error::custom(format!(
+                "environment '{}' is neither one of {:?} nor a readable table file ({})",
+                name_or_path,
+                Environment::PRESETS,
+                e
+            ))
+        }),
+        Source::Inline(environment) => {
+            environment.validate().map_err(serde::de::Error::custom)?;
+            Ok(environment)
+        }
+    }
+}
+
+#[pymethods]
+impl Environment {
+    #[staticmethod]
+    #[pyo3(name = "preset")]
+    fn py_preset(name: &str) -> PyResult<Environment> {
+        Environment::preset(name).ok_or_else(|| {
+            PyValueError::new_err(format!("unknown environment '{}', available: {:?}", name, Environment::PRESETS))
+        })
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "from_file")]
+    fn py_from_file(path: &str) -> PyResult<Environment> {
+        Environment::from_file(path).map_err(|e| PyIOError::new_err(format!("{}: {}", path, e)))
+    }
+
+    #[staticmethod]
+    fn presets() -> Vec<&'static str> {
+        Environment::PRESETS.to_vec()
+    }
+
+    #[getter(ray_count)]
+    fn py_ray_count(&self) -> usize {
+        self.ray_count()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Environment(name={:?}, ray_count={})", self.name, self.ray_count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_valid() {
+        for name in Environment::PRESETS {
+            let preset = Environment::preset(name).unwrap();
+            assert_eq!(preset.name, name);
+            assert_eq!(preset.ray_count(), 7);
+            preset.validate().unwrap();
+        }
+        assert!(Environment::preset("urban").is_none());
+    }
+
+    #[test]
+    fn table_file_matches_the_preset() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/environments/near_urban_cleveland.toml");
+        assert_eq!(Environment::from_file(path).unwrap(), Environment::default());
+    }
+}
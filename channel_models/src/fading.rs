@@ -0,0 +1,253 @@
+use num::complex::Complex32;
+use pyo3::prelude::*;
+use rand_distr::{Distribution, Uniform};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use crate::models::{seeded_rng, ModelRng};
+use crate::{PathKind, PropagationPath, Scenario};
+
+/// random stream of the fading processes, keeps them independent of the other stochastic parts
+/// that share the same seed
+const FADING_STREAM: u64 = 6;
+/// amplitudes below this are clamped to keep the losses finite in deep fades
+const MIN_AMPLITUDE: f32 = 1e-6;
+
+/// Small-scale fading applied between position updates. Every path gets a Rician process
+/// with the K-factor (in dB) of its kind; +inf disables the fading of that kind, -inf gives
+/// Rayleigh fading. The mean power of a path is left unchanged.
+#[pyclass]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Fading {
+    #[pyo3(get, set)]
+    pub enabled: bool,
+    /// K-factor of the line-of-sight path in dB
+    #[pyo3(get, set)]
+    pub los_k_factor: f32,
+    /// K-factor of ground and building reflections in dB
+    #[pyo3(get, set)]
+    pub reflection_k_factor: f32,
+    /// K-factor of intermittent rays and clusters in dB
+    #[pyo3(get, set)]
+    pub scattered_k_factor: f32,
+    /// maximum Doppler shift of the scattered components in Hz, 0 derives it from the UAV speed
+    #[pyo3(get, set)]
+    pub max_doppler: f32,
+    /// sinusoids per quadrature component of each process
+    #[pyo3(get, set)]
+    pub sinusoids: usize,
+}
+
+impl Default for Fading {
+    fn default() -> Self {
+        Fading {
+            enabled: false,
+            los_k_factor: 10.0,
+            reflection_k_factor: 0.0,
+            scattered_k_factor: f32::NEG_INFINITY,
+            max_doppler: 0.0,
+            sinusoids: 16,
+        }
+    }
+}
+
+impl Fading {
+    /// K-factor in dB of paths of the given kind
+    pub fn k_factor(&self, kind: PathKind) -> f32 {
+        match kind {
+            PathKind::LineOfSight => self.los_k_factor,
+            PathKind::GroundReflection | PathKind::Reflection(_) => self.reflection_k_factor,
+            PathKind::Intermittent(_) | PathKind::Cluster(_) => self.scattered_k_factor,
+        }
+    }
+
+    /// the configured maximum Doppler shift, or the one of a UAV moving at the given velocity
+    pub fn max_doppler_for(&self, scenario: &Scenario, velocity: [f32; 3]) -> f32 {
+        if self.max_doppler > 0.0 {
+            self.max_doppler
+        } else {
+            velocity.iter().map(|v| v * v).sum::<f32>().sqrt() / scenario.lambda()
+        }
+    }
+}
+
+#[pymethods]
+impl Fading {
+    #[new]
+    #[pyo3(signature = (
+        enabled = false,
+        los_k_factor = 10.0,
+        reflection_k_factor = 0.0,
+        scattered_k_factor = f32::NEG_INFINITY,
+        max_doppler = 0.0,
+        sinusoids = 16,
+    ))]
+    fn py_new(enabled: bool, los_k_factor: f32, reflection_k_factor: f32, scattered_k_factor: f32, max_doppler: f32, sinusoids: usize) -> Self {
+        Fading { enabled, los_k_factor, reflection_k_factor, scattered_k_factor, max_doppler, sinusoids }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// Unit-power Rayleigh process as a sum of sinusoids (Zheng and Xiao, DOI
+/// 10.1109/LCOMM.2002.1010867): (cos of the arrival angle, in-phase phase, quadrature phase)
+/// per sinusoid.
+struct SumOfSinusoids {
+    sinusoids: Vec<(f32, f32, f32)>,
+}
+
+impl SumOfSinusoids {
+    fn new(count: usize, rng: &mut ModelRng) -> Self {
+        let uniform = Uniform::new(-PI, PI);
+        let theta = uniform.sample(rng);
+        let count = count.max(1);
+        let sinusoids = (1..=count)
+            .map(|n| {
+                let alpha = (2.0 * PI * n as f32 - PI + theta) / (4.0 * count as f32);
+                (alpha.cos(), uniform.sample(rng), uniform.sample(rng))
+            })
+            .collect();
+        SumOfSinusoids { sinusoids }
+    }
+
+    /// value after `cycles` periods of the maximum Doppler frequency
+    fn value(&self, cycles: f64) -> Complex32 {
+        let (mut re, mut im) = (0.0, 0.0);
+        for &(cos_alpha, phi, psi) in &self.sinusoids {
+            // the phase only matters modulo 2 pi, reducing in f64 keeps long runs accurate
+            let phase = ((2.0 * std::f64::consts::PI * cycles * cos_alpha as f64) % (2.0 * std::f64::consts::PI)) as f32;
+            re += (phase + phi).cos();
+            im += (phase + psi).cos();
+        }
+        Complex32::new(re, im) / (self.sinusoids.len() as f32).sqrt()
+    }
+}
+
+/// The running fading processes, one per path kind. The processes advance with the integral
+/// of the maximum Doppler shift over time, so a changing UAV speed changes the fading rate
+/// without phase jumps.
+pub struct FadingProcesses {
+    fading: Fading,
+    seed: u64,
+    rng: ModelRng,
+    processes: HashMap<PathKind, SumOfSinusoids>,
+    /// elapsed periods of the maximum Doppler frequency
+    cycles: f64,
+}
+
+impl FadingProcesses {
+    pub fn new(fading: &Fading, seed: u64) -> Self {
+        let mut rng = seeded_rng(seed);
+        rng.set_stream(FADING_STREAM);
+        FadingProcesses { fading: fading.clone(), seed, rng, processes: HashMap::new(), cycles: 0.0 }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.fading.enabled
+    }
+
+    /// lets the processes evolve for `duration` seconds at the given maximum Doppler shift in Hz
+    pub fn advance(&mut self, duration: f32, max_doppler: f32) {
+        self.cycles += duration as f64 * max_doppler as f64;
+    }
+
+    /// the paths with the current fading applied to their amplitude and phase
+    pub fn apply(&mut self, paths: &[PropagationPath]) -> Vec<PropagationPath> {
+        if !self.fading.enabled {
+            return paths.to_vec();
+        }
+        paths
+            .iter()
+            .map(|path| {
+                let k_factor = self.fading.k_factor(path.kind);
+                if k_factor == f32::INFINITY {
+                    return path.clone();
+                }
+                let k = 10.0_f32.powf(k_factor / 10.0);
+                let (fading, rng) = (&self.fading, &mut self.rng);
+                let process = self.processes.entry(path.kind).or_insert_with(|| SumOfSinusoids::new(fading.sinusoids, rng));
+                let gain = (k / (k + 1.0)).sqrt() + process.value(self.cycles) * (1.0 / (k + 1.0)).sqrt();
+                let (amplitude, phase) = gain.to_polar();
+                PropagationPath { loss: path.loss / amplitude.max(MIN_AMPLITUDE), phase: path.phase + phase, ..path.clone() }
+            })
+            .collect()
+    }
+
+    /// back to the state right after construction
+    pub fn reset(&mut self) {
+        *self = FadingProcesses::new(&self.fading, self.seed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fading() -> Fading {
+        Fading { enabled: true, ..Fading::default() }
+    }
+
+    /// complex gains of a lossless path of the given kind over 1000 periods of the maximum
+    /// Doppler frequency, 20 samples per period
+    fn gains(processes: &mut FadingProcesses, kind: PathKind) -> Vec<Complex32> {
+        let path = PropagationPath::new(kind, 1.0, 0.0, 0.0);
+        (0..20_000)
+            .map(|_| {
+                processes.advance(0.001, 50.0);
+                processes.apply(std::slice::from_ref(&path))[0].gain()
+            })
+            .collect()
+    }
+
+    fn mean_power(gains: &[Complex32]) -> f32 {
+        gains.iter().map(|gain| gain.norm_sqr()).sum::<f32>() / gains.len() as f32
+    }
+
+    #[test]
+    fn sum_of_sinusoids_has_unit_power() {
+        for seed in [1, 2, 3] {
+            let mut rng = seeded_rng(seed);
+            let process = SumOfSinusoids::new(16, &mut rng);
+            let power = (0..20_000).map(|i| process.value(i as f64 * 0.05).norm_sqr()).sum::<f32>() / 20_000.0;
+            assert!((power - 1.0).abs() < 0.05, "seed {}: {}", seed, power);
+        }
+        let gains = gains(&mut FadingProcesses::new(&fading(), 7), PathKind::Intermittent(0));
+        assert!((mean_power(&gains) - 1.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn rician_k_factor_is_reproduced() {
+        for k_factor in [0.0_f32, 6.0, 10.0] {
+            let mut processes = FadingProcesses::new(&Fading { los_k_factor: k_factor, ..fading() }, 7);
+            let gains = gains(&mut processes, PathKind::LineOfSight);
+            let mean = gains.iter().sum::<Complex32>() / gains.len() as f32;
+            let power = mean_power(&gains);
+            let estimate = 10.0 * (mean.norm_sqr() / (power - mean.norm_sqr())).log10();
+            assert!((power - 1.0).abs() < 0.05, "K {} dB: power {}", k_factor, power);
+            assert!((estimate - k_factor).abs() < 1.0, "K {} dB estimated as {} dB", k_factor, estimate);
+        }
+    }
+
+    #[test]
+    fn infinite_k_factor_disables_fading() {
+        let mut processes = FadingProcesses::new(&Fading { los_k_factor: f32::INFINITY, ..fading() }, 7);
+        let path = PropagationPath::new(PathKind::LineOfSight, 3.0, 1e-6, 0.5);
+        processes.advance(0.1, 50.0);
+        assert_eq!(processes.apply(std::slice::from_ref(&path)), vec![path]);
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_fading() {
+        let mut first = FadingProcesses::new(&fading(), 42);
+        let mut second = FadingProcesses::new(&fading(), 42);
+        let sequence = gains(&mut first, PathKind::GroundReflection);
+        assert_eq!(sequence, gains(&mut second, PathKind::GroundReflection));
+        assert_ne!(sequence, gains(&mut FadingProcesses::new(&fading(), 43), PathKind::GroundReflection));
+        first.reset();
+        assert_eq!(sequence, gains(&mut first, PathKind::GroundReflection));
+    }
+}
@@ -0,0 +1,219 @@
+use pyo3::prelude::*;
+use serde::Deserialize;
+use std::f32::consts::FRAC_PI_2;
+
+/// semi-major axis of the WGS84 ellipsoid in metres
+const WGS84_A: f64 = 6_378_137.0;
+/// flattening of the WGS84 ellipsoid
+const WGS84_F: f64 = 1.0 / 298.257_223_563;
+/// first eccentricity squared of the WGS84 ellipsoid
+const WGS84_E2: f64 = WGS84_F * (2.0 - WGS84_F);
+/// mean earth radius (IUGG) in metres, used for great-circle distances
+const MEAN_EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// WGS84 latitude and longitude in degrees and altitude in metres (above the same reference as
+/// the elevation model in use, usually mean sea level, or above the ellipsoid).
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GeodeticPosition {
+    #[pyo3(get, set)]
+    pub latitude: f64,
+    #[pyo3(get, set)]
+    pub longitude: f64,
+    #[serde(default)]
+    #[pyo3(get, set)]
+    pub altitude: f64,
+}
+
+impl GeodeticPosition {
+    pub fn new(latitude: f64, longitude: f64, altitude: f64) -> Self {
+        GeodeticPosition { latitude, longitude, altitude }
+    }
+
+    /// earth-centred, earth-fixed coordinates in metres
+    pub fn to_ecef(&self) -> [f64; 3] {
+        let (latitude, longitude) = (self.latitude.to_radians(), self.longitude.to_radians());
+        let n = WGS84_A / (1.0 - WGS84_E2 * latitude.sin().powi(2)).sqrt();
+        [
+            (n + self.altitude) * latitude.cos() * longitude.cos(),
+            (n + self.altitude) * latitude.cos() * longitude.sin(),
+            (n * (1.0 - WGS84_E2) + self.altitude) * latitude.sin(),
+        ]
+    }
+
+    /// inverse of `to_ecef`, iterates the latitude to well below a millimetre
+    pub fn from_ecef(ecef: [f64; 3]) -> GeodeticPosition {
+        let [x, y, z] = ecef;
+        let p = x.hypot(y);
+        let mut latitude = z.atan2(p * (1.0 - WGS84_E2));
+        let mut altitude = 0.0;
+        for _ in 0..5 {
+            let n = WGS84_A / (1.0 - WGS84_E2 * latitude.sin().powi(2)).sqrt();
+            altitude = p * latitude.cos() + z * latitude.sin() - WGS84_A * WGS84_A / n;
+            latitude = z.atan2(p * (1.0 - WGS84_E2 * n / (n + altitude)));
+        }
+        GeodeticPosition::new(latitude.to_degrees(), y.atan2(x).to_degrees(), altitude)
+    }
+
+    /// rows of the rotation from ECEF to the east-north-up frame at this position
+    fn enu_axes(&self) -> [[f64; 3]; 3] {
+        let (latitude, longitude) = (self.latitude.to_radians(), self.longitude.to_radians());
+        let (sin_lat, cos_lat, sin_lon, cos_lon) = (latitude.sin(), latitude.cos(), longitude.sin(), longitude.cos());
+        [[-sin_lon, cos_lon, 0.0], [-sin_lat * cos_lon, -sin_lat * sin_lon, cos_lat], [cos_lat * cos_lon, cos_lat * sin_lon, sin_lat]]
+    }
+
+    /// east, north and up of `position` in metres in the tangent frame at `self`
+    pub fn enu(&self, position: &GeodeticPosition) -> [f64; 3] {
+        let (origin, target) = (self.to_ecef(), position.to_ecef());
+        let delta = [target[0] - origin[0], target[1] - origin[1], target[2] - origin[2]];
+        self.enu_axes().map(|axis| axis[0] * delta[0] + axis[1] * delta[1] + axis[2] * delta[2])
+    }
+
+    /// north, east and down of `position` in metres in the tangent frame at `self`
+    pub fn ned(&self, position: &GeodeticPosition) -> [f64; 3] {
+        let [east, north, up] = self.enu(position);
+        [north, east, -up]
+    }
+
+    /// Geodetic position of the point with the given local coordinates (x east, y north, z up,
+    /// metres) when `self` is the origin of the local frame, the inverse of `local`.
+    pub fn offset(&self, x: f32, y: f32, z: f32) -> GeodeticPosition {
+        let origin = self.to_ecef();
+        let axes = self.enu_axes();
+        let enu = [x as f64, y as f64, z as f64];
+        let ecef = [0, 1, 2].map(|i| origin[i] + axes[0][i] * enu[0] + axes[1][i] * enu[1] + axes[2][i] * enu[2]);
+        GeodeticPosition::from_ecef(ecef)
+    }
+
+    /// local coordinates (x east, y north, z up, metres) of `position` when `self` is the
+    /// origin of the local frame
+    pub fn local(&self, position: &GeodeticPosition) -> [f32; 3] {
+        self.enu(position).map(|coordinate| coordinate as f32)
+    }
+
+    /// distance in metres along the earth's surface (a sphere of mean radius) to `position`
+    pub fn great_circle_distance(&self, position: &GeodeticPosition) -> f64 {
+        let (latitude_1, latitude_2) = (self.latitude.to_radians(), position.latitude.to_radians());
+        let delta_latitude = latitude_2 - latitude_1;
+        let delta_longitude = (position.longitude - self.longitude).to_radians();
+        let h = (delta_latitude / 2.0).sin().powi(2) + latitude_1.cos() * latitude_2.cos() * (delta_longitude / 2.0).sin().powi(2);
+        2.0 * MEAN_EARTH_RADIUS * h.sqrt().min(1.0).asin()
+    }
+}
+
+/// Roll, pitch and yaw of the local frame (z-y'-x'' about east, north, up; yaw
+/// counter-clockwise from east) for an attitude as autopilots report it (about north, east,
+/// down; yaw clockwise from north, body x forward, y right, z down), all in radians.
+pub fn attitude_from_ned(roll: f32, pitch: f32, yaw: f32) -> (f32, f32, f32) {
+    (roll, -pitch, FRAC_PI_2 - yaw)
+}
+
+/// local (east, north, up) components of a north, east, down vector
+pub fn vector_from_ned(ned: [f32; 3]) -> [f32; 3] {
+    [ned[1], ned[0], -ned[2]]
+}
+
+#[pymethods]
+impl GeodeticPosition {
+    #[new]
+    #[pyo3(signature = (latitude, longitude, altitude = 0.0))]
+    fn py_new(latitude: f64, longitude: f64, altitude: f64) -> Self {
+        GeodeticPosition::new(latitude, longitude, altitude)
+    }
+
+    /// geodetic position of the local point (x east, y north, z up) relative to this origin
+    #[pyo3(name = "offset")]
+    fn py_offset(&self, x: f32, y: f32, z: f32) -> GeodeticPosition {
+        self.offset(x, y, z)
+    }
+
+    /// local coordinates (x, y, z) of the given position relative to this origin
+    #[pyo3(name = "local")]
+    fn py_local(&self, position: &GeodeticPosition) -> [f32; 3] {
+        self.local(position)
+    }
+
+    /// (east, north, up) of the given position relative to this origin
+    #[pyo3(name = "enu")]
+    fn py_enu(&self, position: &GeodeticPosition) -> [f64; 3] {
+        self.enu(position)
+    }
+
+    /// (north, east, down) of the given position relative to this origin
+    #[pyo3(name = "ned")]
+    fn py_ned(&self, position: &GeodeticPosition) -> [f64; 3] {
+        self.ned(position)
+    }
+
+    #[pyo3(name = "great_circle_distance")]
+    fn py_great_circle_distance(&self, position: &GeodeticPosition) -> f64 {
+        self.great_circle_distance(position)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::to_rotated_frame;
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3], tolerance: f32) {
+        assert!((0..3).all(|i| (actual[i] - expected[i]).abs() < tolerance), "{:?} instead of {:?}", actual, expected);
+    }
+
+    #[test]
+    fn ecef_round_trip() {
+        assert_eq!(GeodeticPosition::new(0.0, 0.0, 0.0).to_ecef(), [WGS84_A, 0.0, 0.0]);
+        for position in [
+            GeodeticPosition::new(50.7512, 8.7695, 180.0),
+            GeodeticPosition::new(-33.86, 151.21, 0.0),
+            GeodeticPosition::new(89.9, -120.0, 1000.0),
+            GeodeticPosition::new(0.5, -179.9, -50.0),
+        ] {
+            let back = GeodeticPosition::from_ecef(position.to_ecef());
+            assert!((back.latitude - position.latitude).abs() < 1e-9 && (back.longitude - position.longitude).abs() < 1e-9, "{:?}", back);
+            assert!((back.altitude - position.altitude).abs() < 1e-4, "{:?}", back);
+        }
+    }
+
+    #[test]
+    fn local_and_offset_are_inverse() {
+        let origin = GeodeticPosition::new(50.7512, 8.7695, 180.0);
+        for point in [[0.0, 0.0, 0.0], [1234.5, -678.9, 42.0], [-5000.0, 3000.0, 120.0]] {
+            assert_close(origin.local(&origin.offset(point[0], point[1], point[2])), point, 1e-3);
+        }
+    }
+
+    #[test]
+    fn thousandth_of_a_degree_north_is_111_metres() {
+        let origin = GeodeticPosition::new(50.0, 8.0, 0.0);
+        // the meridian arc of 0.001 degrees at 50 degrees latitude, the earth curving away below
+        assert_close(origin.local(&GeodeticPosition::new(50.001, 8.0, 0.0)), [0.0, 111.22, -0.001], 0.01);
+        // the parallel at 50 degrees is shorter by about cos(50 degrees)
+        assert_close(origin.local(&GeodeticPosition::new(50.0, 8.001, 0.0)), [71.70, 0.0, 0.0], 0.01);
+        assert!((origin.great_circle_distance(&GeodeticPosition::new(50.001, 8.0, 0.0)) - 111.195).abs() < 1e-3);
+        let [north, east, down] = origin.ned(&GeodeticPosition::new(50.001, 8.0, 10.0));
+        assert!((north - 111.22).abs() < 0.01 && east.abs() < 1e-6 && (down + 10.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn ned_attitudes_and_vectors_map_to_the_local_frame() {
+        assert_eq!(vector_from_ned([1.0, 2.0, 3.0]), [2.0, 1.0, -3.0]);
+        let body = |ned_attitude: (f32, f32, f32), direction: [f32; 3]| {
+            let (roll, pitch, yaw) = attitude_from_ned(ned_attitude.0, ned_attitude.1, ned_attitude.2);
+            to_rotated_frame(direction, yaw, pitch, roll)
+        };
+        let (sin_30, cos_30) = 30.0_f32.to_radians().sin_cos();
+        // heading north and east, the nose points along the body x axis
+        assert_close(body((0.0, 0.0, 0.0), [0.0, 1.0, 0.0]), [1.0, 0.0, 0.0], 1e-6);
+        assert_close(body((0.0, 0.0, FRAC_PI_2), [1.0, 0.0, 0.0]), [1.0, 0.0, 0.0], 1e-6);
+        // nose up heading east
+        assert_close(body((0.0, 30.0_f32.to_radians(), FRAC_PI_2), [cos_30, 0.0, sin_30]), [1.0, 0.0, 0.0], 1e-6);
+        // right wing down heading north, the left wing (body y) rises towards the west
+        assert_close(body((30.0_f32.to_radians(), 0.0, 0.0), [-cos_30, 0.0, sin_30]), [0.0, 1.0, 0.0], 1e-6);
+    }
+}
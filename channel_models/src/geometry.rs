@@ -0,0 +1,115 @@
+//! Small vector helpers for the local frame (x, y horizontal, z up, metres) in which the
+//! station and UAV positions are given. With a geodetic origin in the scenario, x points east
+//! and y north.
+//!
+//! Attitudes are Tait-Bryan angles in radians applied in z-y'-x'' order: yaw around the
+//! local z axis (counter-clockwise from x), then pitch, then roll.
+
+use crate::{Direction, Scenario, State};
+
+pub type Vec3 = [f32; 3];
+
+pub fn add(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn dot(a: Vec3, b: Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn norm(a: Vec3) -> f32 {
+    dot(a, a).sqrt()
+}
+
+pub fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+pub fn scale(a: Vec3, factor: f32) -> Vec3 {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
+pub fn station_position(scenario: &Scenario) -> Vec3 {
+    [scenario.station_x, scenario.station_y, scenario.station_z]
+}
+
+pub fn uav_position(state: &State) -> Vec3 {
+    [state.x, state.y, state.z]
+}
+
+pub fn to_direction(a: Vec3) -> Direction {
+    let horizontal = (a[0] * a[0] + a[1] * a[1]).sqrt();
+    Direction::new(a[1].atan2(a[0]), a[2].atan2(horizontal))
+}
+
+pub fn from_direction(direction: &Direction) -> Vec3 {
+    let (sin_el, cos_el) = direction.elevation.sin_cos();
+    let (sin_az, cos_az) = direction.azimuth.sin_cos();
+    [cos_el * cos_az, cos_el * sin_az, sin_el]
+}
+
+/// expresses a vector given in the parent frame in the frame rotated by the given attitude
+pub fn to_rotated_frame(a: Vec3, yaw: f32, pitch: f32, roll: f32) -> Vec3 {
+    // inverse rotation: undo yaw, then pitch, then roll
+    let (sin_y, cos_y) = yaw.sin_cos();
+    let (sin_p, cos_p) = pitch.sin_cos();
+    let (sin_r, cos_r) = roll.sin_cos();
+    let a = [cos_y * a[0] + sin_y * a[1], -sin_y * a[0] + cos_y * a[1], a[2]];
+    let a = [cos_p * a[0] - sin_p * a[2], a[1], sin_p * a[0] + cos_p * a[2]];
+    [a[0], cos_r * a[1] + sin_r * a[2], -sin_r * a[1] + cos_r * a[2]]
+}
+
+/// expresses a vector given in the local frame in the body frame of the UAV
+pub fn to_body_frame(a: Vec3, state: &State) -> Vec3 {
+    to_rotated_frame(a, state.yaw, state.pitch, state.roll)
+}
+
+/// Directions at both ends and Doppler shift of a path, see `PropagationPath`
+#[derive(Clone, Copy, Debug)]
+pub struct PathGeometry {
+    pub departure: Direction,
+    pub arrival: Direction,
+    pub doppler: f32,
+}
+
+/// Doppler shift in Hz seen by the UAV for a wave arriving from `source`; the station does not move
+pub fn doppler_from(scenario: &Scenario, state: &State, source: Vec3) -> f32 {
+    let towards_source = sub(source, uav_position(state));
+    let distance = norm(towards_source);
+    if distance > 0.0 {
+        dot(state.velocity, towards_source) / distance / scenario.lambda()
+    } else {
+        0.0
+    }
+}
+
+/// Geometry of a path that leaves the station towards `first` and reaches the UAV from `last`.
+/// The departure is given in the local frame, the arrival in the body frame of the UAV.
+pub fn path_geometry(scenario: &Scenario, state: &State, first: Vec3, last: Vec3) -> PathGeometry {
+    PathGeometry {
+        departure: to_direction(sub(first, station_position(scenario))),
+        arrival: to_direction(to_body_frame(sub(last, uav_position(state)), state)),
+        doppler: doppler_from(scenario, state, last),
+    }
+}
+
+/// point on the ground (z = 0) at `fraction` of the horizontal way from the station to the UAV
+pub fn ground_point(scenario: &Scenario, state: &State, fraction: f32) -> Vec3 {
+    let station = station_position(scenario);
+    let offset = scale(sub(uav_position(state), station), fraction);
+    [station[0] + offset[0], station[1] + offset[1], 0.0]
+}
+
+pub fn los_geometry(scenario: &Scenario, state: &State) -> PathGeometry {
+    path_geometry(scenario, state, uav_position(state), station_position(scenario))
+}
+
+/// geometry of a ground reflection at `fraction` of the horizontal way from the station
+pub fn ground_reflection_geometry(scenario: &Scenario, state: &State, fraction: f32) -> PathGeometry {
+    let reflection = ground_point(scenario, state, fraction);
+    path_geometry(scenario, state, reflection, reflection)
+}
@@ -0,0 +1,118 @@
+use num::complex::Complex64;
+use pyo3::prelude::*;
+use serde::Deserialize;
+use std::f64::consts::PI;
+
+use crate::Polarization;
+
+const VACUUM_PERMITTIVITY: f64 = physical_constants::VACUUM_ELECTRIC_PERMITTIVITY;
+
+/// Named surface materials of the ground and of buildings with frequency-dependent electrical
+/// properties, see ITU-R P.527 and the material table of ITU-R P.2040.
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroundMaterial {
+    SeaWater,
+    FreshWater,
+    WetGround,
+    MediumDryGround,
+    VeryDryGround,
+    Concrete,
+    Ice,
+    Brick,
+    Wood,
+    Glass,
+    Metal,
+    Plasterboard,
+}
+
+/// single Debye relaxation of water at 20 °C: (static permittivity, high-frequency
+/// permittivity, relaxation frequency in Hz, ionic conductivity in S/m)
+struct Debye(f64, f64, f64, f64);
+
+const FRESH_WATER: Debye = Debye(80.1, 4.9, 17.0e9, 0.01);
+const SEA_WATER: Debye = Debye(70.9, 4.9, 17.8e9, 4.8);
+
+impl Debye {
+    fn electrical_properties(&self, frequency: f64) -> (f64, f64) {
+        let Debye(epsilon_static, epsilon_infinity, relaxation_frequency, ionic_conductivity) = *self;
+        let a = frequency / relaxation_frequency;
+        let epsilon_real = epsilon_infinity + (epsilon_static - epsilon_infinity) / (1.0 + a * a);
+        let epsilon_imag = (epsilon_static - epsilon_infinity) * a / (1.0 + a * a);
+        // dielectric losses expressed as an equivalent conductivity
+        let conductivity = ionic_conductivity + 2.0 * PI * frequency * VACUUM_PERMITTIVITY * epsilon_imag;
+        (epsilon_real, conductivity)
+    }
+}
+
+/// `epsilon_r = a * f^b` and `sigma = c * f^d` with f in GHz, ITU-R P.2040 table 3
+/// (ground types valid from 1 to 10 GHz, building materials from 1 to 100 GHz or less)
+fn power_law(frequency: f64, a: f64, b: f64, c: f64, d: f64) -> (f64, f64) {
+    let f_ghz = frequency / 1e9;
+    (a * f_ghz.powf(b), c * f_ghz.powf(d))
+}
+
+impl GroundMaterial {
+    /// relative permittivity and conductivity in S/m at the given frequency in Hz
+    pub fn electrical_properties(&self, frequency: f32) -> (f64, f64) {
+        let frequency = frequency as f64;
+        match self {
+            GroundMaterial::SeaWater => SEA_WATER.electrical_properties(frequency),
+            GroundMaterial::FreshWater => FRESH_WATER.electrical_properties(frequency),
+            GroundMaterial::WetGround => power_law(frequency, 30.0, -0.4, 0.15, 1.30),
+            GroundMaterial::MediumDryGround => power_law(frequency, 15.0, -0.1, 0.035, 1.63),
+            GroundMaterial::VeryDryGround => power_law(frequency, 3.0, 0.0, 0.00015, 2.52),
+            GroundMaterial::Concrete => power_law(frequency, 5.24, 0.0, 0.0462, 0.7822),
+            // pure ice near 0 °C is an almost lossless dielectric in the UHF and SHF bands
+            GroundMaterial::Ice => (3.17, 1.0e-4),
+            GroundMaterial::Brick => power_law(frequency, 3.91, 0.0, 0.0238, 0.16),
+            GroundMaterial::Wood => power_law(frequency, 1.99, 0.0, 0.0047, 1.0718),
+            GroundMaterial::Glass => power_law(frequency, 6.31, 0.0, 0.0036, 1.3394),
+            // treated as a very good conductor, as in P.2040
+            GroundMaterial::Metal => (1.0, 1.0e7),
+            GroundMaterial::Plasterboard => power_law(frequency, 2.73, 0.0, 0.0085, 0.9395),
+        }
+    }
+}
+
+#[pymethods]
+impl GroundMaterial {
+    /// (relative permittivity, conductivity in S/m) at the given frequency in Hz
+    #[pyo3(name = "electrical_properties")]
+    fn py_electrical_properties(&self, frequency: f32) -> (f64, f64) {
+        self.electrical_properties(frequency)
+    }
+}
+
+/// Fresnel reflection coefficient of a smooth surface for the given grazing angle (radians
+/// above the surface). Circular polarization returns the co-polar part `(rho_v + rho_h) / 2`,
+/// i.e. the share of the reflection that keeps its handedness and is received by an antenna
+/// of the same handedness.
+pub fn reflection_coefficient(
+    permittivity: f64,
+    conductivity: f64,
+    frequency: f32,
+    grazing_angle: f64,
+    polarization: Polarization,
+) -> Complex64 {
+    let omega: f64 = 2.0 * PI * frequency as f64;
+    let x_r = conductivity / (omega * VACUUM_PERMITTIVITY);
+    let epsilon_minus_j_x = Complex64::new(permittivity, -x_r);
+    let tmp_1 = (epsilon_minus_j_x - grazing_angle.cos().powi(2)).sqrt();
+    let sin_psi = Complex64::from(grazing_angle.sin());
+    let rho_horizontal = (sin_psi - tmp_1) / (sin_psi + tmp_1);
+    let rho_vertical = (epsilon_minus_j_x * sin_psi - tmp_1) / (epsilon_minus_j_x * sin_psi + tmp_1);
+    match polarization {
+        Polarization::Horizontal => rho_horizontal,
+        Polarization::Vertical => rho_vertical,
+        Polarization::Circular => (rho_vertical + rho_horizontal) / 2.0,
+    }
+}
+
+/// Scattering loss factor of a rough surface (Gaussian height distribution with the given
+/// standard deviation in metres), see 10.1109/TVT.2017.2659651
+pub fn roughness_factor(roughness: f32, wavelength: f32, grazing_angle: f64) -> f64 {
+    let c_r = (4.0 * PI * roughness as f64) * grazing_angle.sin() / wavelength as f64;
+    (-c_r.powi(2) / 2.0).exp()
+}
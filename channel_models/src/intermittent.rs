@@ -0,0 +1,188 @@
+use pyo3::prelude::*;
+use rand::Rng;
+use rand_distr::{Distribution, Normal, Uniform};
+use std::f32::consts::PI;
+
+use crate::models::{seeded_rng, ModelRng};
+use crate::environment::RayStatistics;
+use crate::{dist_to_loss, Environment, PathKind, PropagationPath, Scenario, State};
+
+fn generate_sample<R: Rng + ?Sized>(parameters: RayStatistics, dist: f32, distance_offset: f32, rng: &mut R) -> f32 {
+    let intercept = parameters.0;
+    let slope = parameters.1;
+    let std_dev = parameters.2;
+    10.0_f32.powf(intercept + slope * (dist - distance_offset).max(0.0) + Normal::new(0.0, std_dev).unwrap().sample(rng))
+}
+
+#[derive(Clone, Copy)]
+struct IntermittentRayParameters {
+    x: f32,
+    y: f32,
+    z: f32,
+    is_on: bool,
+    duration: f32,
+    /// horizontal direction (radians, local frame) the ray arrives from while it is on
+    azimuth: f32,
+}
+
+impl IntermittentRayParameters {
+    // 'step 1'
+    const INITIAL: IntermittentRayParameters = IntermittentRayParameters {x: 0., y: 0., z: -1.0e10, is_on: false, duration: 0., azimuth: 0.};
+}
+
+/// State machine of the intermittent multipath components of the nine-ray model (seven in
+/// the original near-urban parameter set), see DOI 10.1109/TVT.2017.2659651. Every ray is
+/// switched on or off for a randomly drawn travelled distance; ray k can only be on while
+/// ray k-1 is on.
+///
+/// Each instance owns its state and random number generator, so several links can be
+/// emulated side by side.
+#[pyclass]
+pub struct IntermittentRays {
+    environment: Environment,
+    states: Vec<IntermittentRayParameters>,
+    seed: u64,
+    rng: ModelRng,
+}
+
+impl IntermittentRays {
+    pub fn new(environment: &Environment, seed: u64) -> Self {
+        IntermittentRays {
+            environment: environment.clone(),
+            states: vec![IntermittentRayParameters::INITIAL; environment.ray_count()],
+            seed,
+            rng: seeded_rng(seed),
+        }
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// switches all rays off and restarts the random number generator from the seed
+    pub fn reset(&mut self) {
+        self.states.fill(IntermittentRayParameters::INITIAL);
+        self.rng = seeded_rng(self.seed);
+    }
+
+    /// Advances the state machine to the given UAV position and returns the rays that are
+    /// currently on. Their loss is relative to the free-space loss of the direct path, their
+    /// Doppler shift follows from the UAV velocity and the arrival azimuth drawn when the ray
+    /// switched on.
+    pub fn paths(&mut self, scenario: &Scenario, state: &State) -> Vec<PropagationPath> {
+        let (x, y, z) = (state.x, state.y, state.z);
+        let rng = &mut self.rng;
+        let environment = &self.environment;
+        let dist = scenario.distance(x, y, z);
+        let los_loss = dist_to_loss(scenario, dist);
+        let mut paths = Vec::new();
+        // 'step 3'
+        for k in 0..self.states.len() {
+            if k == 0 || self.states[k-1].is_on {
+                let current_ray_parameters: &mut IntermittentRayParameters = &mut self.states[k];
+                // 'step 4'
+                let dist_to_ray_origin = ((current_ray_parameters.x - x).powi(2) + (current_ray_parameters.y - y).powi(2) + (current_ray_parameters.z - z).powi(2)).sqrt();
+                if dist_to_ray_origin > current_ray_parameters.duration {
+                    // 'step 5'
+                    let on_probability = generate_sample(environment.on_probability[k], dist, environment.distance_offset, rng);
+                    let on_sample = Uniform::new(0.0, 1.0).sample(rng);
+                    current_ray_parameters.is_on = on_sample < on_probability;
+                    // the duration is a travelled distance, measured from where the state was entered
+                    current_ray_parameters.x = x;
+                    current_ray_parameters.y = y;
+                    current_ray_parameters.z = z;
+                    // 'step 6'
+                    current_ray_parameters.duration = generate_sample(environment.duration[k], dist, environment.distance_offset, rng);
+                    if !current_ray_parameters.is_on {
+                        break;
+                    }
+                    current_ray_parameters.azimuth = Uniform::new(0.0, 2.0 * PI).sample(rng);
+                }
+                else if !current_ray_parameters.is_on {
+                    break;
+                }
+                // 'step 7'
+                // let additional_loss_db = (Normal::new(-30.3, 4.1).unwrap().sample(rng) as f32).min(0.0);
+                let additional_loss_db = (Normal::new(environment.additional_loss_mean, environment.additional_loss_std).unwrap().sample(rng)).max(0.0);
+                // println!("additional_loss_db: {}", additional_loss_db);
+                let additional_loss_linear = 10.0_f32.powf(additional_loss_db / 20.0);
+                // println!("additional_loss_linear: {}", additional_loss_linear);
+                let phase_shift = Uniform::new(0.0, 2.0 * PI).sample(rng);
+                // 'step 8'
+                let excess_delay = generate_sample(environment.excess_delay[k], dist, environment.distance_offset, rng).max(0.0);  // nanoseconds
+                let excess_delay = excess_delay / 1_000_000_000.0;
+                // println!("excess_delay: {}", excess_delay);
+                // let tap_index = (excess_delay * 200_000_000.0) as usize;
+                // println!("tap_index: {}", tap_index);
+                // println!("ray: {}", k+2);
+                // println!("additional_loss_db: {}", additional_loss_db);
+                // println!("additional_loss_linear: {}", additional_loss_linear);
+                let mut path = PropagationPath::new(PathKind::Intermittent(k as u8), los_loss * additional_loss_linear, excess_delay, phase_shift);
+                let (sin_azimuth, cos_azimuth) = current_ray_parameters.azimuth.sin_cos();
+                path.doppler = (state.velocity[0] * cos_azimuth + state.velocity[1] * sin_azimuth) / scenario.lambda();
+                paths.push(path);
+            }
+        }
+        paths
+    }
+}
+
+#[pymethods]
+impl IntermittentRays {
+    #[new]
+    #[pyo3(signature = (seed = None, environment = None))]
+    fn py_new(seed: Option<u64>, environment: Option<Environment>) -> Self {
+        IntermittentRays::new(&environment.unwrap_or_default(), seed.unwrap_or_else(|| rand::thread_rng().gen()))
+    }
+
+    #[getter(environment)]
+    fn py_environment(&self) -> Environment {
+        self.environment.clone()
+    }
+
+    #[getter(seed)]
+    fn py_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// on/off state of the rays
+    #[getter]
+    fn active(&self) -> Vec<bool> {
+        self.states.iter().map(|state| state.is_on).collect()
+    }
+
+    #[pyo3(name = "reset")]
+    fn py_reset(&mut self) {
+        self.reset()
+    }
+
+    /// `velocity` of the UAV in m/s as (vx, vy, vz), zero if not given
+    #[pyo3(name = "paths", signature = (x, y, z, scenario = None, velocity = None))]
+    fn py_paths(&mut self, x: f32, y: f32, z: f32, scenario: Option<Scenario>, velocity: Option<[f32; 3]>) -> Vec<PropagationPath> {
+        let state = State::at(x, y, z).with_velocity(velocity.unwrap_or_default());
+        self.paths(&scenario.unwrap_or_default(), &state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rays_keep_their_state_for_the_drawn_distance() {
+        let scenario = Scenario::default();
+        let mut rays = IntermittentRays::new(&scenario.environment, 1);
+        let state = State::at(800.0, 0.0, 100.0);
+        rays.paths(&scenario, &state);
+        let active = rays.states.iter().map(|ray| ray.is_on).collect::<Vec<_>>();
+        // without moving, no ray has travelled its duration, so none is drawn again
+        for _ in 0..100 {
+            rays.paths(&scenario, &state);
+            assert_eq!(rays.states.iter().map(|ray| ray.is_on).collect::<Vec<_>>(), active);
+        }
+    }
+}
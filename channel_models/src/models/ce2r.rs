@@ -0,0 +1,191 @@
+use num::complex::Complex64;
+use std::f32::consts::PI;
+
+use crate::geometry::{ground_reflection_geometry, los_geometry};
+use crate::models::{ground_parameters, set_ground_parameter, ChannelModel, UnknownParameter};
+use crate::ground::{reflection_coefficient, roughness_factor};
+use crate::{dist_to_loss, PathKind, PropagationPath, Scenario, State, KA, SPEED_OF_LIGHT};
+
+/// Curved-earth two-ray model, see DOI 10.1109/TVT.2016.2530306
+pub struct CurvedEarthTwoRay {
+    scenario: Scenario,
+}
+
+impl CurvedEarthTwoRay {
+    pub fn new(scenario: &Scenario) -> Self {
+        CurvedEarthTwoRay { scenario: scenario.clone() }
+    }
+}
+
+impl ChannelModel for CurvedEarthTwoRay {
+    fn name(&self) -> &'static str {
+        "curved_earth_two_ray"
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f64)> {
+        ground_parameters(&self.scenario)
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f64) -> Result<(), UnknownParameter> {
+        set_ground_parameter(&mut self.scenario, name, value)
+    }
+
+    fn paths(&mut self, state: &State) -> Vec<PropagationPath> {
+        let scenario = &self.scenario;
+        let (x, y, z) = (state.x, state.y, state.z);
+        dsl!("x,y,z {},{},{}", x, y, z);
+        // with a geodetic origin, heights above the origin's reference, the straight distance
+        // and the great-circle distance follow from the true positions on the WGS84 ellipsoid
+        let great_circle = scenario.origin.map(|origin| {
+            let station = origin.offset(scenario.station_x, scenario.station_y, scenario.station_z);
+            let uav = origin.offset(x, y, z);
+            let (a, b) = (station.to_ecef(), uav.to_ecef());
+            let chord = ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt();
+            ((uav.altitude - origin.altitude) as f32, (station.altitude - origin.altitude) as f32, chord as f32, station.great_circle_distance(&uav))
+        });
+        let (z, station_z, r_1) = match great_circle {
+            Some((z, station_z, chord, _)) => (z, station_z, chord),
+            None => (z, scenario.station_z, scenario.distance(x, y, z)),
+        };
+        let loss_los = dist_to_loss(scenario, r_1);
+        let r_1 = r_1 as f64;
+        dsl!("r_1 {}", r_1);
+        // earth radius and absolute heights
+        dsl!("KA {}", KA);
+        let abs_height_uav = KA + z as f64;
+        dsl!("abs_height_uav {}", abs_height_uav);
+        let abs_height_station = KA + station_z as f64;
+        dsl!("abs_height_station {}", abs_height_station);
+        let x_y_dist = match great_circle {
+            Some((_, _, _, ground_distance)) => ground_distance,
+            None => scenario.distance(x, y, scenario.station_z) as f64,
+        };
+        // angle between uav and station via earth canter
+        let q: f64 = if great_circle.is_some() {
+            x_y_dist / KA
+        }
+        else if x_y_dist != 0.0 {
+            (
+                (abs_height_uav.powi(2) + abs_height_station.powi(2) - r_1.powi(2))
+                    /
+                    (2.0_f64 * abs_height_uav * abs_height_station)
+            ).acos()  // angle theta_1 + theta_2
+        }
+        else {
+            0.0_f64
+        };
+        // dsl!("r_1.powi(2) {}", (r_1 as f64).powi(2));
+        // dsl!("q_no_acos {}", (
+        //     (abs_height_uav.powi(2) + abs_height_station.powi(2) - (r_1 as f64).powi(2))
+        //         /
+        //     (2.0_f64*abs_height_uav*abs_height_station)
+        // ));
+        dsl!("q {}", q);
+        // distance along earth surface between orthogonal projections of uav and station onto surface
+        let d: f64 = KA * q;  // in meters
+        dsl!("d {}", d);
+        // intermediate quantities
+        let m: f64 = d.powi(2) / (4.0 * KA * (z as f64 + station_z as f64));
+        let c: f64 = (z as f64 - station_z as f64) / (z as f64 + station_z as f64);
+        let b: f64 = 2.0 * ((m + 1.0) / (3.0 * m)).sqrt() * ((PI / 3.0) as f64 + (3.0 * c * ((3.0 * m) / (m + 1.0).powi(3)).sqrt() / 2.0).acos() / 3.0).cos();
+        let b = b.clamp(-1.0, 1.0);
+        dsl!("m {}", m);
+        dsl!("c {}", c);
+        dsl!("b {}", b);
+        // dsl!("((m + 1.0)/(3.0*m)).sqrt() {}", ((m + 1.0)/(3.0*m)).sqrt());
+        // dsl!("3.0*c*((3.0*m)/(m+1.0).powi(3)).sqrt()/2.0) {}", 3.0*c*((3.0*m)/(m+1.0).powi(3)).sqrt()/2.0);
+        // dsl!("(3.0*m)/(m+1.0).powi(3) {}", (3.0*m)/(m+1.0).powi(3));
+        // dsl!("(PI/3.0 + (3.0*c*((3.0*m)/(m+1.0).powi(3)).sqrt()/2.0).acos()/3.0).cos() {}", ((PI/3.0) as f64+ (3.0*c*((3.0*m)/(m+1.0).powi(3)).sqrt()/2.0).acos()/3.0).cos());
+        // reflection point
+        let d_1 = d * (1.0 + b) / 2.0;
+        let d_1 = d_1.clamp(0.0_f64, d);  // TODO
+        let d_2 = d - d_1;
+        let theta_1 = d_1 / KA;
+        dsl!("d_1 {}", d_1);
+        dsl!("d_2 {}", d_2);
+        dsl!("theta_1 {}", theta_1);
+        // grazing angle
+        let psi = if x_y_dist > 0.0 {
+            let psi_ce: f64 = (((z + station_z) as f64) / d) * (1.0 - (m * (1.0 + b.powi(2))));
+            let d_station_reflection = station_z as f64 * x_y_dist / (z + station_z) as f64;
+            let psi_fe = (station_z as f64 / d_station_reflection).atan();
+            let fade_value = 0.5_f64 + 0.5_f64 * ((x_y_dist - 10.0_f64) / scenario.smoothing_factor).tanh();
+            psi_ce * fade_value + psi_fe * (1.0_f64 - fade_value)
+        }
+        else {
+            (PI / 2.0) as f64
+        };
+        dsl!("psi {}", psi);
+        // path length difference
+        let delta_r =  if x_y_dist != 0.0 {
+             (2.0 * d_1 * d_2 * psi.powi(2)) / d
+        }
+        else {
+            (z.min(station_z) + station_z) as f64
+        };
+        dsl!("delta_r {}", delta_r);
+        // r_2
+        let r_2 = r_1 + delta_r;
+        dsl!("r_2 {}", r_2);
+        // reflected path amplitude purely by distance
+        let alpha_s = 1.0_f64 / (dist_to_loss(scenario, r_2 as f32) as f64);
+        dsl!("alpha_s {}", alpha_s);
+        // lengths l_1 and l_2
+        let l_1 = (abs_height_uav.powi(2) + KA.powi(2) - 2.0*KA*abs_height_uav*(theta_1.cos())).sqrt();
+        // let l_2 = r_2 - l_1;  // TODO
+        let l_2 = (abs_height_station.powi(2) + KA.powi(2) - 2.0*KA*abs_height_station*((q-theta_1).cos())).sqrt();
+        // divergence factor D
+        let divergence = 1.0 / (1.0 + ((2.0*l_1*l_2)/(KA*psi.sin()*(l_1+l_2)))).sqrt();
+        dsl!("divergence {}", divergence);
+        let v = (PI/2.0) as f64 - q;
+        let p = ((z + station_z) as f64)*q.sin() / v.sin();
+        let phi: f64 = ((r_1.powi(2) + abs_height_uav.powi(2) - abs_height_station.powi(2)) / (2.0*r_1*abs_height_uav)).acos();
+        let beta = (r_1 * phi.sin() / p).asin();
+        // elevation angle
+        let _theta_e = PI as f64 - phi - beta;
+        // surface roughness
+        let r_f = roughness_factor(scenario.ground_roughness, scenario.lambda(), psi);
+        dsl!("r_f {}", r_f);
+        // surface reflection coefficient
+        let (epsilon_r, sigma) = scenario.ground_properties();
+        let rho: Complex64 = reflection_coefficient(epsilon_r, sigma, scenario.frequency, psi, scenario.polarization);
+        let (gamma_f, additional_phase_shift) = rho.to_polar();
+        dsl!("gamma_f {}", gamma_f);
+        let amplitude_reflected_ray = alpha_s * gamma_f * divergence * r_f;
+        let loss_nlos = 1.0 / amplitude_reflected_ray;
+        let delta_t = delta_r / (SPEED_OF_LIGHT as f64);
+        dsl!("delta_t {}", delta_t);
+        // d_1 is measured from the UAV; the reflection point is placed on the flat ground plane of
+        // the local frame, which is accurate enough for the antenna gains
+        let reflection_fraction = if d > 0.0 { d_2 / d } else { (station_z / (z + station_z)) as f64 };
+        let paths = vec![
+            PropagationPath::new(PathKind::LineOfSight, loss_los, 0., 0.)
+                .with_geometry(los_geometry(scenario, state)),
+            PropagationPath::new(PathKind::GroundReflection, loss_nlos as f32, delta_t as f32, additional_phase_shift as f32)
+                .with_geometry(ground_reflection_geometry(scenario, state, reflection_fraction as f32)),
+        ];
+        // println!("{:?}", paths);
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TapGrid, MAGIC_SCALING_COEFF, MAX_TAPS};
+
+    /// Pins the taps of the default scenario at one position. Since the Doppler commit the
+    /// carrier phase of each path's delay and the reflection phase reach the taps, so they
+    /// are complex; before, only the real magnitudes were sent.
+    #[test]
+    fn taps_at_a_fixed_position() {
+        let scenario = Scenario::default();
+        let paths = CurvedEarthTwoRay::new(&scenario).paths(&State::at(300.0, 0.0, 10.0));
+        let taps = TapGrid::new(200e6, MAX_TAPS).quantized_taps(&paths, scenario.frequency, MAGIC_SCALING_COEFF);
+        // line of sight and ground reflection fall onto the first tap
+        let mut expected = vec![0; 2 * MAX_TAPS];
+        expected[0] = 23056;
+        expected[MAX_TAPS] = 19763;
+        assert_eq!(taps, expected);
+    }
+}
@@ -0,0 +1,26 @@
+use crate::geometry::los_geometry;
+use crate::models::ChannelModel;
+use crate::{dist_to_loss, PathKind, PropagationPath, Scenario, State};
+
+/// Friis free-space path loss, a single line-of-sight path
+pub struct FreeSpace {
+    scenario: Scenario,
+}
+
+impl FreeSpace {
+    pub fn new(scenario: &Scenario) -> Self {
+        FreeSpace { scenario: scenario.clone() }
+    }
+}
+
+impl ChannelModel for FreeSpace {
+    fn name(&self) -> &'static str {
+        "free_space"
+    }
+
+    fn paths(&mut self, state: &State) -> Vec<PropagationPath> {
+        let dist = self.scenario.distance(state.x, state.y, state.z);
+        let loss = dist_to_loss(&self.scenario, dist);
+        vec![PropagationPath::new(PathKind::LineOfSight, loss, 0., 0.).with_geometry(los_geometry(&self.scenario, state))]
+    }
+}
@@ -0,0 +1,196 @@
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::error::Error;
+use std::fmt;
+
+use crate::{PropagationPath, Scenario, State, WithAntennas, WithShadowing};
+
+mod ce2r;
+mod free_space;
+mod nine_ray;
+mod python;
+mod ray_tracing;
+mod terrain_two_ray;
+mod tr36777;
+mod two_ray;
+mod two_segment;
+
+pub use ce2r::CurvedEarthTwoRay;
+pub use free_space::FreeSpace;
+pub use nine_ray::NineRaySuburban;
+pub(crate) use python::add_model_classes;
+pub use ray_tracing::RayTracing;
+pub use terrain_two_ray::TerrainTwoRay;
+pub use tr36777::{AerialScenario, Tr36777};
+pub use two_ray::FlatEarthTwoRay;
+pub use two_segment::{LogDistanceSegment, TwoSegmentLogDistance, TwoSegmentParameters};
+
+/// Returned when setting a parameter the model does not know.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownParameter(pub String);
+
+impl fmt::Display for UnknownParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown model parameter '{}'", self.0)
+    }
+}
+
+impl Error for UnknownParameter {}
+
+/// A channel model turns the current UAV state into a set of discrete propagation paths.
+///
+/// Models may keep state along the trajectory (e.g. intermittent rays), hence `paths` takes
+/// `&mut self`; `reset` brings the model back to the state right after construction.
+pub trait ChannelModel: Send {
+    /// unique identifier used to select the model (GUI, gamepad, control port, Python)
+    fn name(&self) -> &'static str;
+
+    /// current values of all tunable parameters
+    fn parameters(&self) -> Vec<(&'static str, f64)> {
+        Vec::new()
+    }
+
+    fn set_parameter(&mut self, name: &str, _value: f64) -> Result<(), UnknownParameter> {
+        Err(UnknownParameter(name.to_string()))
+    }
+
+    fn paths(&mut self, state: &State) -> Vec<PropagationPath>;
+
+    fn reset(&mut self) {}
+}
+
+/// Random number generator of the stochastic models. ChaCha is portable across platforms and
+/// rand versions, so a recorded trajectory replayed with the same seed gives the same paths.
+pub(crate) type ModelRng = ChaCha8Rng;
+
+pub(crate) fn seeded_rng(seed: u64) -> ModelRng {
+    ModelRng::seed_from_u64(seed)
+}
+
+/// the seed fixed by the scenario, or a fresh random one
+pub(crate) fn resolve_seed(scenario: &Scenario) -> u64 {
+    scenario.seed.unwrap_or_else(|| thread_rng().gen())
+}
+
+type ModelConstructor = fn(&Scenario) -> Box<dyn ChannelModel>;
+
+/// All available models by name, in the order they are cycled through by the gamepad and
+/// indexed by the GUI. Adding a model means adding its name and constructor here; the name
+/// must match `ChannelModel::name`.
+const MODELS: &[(&str, ModelConstructor)] = &[
+    ("free_space", |scenario| Box::new(FreeSpace::new(scenario))),
+    ("flat_earth_two_ray", |scenario| Box::new(FlatEarthTwoRay::new(scenario))),
+    ("curved_earth_two_ray", |scenario| Box::new(CurvedEarthTwoRay::new(scenario))),
+    ("nine_ray_suburban", |scenario| Box::new(NineRaySuburban::new(scenario))),
+    ("two_segment_log_distance", |scenario| Box::new(TwoSegmentLogDistance::new(scenario))),
+    ("tr36777_uma_av", |scenario| Box::new(Tr36777::uma(scenario))),
+    ("tr36777_umi_av", |scenario| Box::new(Tr36777::umi(scenario))),
+    ("tr36777_rma_av", |scenario| Box::new(Tr36777::rma(scenario))),
+    ("terrain_two_ray", |scenario| Box::new(TerrainTwoRay::new(scenario))),
+    ("ray_tracing", |scenario| Box::new(RayTracing::new(scenario))),
+];
+
+/// one instance of every registered model, in registry order
+pub fn create_models(scenario: &Scenario) -> Vec<Box<dyn ChannelModel>> {
+    MODELS.iter().map(|(_, constructor)| with_layers(constructor(scenario), scenario)).collect()
+}
+
+/// wraps a model into the layers the scenario asks for, which apply on top of any model
+fn with_layers(mut model: Box<dyn ChannelModel>, scenario: &Scenario) -> Box<dyn ChannelModel> {
+    if scenario.shadowing.is_enabled() {
+        model = Box::new(WithShadowing::new(model, scenario));
+    }
+    if !(scenario.station_antenna.is_isotropic() && scenario.uav_antenna.is_isotropic()) {
+        model = Box::new(WithAntennas::new(model, scenario));
+    }
+    model
+}
+
+/// the model registered under `name` with its layers, None if there is none; only this model
+/// is constructed
+pub fn create_model(name: &str, scenario: &Scenario) -> Option<Box<dyn ChannelModel>> {
+    MODELS.iter().find(|(model_name, _)| *model_name == name).map(|(_, constructor)| with_layers(constructor(scenario), scenario))
+}
+
+pub fn model_names() -> Vec<&'static str> {
+    MODELS.iter().map(|(name, _)| *name).collect()
+}
+
+/// Whether the model registered under `name` would be constructed differently from `scenario`
+/// than from `previous`. Sections only one model reads (e.g. `environment`) count for that
+/// model, all other settings for every model; `fading` is applied by the emulator and counts
+/// for none.
+pub fn scenario_changed_for(name: &str, scenario: &Scenario, previous: &Scenario) -> bool {
+    let common = |scenario: &Scenario| Scenario {
+        environment: Default::default(),
+        fading: Default::default(),
+        two_segment: Default::default(),
+        terrain: None,
+        buildings: None,
+        ..scenario.clone()
+    };
+    let section_changed = match name {
+        "nine_ray_suburban" => scenario.environment != previous.environment,
+        "two_segment_log_distance" => scenario.two_segment != previous.two_segment,
+        "terrain_two_ray" => scenario.terrain != previous.terrain,
+        "ray_tracing" => scenario.buildings != previous.buildings,
+        _ => false,
+    };
+    section_changed || common(scenario) != common(previous)
+}
+
+/// parameters of the ground near the reflection point, shared by the models with a ground reflection
+fn ground_parameters(scenario: &Scenario) -> Vec<(&'static str, f64)> {
+    let (permittivity, conductivity) = scenario.ground_properties();
+    vec![
+        ("ground_permittivity", permittivity),
+        ("ground_conductivity", conductivity),
+        ("ground_roughness", scenario.ground_roughness as f64),
+        ("smoothing_factor", scenario.smoothing_factor),
+    ]
+}
+
+/// Setting permittivity or conductivity explicitly replaces a configured ground material, the
+/// other property keeps the value the material had at the current frequency.
+fn set_ground_parameter(scenario: &mut Scenario, name: &str, value: f64) -> Result<(), UnknownParameter> {
+    if name == "ground_permittivity" || name == "ground_conductivity" {
+        (scenario.ground_permittivity, scenario.ground_conductivity) = scenario.ground_properties();
+        scenario.ground_material = None;
+    }
+    match name {
+        "ground_permittivity" => scenario.ground_permittivity = value,
+        "ground_conductivity" => scenario.ground_conductivity = value,
+        "ground_roughness" => scenario.ground_roughness = value as f32,
+        "smoothing_factor" => scenario.smoothing_factor = value,
+        _ => return Err(UnknownParameter(name.to_string())),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Environment, Fading};
+
+    #[test]
+    fn scenario_changes_only_concern_the_models_reading_them() {
+        let previous = Scenario::default();
+        let environment = Scenario { environment: Environment { additional_loss_mean: 20.0, ..Environment::default() }, ..Scenario::default() };
+        let changed: Vec<&str> = model_names().into_iter().filter(|name| scenario_changed_for(name, &environment, &previous)).collect();
+        assert_eq!(changed, vec!["nine_ray_suburban"]);
+        let fading = Scenario { fading: Fading { enabled: true, ..Fading::default() }, ..Scenario::default() };
+        assert!(model_names().iter().all(|name| !scenario_changed_for(name, &fading, &previous)));
+        let frequency = Scenario { frequency: 5.8e9, ..Scenario::default() };
+        assert!(model_names().iter().all(|name| scenario_changed_for(name, &frequency, &previous)));
+    }
+
+    #[test]
+    fn registered_names_match_the_models() {
+        let names: Vec<&str> = create_models(&Scenario::default()).iter().map(|model| model.name()).collect();
+        assert_eq!(names, model_names());
+        for name in model_names() {
+            assert_eq!(create_model(name, &Scenario::default()).unwrap().name(), name);
+        }
+        assert!(create_model("unknown", &Scenario::default()).is_none());
+    }
+}
@@ -0,0 +1,119 @@
+use crate::models::{ground_parameters, resolve_seed, set_ground_parameter, ChannelModel, CurvedEarthTwoRay, UnknownParameter};
+use crate::{IntermittentRays, PropagationPath, Scenario, State};
+
+/// Curved-earth two-ray model extended by intermittent multipath components (up to seven in
+/// the near-urban environment), see DOI 10.1109/TVT.2017.2659651
+pub struct NineRaySuburban {
+    scenario: Scenario,
+    ce2r: CurvedEarthTwoRay,
+    rays: IntermittentRays,
+}
+
+impl NineRaySuburban {
+    /// uses the seed of the scenario, or a random one if the scenario does not fix it
+    pub fn new(scenario: &Scenario) -> Self {
+        NineRaySuburban {
+            scenario: scenario.clone(),
+            ce2r: CurvedEarthTwoRay::new(scenario),
+            rays: IntermittentRays::new(&scenario.environment, resolve_seed(scenario)),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.rays.seed()
+    }
+}
+
+impl ChannelModel for NineRaySuburban {
+    fn name(&self) -> &'static str {
+        "nine_ray_suburban"
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f64)> {
+        ground_parameters(&self.scenario)
+    }
+
+    /// applied to both scenario copies so that the intermittent rays see the same ground as
+    /// the two-ray part
+    fn set_parameter(&mut self, name: &str, value: f64) -> Result<(), UnknownParameter> {
+        set_ground_parameter(&mut self.scenario, name, value)?;
+        self.ce2r.set_parameter(name, value)
+    }
+
+    /// 'step 2'
+    fn paths(&mut self, state: &State) -> Vec<PropagationPath> {
+        let mut paths = self.ce2r.paths(state);
+        paths.extend(self.rays.paths(&self.scenario, state));
+        paths
+    }
+
+    fn reset(&mut self) {
+        self.rays.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PathKind, TapGrid, MAGIC_SCALING_COEFF, MAX_TAPS};
+
+    /// quantized taps along a straight flight away from the station, as `taps_for_position`
+    /// computes them
+    fn taps_along_trajectory(model: &mut NineRaySuburban, scenario: &Scenario) -> Vec<Vec<i16>> {
+        let tap_grid = TapGrid::new(200e6, MAX_TAPS);
+        (0..400)
+            .map(|step| {
+                let state = State::new(300.0 + 5.0 * step as f32, 50.0, 100.0, 0.0, 0.0, 0.0).with_velocity([20.0, 0.0, 0.0]);
+                tap_grid.quantized_taps(&model.paths(&state), scenario.frequency, MAGIC_SCALING_COEFF)
+            })
+            .collect()
+    }
+
+    fn seeded_scenario(seed: u64) -> Scenario {
+        Scenario { seed: Some(seed), ..Scenario::default() }
+    }
+
+    #[test]
+    fn same_seed_gives_identical_taps() {
+        let scenario = seeded_scenario(42);
+        let first = taps_along_trajectory(&mut NineRaySuburban::new(&scenario), &scenario);
+        let second = taps_along_trajectory(&mut NineRaySuburban::new(&scenario), &scenario);
+        assert_eq!(first, second);
+        let other_scenario = seeded_scenario(43);
+        assert_ne!(first, taps_along_trajectory(&mut NineRaySuburban::new(&other_scenario), &other_scenario));
+    }
+
+    #[test]
+    fn trajectory_switches_intermittent_rays_on() {
+        let scenario = seeded_scenario(42);
+        let mut model = NineRaySuburban::new(&scenario);
+        let intermittent = (0..400)
+            .filter(|step| {
+                let state = State::at(300.0 + 5.0 * *step as f32, 50.0, 100.0);
+                model.paths(&state).iter().any(|path| matches!(path.kind, PathKind::Intermittent(_)))
+            })
+            .count();
+        assert!(intermittent > 0);
+    }
+
+    #[test]
+    fn reset_reproduces_the_sequence() {
+        let scenario = seeded_scenario(7);
+        let mut model = NineRaySuburban::new(&scenario);
+        let first = taps_along_trajectory(&mut model, &scenario);
+        model.reset();
+        assert_eq!(first, taps_along_trajectory(&mut model, &scenario));
+    }
+
+    #[test]
+    fn set_parameter_updates_both_scenario_copies() {
+        let scenario = seeded_scenario(42);
+        let mut model = NineRaySuburban::new(&scenario);
+        model.set_parameter("ground_roughness", 0.3).unwrap();
+        model.set_parameter("ground_permittivity", 5.0).unwrap();
+        assert!(model.set_parameter("unknown", 1.0).is_err());
+        assert_eq!(model.scenario.ground_roughness, 0.3);
+        assert_eq!(model.scenario.ground_permittivity, 5.0);
+        assert_eq!(model.parameters(), model.ce2r.parameters());
+    }
+}
@@ -0,0 +1,247 @@
+//! Python classes of the channel models. Every model is a subclass of `ChannelModel`, which
+//! keeps the model and its state between calls and evaluates whole trajectories given as
+//! numpy arrays without going back to Python for every position.
+
+use num::complex::Complex32;
+use numpy::ndarray::Array2;
+use numpy::{IntoPyArray, PyArray2, PyReadonlyArray2};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::collections::HashMap;
+
+use crate::models::{create_model, model_names, ChannelModel};
+use crate::{PropagationPath, Scenario, State, TapGrid, TapKernel, MAGIC_SCALING_COEFF, MAX_TAPS};
+
+/// rows of an N x 3 array, which must have as many rows as `expected` if given
+fn rows(array: Option<PyReadonlyArray2<f64>>, name: &str, expected: Option<usize>) -> PyResult<Option<Vec<[f32; 3]>>> {
+    let Some(array) = array else {
+        return Ok(None);
+    };
+    let array = array.as_array();
+    if array.ncols() != 3 || expected.is_some_and(|expected| array.nrows() != expected) {
+        return Err(PyValueError::new_err(format!(
+            "{} must have shape ({}, 3), got {:?}",
+            name,
+            expected.map_or("N".to_string(), |expected| expected.to_string()),
+            array.shape()
+        )));
+    }
+    Ok(Some(array.rows().into_iter().map(|row| [row[0] as f32, row[1] as f32, row[2] as f32]).collect()))
+}
+
+/// UAV states along a trajectory given as numpy arrays
+fn trajectory(
+    positions: PyReadonlyArray2<f64>,
+    attitudes: Option<PyReadonlyArray2<f64>>,
+    velocities: Option<PyReadonlyArray2<f64>>,
+) -> PyResult<Vec<State>> {
+    let positions = rows(Some(positions), "positions", None)?.unwrap();
+    let attitudes = rows(attitudes, "attitudes", Some(positions.len()))?;
+    let velocities = rows(velocities, "velocities", Some(positions.len()))?;
+    Ok(positions
+        .iter()
+        .enumerate()
+        .map(|(i, &[x, y, z])| {
+            let [roll, pitch, yaw] = attitudes.as_ref().map_or([0.0; 3], |attitudes| attitudes[i]);
+            State::new(x, y, z, roll, pitch, yaw).with_velocity(velocities.as_ref().map_or([0.0; 3], |velocities| velocities[i]))
+        })
+        .collect())
+}
+
+/// A channel model together with its state, e.g. the intermittent rays of the nine-ray model
+/// or the correlated fields of the stochastic models, which evolve along the trajectory.
+#[pyclass(name = "ChannelModel", subclass)]
+pub struct PyChannelModel {
+    model: Box<dyn ChannelModel>,
+    scenario: Scenario,
+}
+
+impl PyChannelModel {
+    fn create(name: &str, scenario: Option<Scenario>) -> PyResult<Self> {
+        let scenario = scenario.unwrap_or_default();
+        let model = create_model(name, &scenario)
+            .ok_or_else(|| PyValueError::new_err(format!("unknown model '{}', available: {:?}", name, model_names())))?;
+        Ok(PyChannelModel { model, scenario })
+    }
+
+    fn paths_along(&mut self, py: Python<'_>, states: Vec<State>) -> Vec<Vec<PropagationPath>> {
+        let model = &mut self.model;
+        py.allow_threads(|| states.iter().map(|state| model.paths(state)).collect())
+    }
+}
+
+#[pymethods]
+impl PyChannelModel {
+    /// the model registered under `name`, one of `model_names()`
+    #[new]
+    #[pyo3(signature = (name, scenario = None))]
+    fn py_new(name: &str, scenario: Option<Scenario>) -> PyResult<Self> {
+        PyChannelModel::create(name, scenario)
+    }
+
+    #[getter]
+    fn name(&self) -> &'static str {
+        self.model.name()
+    }
+
+    #[getter]
+    fn scenario(&self) -> Scenario {
+        self.scenario.clone()
+    }
+
+    fn parameters(&self) -> HashMap<&'static str, f64> {
+        self.model.parameters().into_iter().collect()
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f64) -> PyResult<()> {
+        self.model.set_parameter(name, value).map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    /// back to the state right after construction
+    fn reset(&mut self) {
+        self.model.reset()
+    }
+
+    /// paths at a single position; attitude in radians, `velocity` (vx, vy, vz) in m/s
+    #[pyo3(signature = (x, y, z, roll = 0.0, pitch = 0.0, yaw = 0.0, velocity = None))]
+    #[allow(clippy::too_many_arguments)]
+    fn paths(&mut self, x: f32, y: f32, z: f32, roll: f32, pitch: f32, yaw: f32, velocity: Option<[f32; 3]>) -> Vec<PropagationPath> {
+        self.model.paths(&State::new(x, y, z, roll, pitch, yaw).with_velocity(velocity.unwrap_or_default()))
+    }
+
+    /// Evaluates the model at every row of `positions` (N x 3, metres), in order, with
+    /// optional `attitudes` (roll, pitch, yaw) and `velocities` (vx, vy, vz) of the same shape.
+    /// Returns the complex gains, delays (s) and Doppler shifts (Hz) as N x P arrays, P being
+    /// the largest number of paths at any position; missing paths have gain 0 and NaN delay
+    /// and Doppler.
+    #[pyo3(signature = (positions, attitudes = None, velocities = None))]
+    #[allow(clippy::type_complexity)]
+    fn evaluate<'py>(
+        &mut self,
+        py: Python<'py>,
+        positions: PyReadonlyArray2<f64>,
+        attitudes: Option<PyReadonlyArray2<f64>>,
+        velocities: Option<PyReadonlyArray2<f64>>,
+    ) -> PyResult<(&'py PyArray2<Complex32>, &'py PyArray2<f32>, &'py PyArray2<f32>)> {
+        let states = trajectory(positions, attitudes, velocities)?;
+        let all_paths = self.paths_along(py, states);
+        let width = all_paths.iter().map(Vec::len).max().unwrap_or(0);
+        let mut gains = Array2::zeros((all_paths.len(), width));
+        let mut delays = Array2::from_elem((all_paths.len(), width), f32::NAN);
+        let mut dopplers = Array2::from_elem((all_paths.len(), width), f32::NAN);
+        for (i, paths) in all_paths.iter().enumerate() {
+            for (j, path) in paths.iter().enumerate() {
+                gains[[i, j]] = path.gain();
+                delays[[i, j]] = path.delay;
+                dopplers[[i, j]] = path.doppler;
+            }
+        }
+        Ok((gains.into_pyarray(py), delays.into_pyarray(py), dopplers.into_pyarray(py)))
+    }
+
+    /// Complex FIR taps (N x num_taps) at every row of `positions`, i.e. the paths placed on a
+    /// grid of `sample_rate` with the interpolation `kernel` ("floor", "linear" or "sinc"), as
+    /// the emulator does before scaling and quantization.
+    #[pyo3(signature = (positions, sample_rate, num_taps = 41, kernel = "floor", sinc_half_width = 4, attitudes = None, velocities = None))]
+    #[allow(clippy::too_many_arguments)]
+    fn taps<'py>(
+        &mut self,
+        py: Python<'py>,
+        positions: PyReadonlyArray2<f64>,
+        sample_rate: f32,
+        num_taps: usize,
+        kernel: &str,
+        sinc_half_width: usize,
+        attitudes: Option<PyReadonlyArray2<f64>>,
+        velocities: Option<PyReadonlyArray2<f64>>,
+    ) -> PyResult<&'py PyArray2<Complex32>> {
+        let kernel: TapKernel = kernel.parse().map_err(PyValueError::new_err)?;
+        let tap_grid = TapGrid::new(sample_rate, num_taps).with_kernel(kernel, sinc_half_width);
+        let states = trajectory(positions, attitudes, velocities)?;
+        let all_paths = self.paths_along(py, states);
+        let frequency = self.scenario.frequency;
+        let mut taps = Array2::zeros((all_paths.len(), num_taps));
+        for (i, paths) in all_paths.iter().enumerate() {
+            for (j, tap) in tap_grid.complex_taps(paths, frequency).into_iter().enumerate() {
+                taps[[i, j]] = tap;
+            }
+        }
+        Ok(taps.into_pyarray(py))
+    }
+
+    /// The taps the emulator sends at every row of `positions`, quantized with
+    /// `magic_scaling_coeff` like `taps_for_position`, as N x MAX_TAPS arrays of the real and
+    /// of the imaginary parts. The model state evolves along the trajectory as in the emulator;
+    /// small-scale fading is not applied.
+    #[pyo3(signature = (positions, sample_rate, magic_scaling_coeff = MAGIC_SCALING_COEFF, kernel = "floor", sinc_half_width = 4, attitudes = None, velocities = None))]
+    #[allow(clippy::too_many_arguments, clippy::type_complexity)]
+    fn quantized_taps<'py>(
+        &mut self,
+        py: Python<'py>,
+        positions: PyReadonlyArray2<f64>,
+        sample_rate: f32,
+        magic_scaling_coeff: f32,
+        kernel: &str,
+        sinc_half_width: usize,
+        attitudes: Option<PyReadonlyArray2<f64>>,
+        velocities: Option<PyReadonlyArray2<f64>>,
+    ) -> PyResult<(&'py PyArray2<i16>, &'py PyArray2<i16>)> {
+        let kernel: TapKernel = kernel.parse().map_err(PyValueError::new_err)?;
+        let tap_grid = TapGrid::new(sample_rate, MAX_TAPS).with_kernel(kernel, sinc_half_width);
+        let states = trajectory(positions, attitudes, velocities)?;
+        let all_paths = self.paths_along(py, states);
+        let frequency = self.scenario.frequency;
+        let mut real = Array2::zeros((all_paths.len(), MAX_TAPS));
+        let mut imaginary = Array2::zeros((all_paths.len(), MAX_TAPS));
+        for (i, paths) in all_paths.iter().enumerate() {
+            let taps = tap_grid.quantized_taps(paths, frequency, magic_scaling_coeff);
+            for j in 0..MAX_TAPS {
+                real[[i, j]] = taps[j];
+                imaginary[[i, j]] = taps[j + MAX_TAPS];
+            }
+        }
+        Ok((real.into_pyarray(py), imaginary.into_pyarray(py)))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ChannelModel('{}')", self.model.name())
+    }
+}
+
+/// one subclass of `ChannelModel` per registered model, constructed from the scenario alone
+macro_rules! model_classes {
+    ($($class:ident => $python_name:literal, $name:literal;)*) => {
+        $(
+            #[pyclass(name = $python_name, extends = PyChannelModel)]
+            struct $class;
+
+            #[pymethods]
+            impl $class {
+                #[new]
+                #[pyo3(signature = (scenario = None))]
+                fn py_new(scenario: Option<Scenario>) -> PyResult<($class, PyChannelModel)> {
+                    Ok(($class, PyChannelModel::create($name, scenario)?))
+                }
+            }
+        )*
+
+        pub(crate) fn add_model_classes(m: &PyModule) -> PyResult<()> {
+            m.add_class::<PyChannelModel>()?;
+            $(m.add_class::<$class>()?;)*
+            Ok(())
+        }
+    };
+}
+
+model_classes! {
+    PyFreeSpace => "FreeSpace", "free_space";
+    PyFlatEarthTwoRay => "FlatEarthTwoRay", "flat_earth_two_ray";
+    PyCurvedEarthTwoRay => "CurvedEarthTwoRay", "curved_earth_two_ray";
+    PyNineRaySuburban => "NineRaySuburban", "nine_ray_suburban";
+    PyTwoSegmentLogDistance => "TwoSegmentLogDistance", "two_segment_log_distance";
+    PyTr36777UmaAv => "Tr36777UmaAv", "tr36777_uma_av";
+    PyTr36777UmiAv => "Tr36777UmiAv", "tr36777_umi_av";
+    PyTr36777RmaAv => "Tr36777RmaAv", "tr36777_rma_av";
+    PyTerrainTwoRay => "TerrainTwoRay", "terrain_two_ray";
+    PyRayTracing => "RayTracing", "ray_tracing";
+}
@@ -0,0 +1,117 @@
+use num::complex::Complex64;
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use crate::buildings::{is_clear, specular_paths, Facet, SpecularPath};
+use crate::geometry::{dot, los_geometry, norm, path_geometry, station_position, sub, uav_position};
+use crate::ground::{reflection_coefficient, roughness_factor};
+use crate::models::{ground_parameters, set_ground_parameter, ChannelModel, UnknownParameter};
+use crate::{dist_to_loss, PathKind, Polarization, PropagationPath, Scenario, State, SPEED_OF_LIGHT};
+
+/// Deterministic multipath from the building scene of the scenario plus the flat ground:
+/// line-of-sight path unless a surface blocks it, and the specular reflections up to the
+/// order configured for the buildings, found with the image method. Diffraction and
+/// transmission through walls are not modelled, so a blocked UAV only receives reflections.
+pub struct RayTracing {
+    scenario: Scenario,
+    facets: Vec<Facet>,
+    max_order: usize,
+    max_paths: usize,
+}
+
+impl RayTracing {
+    /// without buildings in the scenario, only the ground reflects
+    pub fn new(scenario: &Scenario) -> Self {
+        let mut facets = vec![Facet::ground()];
+        let (mut max_order, mut max_paths) = (1, 1);
+        if let Some(buildings) = &scenario.buildings {
+            facets.extend(buildings.facets(scenario.origin.as_ref()));
+            max_order = buildings.max_order;
+            max_paths = buildings.max_paths;
+        }
+        RayTracing { scenario: scenario.clone(), facets, max_order, max_paths }
+    }
+
+    /// Reflection coefficient of a single bounce. The polarization of the scenario refers to
+    /// the ground, on walls a vertically polarized wave is polarized parallel to the surface.
+    fn reflection(&self, facet: &Facet, incidence: [f32; 3]) -> Complex64 {
+        let scenario = &self.scenario;
+        let grazing_angle = (dot(incidence, facet.normal).abs() / norm(incidence)).min(1.0).asin() as f64;
+        let polarization = match scenario.polarization {
+            Polarization::Vertical if facet.normal[2].abs() < FRAC_1_SQRT_2 => Polarization::Horizontal,
+            Polarization::Horizontal if facet.normal[2].abs() < FRAC_1_SQRT_2 => Polarization::Vertical,
+            polarization => polarization,
+        };
+        match facet.material {
+            Some(material) => {
+                let (permittivity, conductivity) = material.electrical_properties(scenario.frequency);
+                reflection_coefficient(permittivity, conductivity, scenario.frequency, grazing_angle, polarization)
+            }
+            None => {
+                let (permittivity, conductivity) = scenario.ground_properties();
+                reflection_coefficient(permittivity, conductivity, scenario.frequency, grazing_angle, polarization)
+                    * roughness_factor(scenario.ground_roughness, scenario.lambda(), grazing_angle)
+            }
+        }
+    }
+
+    fn reflected_path(&self, state: &State, path: &SpecularPath, d_los: f32) -> PropagationPath {
+        let scenario = &self.scenario;
+        let mut previous = station_position(scenario);
+        let mut length = 0.0;
+        let mut coefficient = Complex64::new(1.0, 0.0);
+        for (&point, &index) in path.points.iter().zip(&path.facets) {
+            let incidence = sub(point, previous);
+            length += norm(incidence);
+            coefficient *= self.reflection(&self.facets[index], incidence);
+            previous = point;
+        }
+        length += norm(sub(uav_position(state), previous));
+        let (magnitude, phase) = coefficient.to_polar();
+        let loss = dist_to_loss(scenario, length) / magnitude as f32;
+        let kind = if path.facets == [0] { PathKind::GroundReflection } else { PathKind::Reflection(0) };
+        let first = path.points[0];
+        let last = path.points[path.points.len() - 1];
+        PropagationPath::new(kind, loss, (length - d_los).max(0.0) / SPEED_OF_LIGHT, phase as f32)
+            .with_geometry(path_geometry(scenario, state, first, last))
+    }
+}
+
+impl ChannelModel for RayTracing {
+    fn name(&self) -> &'static str {
+        "ray_tracing"
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f64)> {
+        ground_parameters(&self.scenario)
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f64) -> Result<(), UnknownParameter> {
+        set_ground_parameter(&mut self.scenario, name, value)
+    }
+
+    fn paths(&mut self, state: &State) -> Vec<PropagationPath> {
+        let scenario = &self.scenario;
+        let (station, uav) = (station_position(scenario), uav_position(state));
+        let d_los = norm(sub(uav, station));
+        let mut paths = Vec::new();
+        if is_clear(&self.facets, station, uav, &[]) {
+            paths.push(PropagationPath::new(PathKind::LineOfSight, dist_to_loss(scenario, d_los), 0., 0.).with_geometry(los_geometry(scenario, state)));
+        }
+        let mut reflections: Vec<PropagationPath> = specular_paths(&self.facets, station, uav, self.max_order)
+            .iter()
+            .map(|path| self.reflected_path(state, path, d_los))
+            .collect();
+        reflections.sort_by(|a, b| a.loss.total_cmp(&b.loss));
+        reflections.truncate(self.max_paths.min(u8::MAX as usize + 1));
+        let mut index = 0;
+        for mut path in reflections {
+            if let PathKind::Reflection(_) = path.kind {
+                path.kind = PathKind::Reflection(index);
+                index = index.saturating_add(1);
+            }
+            paths.push(path);
+        }
+        dsl!("{} paths", paths.len());
+        paths
+    }
+}
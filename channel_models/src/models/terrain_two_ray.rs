@@ -0,0 +1,69 @@
+use num::complex::Complex64;
+
+use crate::geometry::{los_geometry, path_geometry, station_position, uav_position};
+use crate::ground::{reflection_coefficient, roughness_factor};
+use crate::models::{ground_parameters, set_ground_parameter, ChannelModel, UnknownParameter};
+use crate::terrain::Profile;
+use crate::{dist_to_loss, PathKind, PropagationPath, Scenario, State, SPEED_OF_LIGHT};
+
+/// Two-ray model over the terrain of the scenario's elevation model: the line-of-sight path
+/// is attenuated by the Deygout diffraction loss (ITU-R P.526) of the terrain profile between
+/// station and UAV, and the ground reflection is placed on the terrain facet that reflects
+/// specularly. Without terrain (or origin) the ground is flat at height 0.
+pub struct TerrainTwoRay {
+    scenario: Scenario,
+}
+
+impl TerrainTwoRay {
+    pub fn new(scenario: &Scenario) -> Self {
+        TerrainTwoRay { scenario: scenario.clone() }
+    }
+}
+
+impl ChannelModel for TerrainTwoRay {
+    fn name(&self) -> &'static str {
+        "terrain_two_ray"
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f64)> {
+        ground_parameters(&self.scenario)
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f64) -> Result<(), UnknownParameter> {
+        set_ground_parameter(&mut self.scenario, name, value)
+    }
+
+    fn paths(&mut self, state: &State) -> Vec<PropagationPath> {
+        let scenario = &self.scenario;
+        let profile = Profile::between(scenario, state);
+        let d_los = scenario.distance(state.x, state.y, state.z);
+        let diffraction_loss = profile.diffraction_loss(scenario.lambda());
+        dsl!("distance {}, diffraction loss {}dB", d_los, diffraction_loss);
+        let loss_los = dist_to_loss(scenario, d_los) * 10.0_f32.powf(diffraction_loss / 20.0);
+        let mut paths = vec![
+            PropagationPath::new(PathKind::LineOfSight, loss_los, 0., 0.).with_geometry(los_geometry(scenario, state)),
+        ];
+        if let Some(reflection) = profile.reflection() {
+            let psi = reflection.grazing_angle as f64;
+            let (epsilon_r, sigma) = scenario.ground_properties();
+            let rho: Complex64 = reflection_coefficient(epsilon_r, sigma, scenario.frequency, psi, scenario.polarization);
+            let (gamma, phase) = rho.to_polar();
+            let r_f = roughness_factor(scenario.ground_roughness, scenario.lambda(), psi);
+            let loss_nlos = dist_to_loss(scenario, reflection.length) / (gamma * r_f) as f32;
+            let delta_t = (reflection.length - d_los).max(0.0) / SPEED_OF_LIGHT;
+            let station = station_position(scenario);
+            let uav = uav_position(state);
+            let fraction = reflection.point.0 / profile.horizontal_distance();
+            let point = [
+                station[0] + fraction * (uav[0] - station[0]),
+                station[1] + fraction * (uav[1] - station[1]),
+                reflection.point.1,
+            ];
+            paths.push(
+                PropagationPath::new(PathKind::GroundReflection, loss_nlos, delta_t, phase as f32)
+                    .with_geometry(path_geometry(scenario, state, point, point)),
+            );
+        }
+        paths
+    }
+}
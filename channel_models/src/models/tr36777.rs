@@ -0,0 +1,467 @@
+use rand::Rng;
+use rand_distr::{Distribution, Normal, Uniform};
+use std::f32::consts::PI;
+
+use crate::geometry::los_geometry;
+use crate::models::{resolve_seed, seeded_rng, ChannelModel, ModelRng};
+use crate::shadowing::CorrelatedField;
+use crate::{PathKind, PropagationPath, Scenario, State, SPEED_OF_LIGHT};
+
+// random streams of the fields and cluster draws, independent of the other stochastic parts
+// sharing the same seed
+const LOS_STATE_STREAM: u64 = 2;
+const LOS_SHADOW_FADING_STREAM: u64 = 3;
+const NLOS_SHADOW_FADING_STREAM: u64 = 4;
+const CLUSTER_STREAM: u64 = 5;
+
+/// standard deviation of the per-cluster shadowing in dB, TR 38.901 table 7.5-6
+const CLUSTER_SHADOWING_STD: f32 = 3.0;
+/// highest UE height in metres the aerial formulas of TR 36.777 are defined for, UAVs above
+/// it are treated as flying at this height
+const MAX_UT_HEIGHT: f32 = 300.0;
+
+/// Deployment scenarios of TR 36.777 annex B. Below the height thresholds of the aerial
+/// extensions the terrestrial TR 38.901 models apply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AerialScenario {
+    UrbanMacro,
+    UrbanMicro,
+    RuralMacro,
+}
+
+/// LOS probability `d_1 / d_2d + exp(-d_2d / p_1) * (1 - d_1 / d_2d)` of the aerial UEs
+fn aerial_los_probability(d_2d: f32, d_1: f32, p_1: f32) -> f32 {
+    if d_2d <= d_1 {
+        1.0
+    } else {
+        d_1 / d_2d + (-d_2d / p_1).exp() * (1.0 - d_1 / d_2d)
+    }
+}
+
+/// TR 38.901 UMa/UMi shape with the given decay distance
+fn terrestrial_los_probability(d_2d: f32, decay: f32) -> f32 {
+    if d_2d <= 18.0 {
+        1.0
+    } else {
+        18.0 / d_2d + (-d_2d / decay).exp() * (1.0 - 18.0 / d_2d)
+    }
+}
+
+fn free_space_path_loss(d_3d: f32, f_ghz: f32) -> f32 {
+    32.45 + 20.0 * d_3d.log10() + 20.0 * f_ghz.log10()
+}
+
+/// TR 38.901 RMa LOS path loss before the breakpoint, average building height 5 m
+fn rma_pl_1(d_3d: f32, f_ghz: f32) -> f32 {
+    let h = 5.0_f32;
+    20.0 * (40.0 * PI * d_3d * f_ghz / 3.0).log10() + (0.03 * h.powf(1.72)).min(10.0) * d_3d.log10()
+        - (0.044 * h.powf(1.72)).min(14.77)
+        + 0.002 * h.log10() * d_3d
+}
+
+/// Gaussian CDF via the error function approximation 7.1.26 of Abramowitz and Stegun
+fn standard_normal_cdf(x: f32) -> f32 {
+    let z = x.abs() / 2.0_f32.sqrt();
+    let t = 1.0 / (1.0 + 0.3275911 * z);
+    let polynomial = t * (0.2548296 + t * (-0.28449674 + t * (1.4214138 + t * (-1.4531521 + t * 1.0614054))));
+    let erf = 1.0 - polynomial * (-z * z).exp();
+    0.5 * (1.0 + erf.copysign(x))
+}
+
+impl AerialScenario {
+    /// probability of a line-of-sight link, TR 36.777 table B-1
+    pub fn los_probability(&self, d_2d: f32, h_ut: f32) -> f32 {
+        let h_ut = h_ut.min(MAX_UT_HEIGHT);
+        let log_h = h_ut.log10();
+        match self {
+            AerialScenario::UrbanMacro if h_ut <= 22.5 => {
+                let c = if h_ut <= 13.0 { 0.0 } else { ((h_ut - 13.0) / 10.0).powf(1.5) };
+                let p = terrestrial_los_probability(d_2d, 63.0);
+                if d_2d <= 18.0 {
+                    p
+                } else {
+                    p * (1.0 + c * 5.0 / 4.0 * (d_2d / 100.0).powi(3) * (-d_2d / 150.0).exp())
+                }
+            }
+            AerialScenario::UrbanMacro if h_ut <= 100.0 => {
+                aerial_los_probability(d_2d, (460.0 * log_h - 700.0).max(18.0), 4300.0 * log_h - 3800.0)
+            }
+            AerialScenario::UrbanMacro => 1.0,
+            AerialScenario::UrbanMicro if h_ut <= 22.5 => terrestrial_los_probability(d_2d, 36.0),
+            AerialScenario::UrbanMicro => {
+                aerial_los_probability(d_2d, (294.05 * log_h - 432.94).max(18.0), 233.98 * log_h - 0.95)
+            }
+            AerialScenario::RuralMacro if h_ut <= 10.0 => {
+                if d_2d <= 10.0 {
+                    1.0
+                } else {
+                    (-(d_2d - 10.0) / 1000.0).exp()
+                }
+            }
+            AerialScenario::RuralMacro if h_ut <= 40.0 => aerial_los_probability(
+                d_2d,
+                (1350.8 * log_h - 1602.0).max(18.0),
+                (15021.0 * log_h - 16053.0).max(1000.0),
+            ),
+            AerialScenario::RuralMacro => 1.0,
+        }
+    }
+
+    /// (path loss, shadow fading standard deviation) in dB of a LOS link, TR 36.777 table B-2
+    /// and TR 38.901 table 7.4.1-1
+    pub fn los_path_loss(&self, d_2d: f32, d_3d: f32, h_bs: f32, h_ut: f32, frequency: f32) -> (f32, f32) {
+        let h_ut = h_ut.min(MAX_UT_HEIGHT);
+        let f_ghz = frequency / 1e9;
+        let log_h = h_ut.log10();
+        match self {
+            AerialScenario::UrbanMacro if h_ut > 22.5 => {
+                (28.0 + 22.0 * d_3d.log10() + 20.0 * f_ghz.log10(), 4.64 * (-0.0066 * h_ut).exp())
+            }
+            AerialScenario::UrbanMicro if h_ut > 22.5 => (
+                free_space_path_loss(d_3d, f_ghz).max(30.9 + (22.25 - 0.5 * log_h) * d_3d.log10() + 20.0 * f_ghz.log10()),
+                (5.0 * (-0.01 * h_ut).exp()).max(2.0),
+            ),
+            AerialScenario::UrbanMacro | AerialScenario::UrbanMicro => {
+                let (intercept, slope, breakpoint_term) = if *self == AerialScenario::UrbanMacro { (28.0, 22.0, 9.0) } else { (32.4, 21.0, 9.5) };
+                let breakpoint = 4.0 * (h_bs - 1.0).max(0.0) * (h_ut - 1.0).max(0.0) * frequency / SPEED_OF_LIGHT;
+                let path_loss = if d_2d <= breakpoint {
+                    intercept + slope * d_3d.log10() + 20.0 * f_ghz.log10()
+                } else {
+                    intercept + 40.0 * d_3d.log10() + 20.0 * f_ghz.log10()
+                        - breakpoint_term * (breakpoint.powi(2) + (h_bs - h_ut).powi(2)).log10()
+                };
+                (path_loss, 4.0)
+            }
+            AerialScenario::RuralMacro if h_ut > 10.0 => (
+                (23.9 - 1.8 * log_h).max(20.0) * d_3d.log10() + 20.0 * (40.0 * PI * f_ghz / 3.0).log10(),
+                4.2 * (-0.0046 * h_ut).exp(),
+            ),
+            AerialScenario::RuralMacro => {
+                let breakpoint = 2.0 * PI * h_bs * h_ut * frequency / SPEED_OF_LIGHT;
+                if d_2d <= breakpoint {
+                    (rma_pl_1(d_3d, f_ghz), 4.0)
+                } else {
+                    (rma_pl_1(breakpoint, f_ghz) + 40.0 * (d_3d / breakpoint).log10(), 6.0)
+                }
+            }
+        }
+    }
+
+    /// (path loss, shadow fading standard deviation) in dB of a NLOS link, never below the
+    /// LOS path loss
+    pub fn nlos_path_loss(&self, d_2d: f32, d_3d: f32, h_bs: f32, h_ut: f32, frequency: f32) -> (f32, f32) {
+        let h_ut = h_ut.min(MAX_UT_HEIGHT);
+        let f_ghz = frequency / 1e9;
+        let log_h = h_ut.log10();
+        let (los, _) = self.los_path_loss(d_2d, d_3d, h_bs, h_ut, frequency);
+        let (nlos, std_dev) = match self {
+            AerialScenario::UrbanMacro if h_ut > 10.0 => {
+                (-17.5 + (46.0 - 7.0 * log_h) * d_3d.log10() + 20.0 * (40.0 * PI * f_ghz / 3.0).log10(), 6.0)
+            }
+            AerialScenario::UrbanMacro => {
+                (13.54 + 39.08 * d_3d.log10() + 20.0 * f_ghz.log10() - 0.6 * (h_ut - 1.5), 6.0)
+            }
+            AerialScenario::UrbanMicro if h_ut > 22.5 => {
+                (32.4 + (43.2 - 7.6 * log_h) * d_3d.log10() + 20.0 * f_ghz.log10(), 8.0)
+            }
+            AerialScenario::UrbanMicro => {
+                (35.3 * d_3d.log10() + 22.4 + 21.3 * f_ghz.log10() - 0.3 * (h_ut - 1.5), 7.82)
+            }
+            AerialScenario::RuralMacro if h_ut > 10.0 => {
+                (-12.0 + (35.0 - 5.3 * log_h) * d_3d.log10() + 20.0 * (40.0 * PI * f_ghz / 3.0).log10(), 6.0)
+            }
+            AerialScenario::RuralMacro => {
+                let (w, h) = (20.0_f32, 5.0_f32);
+                (
+                    161.04 - 7.1 * w.log10() + 7.5 * h.log10() - (24.37 - 3.7 * (h / h_bs).powi(2)) * h_bs.log10()
+                        + (43.42 - 3.1 * h_bs.log10()) * (d_3d.log10() - 3.0)
+                        + 20.0 * f_ghz.log10()
+                        - (3.2 * (11.75 * h_ut).log10().powi(2) - 4.97)
+                        - 0.6 * (h_ut - 1.5),
+                    8.0,
+                )
+            }
+        };
+        (nlos.max(los), std_dev)
+    }
+
+    /// (mean, standard deviation) of log10(delay spread / 1 s), TR 38.901 table 7.5-6
+    pub fn delay_spread(&self, los: bool, frequency: f32) -> (f32, f32) {
+        let f_ghz = frequency / 1e9;
+        match (self, los) {
+            (AerialScenario::UrbanMacro, true) => (-6.955 - 0.0963 * f_ghz.max(6.0).log10(), 0.66),
+            #[allow(clippy::approx_constant)]
+            (AerialScenario::UrbanMacro, false) => (-6.28 - 0.204 * f_ghz.max(6.0).log10(), 0.39),
+            (AerialScenario::UrbanMicro, true) => (-0.24 * (1.0 + f_ghz.max(2.0)).log10() - 7.14, 0.38),
+            (AerialScenario::UrbanMicro, false) => {
+                let log_f = (1.0 + f_ghz.max(2.0)).log10();
+                (-0.24 * log_f - 6.83, 0.16 * log_f + 0.28)
+            }
+            (AerialScenario::RuralMacro, true) => (-7.49, 0.55),
+            (AerialScenario::RuralMacro, false) => (-7.43, 0.48),
+        }
+    }
+
+    /// (number of clusters, delay scaling parameter r_tau) for the LOS or NLOS state
+    fn clusters(&self, los: bool) -> (usize, f32) {
+        match (self, los) {
+            (AerialScenario::UrbanMacro, true) => (12, 2.5),
+            (AerialScenario::UrbanMacro, false) => (20, 2.3),
+            (AerialScenario::UrbanMicro, true) => (12, 3.0),
+            (AerialScenario::UrbanMicro, false) => (19, 2.1),
+            (AerialScenario::RuralMacro, true) => (11, 3.8),
+            (AerialScenario::RuralMacro, false) => (10, 1.7),
+        }
+    }
+
+    /// (mean, standard deviation) of the Rician K-factor in dB of LOS links
+    fn k_factor(&self) -> (f32, f32) {
+        match self {
+            AerialScenario::UrbanMacro => (9.0, 3.5),
+            AerialScenario::UrbanMicro => (9.0, 5.0),
+            AerialScenario::RuralMacro => (7.0, 4.0),
+        }
+    }
+
+    /// correlation distances in metres of (LOS state, LOS shadow fading, NLOS shadow fading)
+    fn correlation_distances(&self) -> (f32, f32, f32) {
+        match self {
+            AerialScenario::UrbanMacro => (50.0, 37.0, 50.0),
+            AerialScenario::UrbanMicro => (50.0, 10.0, 13.0),
+            AerialScenario::RuralMacro => (60.0, 37.0, 120.0),
+        }
+    }
+
+    /// distance in metres after which the clusters are drawn anew, the correlation distance of
+    /// the delay spread in the LOS or NLOS state
+    fn cluster_redraw_distance(&self, los: bool) -> f32 {
+        match (self, los) {
+            (AerialScenario::UrbanMacro, true) => 30.0,
+            (AerialScenario::UrbanMacro, false) => 40.0,
+            (AerialScenario::UrbanMicro, true) => 7.0,
+            (AerialScenario::UrbanMicro, false) => 10.0,
+            (AerialScenario::RuralMacro, true) => 50.0,
+            (AerialScenario::RuralMacro, false) => 36.0,
+        }
+    }
+}
+
+/// one set of clusters, kept while the UAV stays near where it was drawn
+struct Clusters {
+    los: bool,
+    origin: [f32; 3],
+    /// power share of the direct path, zero in the NLOS state
+    los_power: f32,
+    /// (delay in s, power share, phase, arrival azimuth) per cluster
+    clusters: Vec<(f32, f32, f32, f32)>,
+}
+
+/// Clusters with exponential delays and powers following TR 38.901 7.5 steps 5 and 6
+fn draw_clusters<R: Rng + ?Sized>(aerial: AerialScenario, los: bool, frequency: f32, origin: [f32; 3], rng: &mut R) -> Clusters {
+    let (count, r_tau) = aerial.clusters(los);
+    let (ds_mean, ds_std) = aerial.delay_spread(los, frequency);
+    let delay_spread = 10.0_f32.powf(Normal::new(ds_mean, ds_std).unwrap().sample(rng));
+    let uniform = Uniform::new(f32::EPSILON, 1.0);
+    let mut delays: Vec<f32> = (0..count).map(|_| -r_tau * delay_spread * uniform.sample(rng).ln()).collect();
+    delays.sort_by(|a, b| a.total_cmp(b));
+    let min_delay = delays[0];
+    let cluster_shadowing = Normal::new(0.0, CLUSTER_SHADOWING_STD).unwrap();
+    let mut clusters: Vec<(f32, f32, f32, f32)> = delays
+        .into_iter()
+        .map(|delay| {
+            let delay = delay - min_delay;
+            let power = (-delay * (r_tau - 1.0) / (r_tau * delay_spread)).exp() * 10.0_f32.powf(-cluster_shadowing.sample(rng) / 10.0);
+            (delay, power, 2.0 * PI * uniform.sample(rng), 2.0 * PI * uniform.sample(rng))
+        })
+        .collect();
+    let los_power = if los {
+        let (k_mean, k_std) = aerial.k_factor();
+        let k = 10.0_f32.powf(Normal::new(k_mean, k_std).unwrap().sample(rng) / 10.0);
+        k / (k + 1.0)
+    } else {
+        0.0
+    };
+    let total: f32 = clusters.iter().map(|cluster| cluster.1).sum();
+    for cluster in &mut clusters {
+        cluster.1 *= (1.0 - los_power) / total;
+    }
+    Clusters { los, origin, los_power, clusters }
+}
+
+/// Height-dependent aerial-vehicle channel of 3GPP TR 36.777 annex B: LOS probability, path
+/// loss and shadow fading of the aerial UE, with the delay spread, cluster and K-factor
+/// statistics of TR 38.901. The ground station height is the base station height.
+///
+/// The LOS state and the shadow fading are spatially consistent: both follow Gaussian fields
+/// over the horizontal UAV position with the correlation distances of TR 38.901, the LOS state
+/// compares the uniform transform of its field with the LOS probability. The clusters are
+/// drawn anew when the LOS state changes or the UAV moved beyond the delay spread correlation
+/// distance. Above `MAX_UT_HEIGHT` the formulas are evaluated at that height.
+pub struct Tr36777 {
+    scenario: Scenario,
+    aerial: AerialScenario,
+    seed: u64,
+    los_state: CorrelatedField,
+    los_shadow_fading: CorrelatedField,
+    nlos_shadow_fading: CorrelatedField,
+    rng: ModelRng,
+    clusters: Option<Clusters>,
+}
+
+impl Tr36777 {
+    /// uses the seed of the scenario, or a random one if the scenario does not fix it
+    pub fn new(scenario: &Scenario, aerial: AerialScenario) -> Self {
+        let seed = resolve_seed(scenario);
+        let (los_distance, los_sf_distance, nlos_sf_distance) = aerial.correlation_distances();
+        let field = |stream: u64, distance: f32| {
+            let mut rng = seeded_rng(seed);
+            rng.set_stream(stream);
+            CorrelatedField::exponential(distance, &mut rng)
+        };
+        Tr36777 {
+            scenario: scenario.clone(),
+            aerial,
+            seed,
+            los_state: field(LOS_STATE_STREAM, los_distance),
+            los_shadow_fading: field(LOS_SHADOW_FADING_STREAM, los_sf_distance),
+            nlos_shadow_fading: field(NLOS_SHADOW_FADING_STREAM, nlos_sf_distance),
+            rng: Self::cluster_rng(seed),
+            clusters: None,
+        }
+    }
+
+    pub fn uma(scenario: &Scenario) -> Self {
+        Tr36777::new(scenario, AerialScenario::UrbanMacro)
+    }
+
+    pub fn umi(scenario: &Scenario) -> Self {
+        Tr36777::new(scenario, AerialScenario::UrbanMicro)
+    }
+
+    pub fn rma(scenario: &Scenario) -> Self {
+        Tr36777::new(scenario, AerialScenario::RuralMacro)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn cluster_rng(seed: u64) -> ModelRng {
+        let mut rng = seeded_rng(seed);
+        rng.set_stream(CLUSTER_STREAM);
+        rng
+    }
+
+    /// whether the link at the given UAV position is in line of sight
+    pub fn is_los(&self, state: &State) -> bool {
+        let d_2d = self.scenario.distance(state.x, state.y, self.scenario.station_z);
+        let h_ut = state.z.max(1.5);
+        standard_normal_cdf(self.los_state.value(state.x, state.y)) < self.aerial.los_probability(d_2d, h_ut)
+    }
+}
+
+impl ChannelModel for Tr36777 {
+    fn name(&self) -> &'static str {
+        match self.aerial {
+            AerialScenario::UrbanMacro => "tr36777_uma_av",
+            AerialScenario::UrbanMicro => "tr36777_umi_av",
+            AerialScenario::RuralMacro => "tr36777_rma_av",
+        }
+    }
+
+    fn paths(&mut self, state: &State) -> Vec<PropagationPath> {
+        let scenario = &self.scenario;
+        let los = self.is_los(state);
+        let h_bs = scenario.station_z;
+        let h_ut = state.z.max(1.5);
+        // below 10 m the terrestrial fits are not valid
+        let d_2d = scenario.distance(state.x, state.y, h_bs).max(10.0);
+        let d_3d = (d_2d.powi(2) + (h_ut - h_bs).powi(2)).sqrt();
+        let (path_loss, shadow_fading) = if los {
+            let (path_loss, std_dev) = self.aerial.los_path_loss(d_2d, d_3d, h_bs, h_ut, scenario.frequency);
+            (path_loss, std_dev * self.los_shadow_fading.value(state.x, state.y))
+        } else {
+            let (path_loss, std_dev) = self.aerial.nlos_path_loss(d_2d, d_3d, h_bs, h_ut, scenario.frequency);
+            (path_loss, std_dev * self.nlos_shadow_fading.value(state.x, state.y))
+        };
+        dsl!("los {}, path loss {}dB, shadow fading {}dB", los, path_loss, shadow_fading);
+        let position = [state.x, state.y, state.z];
+        let redraw = match &self.clusters {
+            Some(clusters) => {
+                let moved = ((0..3).map(|i| (position[i] - clusters.origin[i]).powi(2)).sum::<f32>()).sqrt();
+                clusters.los != los || moved > self.aerial.cluster_redraw_distance(los)
+            }
+            None => true,
+        };
+        if redraw {
+            self.clusters = Some(draw_clusters(self.aerial, los, scenario.frequency, position, &mut self.rng));
+        }
+        let clusters = self.clusters.as_ref().unwrap();
+        let amplitude_loss = 10.0_f32.powf((path_loss + shadow_fading) / 20.0);
+        let mut paths = Vec::with_capacity(clusters.clusters.len() + 1);
+        if los {
+            paths.push(
+                PropagationPath::new(PathKind::LineOfSight, amplitude_loss / clusters.los_power.sqrt(), 0., 0.)
+                    .with_geometry(los_geometry(scenario, state)),
+            );
+        }
+        for (k, &(delay, power, phase, azimuth)) in clusters.clusters.iter().enumerate() {
+            let mut path = PropagationPath::new(PathKind::Cluster(k as u8), amplitude_loss / power.sqrt(), delay, phase);
+            path.doppler = (state.velocity[0] * azimuth.cos() + state.velocity[1] * azimuth.sin()) / scenario.lambda();
+            paths.push(path);
+        }
+        paths
+    }
+
+    fn reset(&mut self) {
+        self.rng = Self::cluster_rng(self.seed);
+        self.clusters = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(value: f32, expected: f32) {
+        assert!((value - expected).abs() < 1e-3 * expected.abs().max(1.0), "{} != {}", value, expected);
+    }
+
+    // reference values evaluated independently from the formulas of TR 36.777 tables B-1/B-2
+
+    #[test]
+    fn los_probability_table_b1() {
+        assert_close(AerialScenario::UrbanMacro.los_probability(500.0, 50.0), 0.888);
+        assert_close(AerialScenario::UrbanMicro.los_probability(300.0, 100.0), 0.771);
+        assert_close(AerialScenario::RuralMacro.los_probability(1500.0, 20.0), 0.686);
+        // always LOS above 100 m (UMa) and 40 m (RMa), and within d_1
+        assert_eq!(AerialScenario::UrbanMacro.los_probability(5000.0, 150.0), 1.0);
+        assert_eq!(AerialScenario::RuralMacro.los_probability(5000.0, 50.0), 1.0);
+        assert_eq!(AerialScenario::UrbanMicro.los_probability(100.0, 200.0), 1.0);
+    }
+
+    #[test]
+    fn path_loss_table_b2() {
+        let f = 2e9;
+        let (path_loss, std_dev) = AerialScenario::UrbanMacro.los_path_loss(997.0, 1000.0, 25.0, 100.0, f);
+        assert_close(path_loss, 100.020);
+        assert_close(std_dev, 2.398);
+        let (path_loss, std_dev) = AerialScenario::UrbanMicro.los_path_loss(490.0, 500.0, 10.0, 100.0, f);
+        assert_close(path_loss, 94.273);
+        assert_close(std_dev, 2.0);
+        let (path_loss, std_dev) = AerialScenario::RuralMacro.los_path_loss(1999.0, 2000.0, 35.0, 50.0, f);
+        assert_close(path_loss, 107.261);
+        assert_close(std_dev, 3.337);
+        assert_close(AerialScenario::UrbanMacro.nlos_path_loss(999.0, 1000.0, 25.0, 50.0, f).0, 123.284);
+        assert_close(AerialScenario::UrbanMicro.nlos_path_loss(490.0, 500.0, 10.0, 100.0, f).0, 113.991);
+    }
+
+    #[test]
+    fn heights_above_the_table_are_clamped() {
+        for aerial in [AerialScenario::UrbanMacro, AerialScenario::UrbanMicro, AerialScenario::RuralMacro] {
+            assert_eq!(aerial.los_probability(800.0, 500.0), aerial.los_probability(800.0, MAX_UT_HEIGHT));
+            assert_eq!(aerial.los_path_loss(800.0, 900.0, 25.0, 500.0, 2e9), aerial.los_path_loss(800.0, 900.0, 25.0, MAX_UT_HEIGHT, 2e9));
+            assert_eq!(aerial.nlos_path_loss(800.0, 900.0, 25.0, 500.0, 2e9), aerial.nlos_path_loss(800.0, 900.0, 25.0, MAX_UT_HEIGHT, 2e9));
+        }
+    }
+}
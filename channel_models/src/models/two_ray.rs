@@ -0,0 +1,39 @@
+use crate::geometry::{ground_reflection_geometry, los_geometry};
+use crate::models::ChannelModel;
+use crate::{dist_to_loss, PathKind, PropagationPath, Scenario, State, SPEED_OF_LIGHT};
+
+/// Line-of-sight path plus a ground reflection on a flat, perfectly reflecting earth
+pub struct FlatEarthTwoRay {
+    scenario: Scenario,
+}
+
+impl FlatEarthTwoRay {
+    pub fn new(scenario: &Scenario) -> Self {
+        FlatEarthTwoRay { scenario: scenario.clone() }
+    }
+}
+
+impl ChannelModel for FlatEarthTwoRay {
+    fn name(&self) -> &'static str {
+        "flat_earth_two_ray"
+    }
+
+    fn paths(&mut self, state: &State) -> Vec<PropagationPath> {
+        let scenario = &self.scenario;
+        let (x, y, z) = (state.x, state.y, state.z);
+        let d_los = scenario.distance(x, y, z);
+        let d_nlos = scenario.distance(x, y, z + 2. * scenario.station_z);
+        let delta_d = d_nlos - d_los;
+        let delta_t = delta_d / SPEED_OF_LIGHT;
+        let loss_los = dist_to_loss(scenario, d_los);
+        let loss_nlos = dist_to_loss(scenario, d_nlos);
+        // the image of the station splits the horizontal distance in the ratio of the heights
+        let reflection_fraction = scenario.station_z / (scenario.station_z + z).max(f32::EPSILON);
+        vec![
+            PropagationPath::new(PathKind::LineOfSight, loss_los, 0., 0.)
+                .with_geometry(los_geometry(scenario, state)),
+            PropagationPath::new(PathKind::GroundReflection, loss_nlos, delta_t, 0.)
+                .with_geometry(ground_reflection_geometry(scenario, state, reflection_fraction)),
+        ]
+    }
+}
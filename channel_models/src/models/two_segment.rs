@@ -0,0 +1,207 @@
+use pyo3::prelude::*;
+use rand_distr::{Distribution, Normal};
+use serde::Deserialize;
+
+use crate::geometry::los_geometry;
+use crate::models::{resolve_seed, seeded_rng, ChannelModel, ModelRng, UnknownParameter};
+use crate::{PathKind, PropagationPath, Scenario, State};
+
+/// Log-distance path loss `intercept + 10 * exponent * log10(d / reference_distance) + X
+/// + direction_offset * y` in dB with Gaussian shadowing X and y = +1 when flying towards the
+/// ground station, -1 when flying away.
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LogDistanceSegment {
+    #[pyo3(get, set)]
+    pub intercept: f32,
+    #[pyo3(get, set)]
+    pub exponent: f32,
+    /// metres
+    #[pyo3(get, set)]
+    pub reference_distance: f32,
+    /// standard deviation of the shadowing in dB
+    #[pyo3(get, set)]
+    pub shadowing_std: f32,
+    #[pyo3(get, set)]
+    pub direction_offset: f32,
+}
+
+#[pymethods]
+impl LogDistanceSegment {
+    #[new]
+    fn py_new(intercept: f32, exponent: f32, reference_distance: f32, shadowing_std: f32, direction_offset: f32) -> Self {
+        LogDistanceSegment { intercept, exponent, reference_distance, shadowing_std, direction_offset }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// Constants of the two-segment model: one log-distance fit below and one above an elevation
+/// angle threshold. The defaults are the fits of the air-ground measurement campaign for
+/// elevations below and above 5°.
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TwoSegmentParameters {
+    /// degrees
+    #[pyo3(get, set)]
+    pub elevation_threshold: f32,
+    #[pyo3(get, set)]
+    pub low: LogDistanceSegment,
+    #[pyo3(get, set)]
+    pub high: LogDistanceSegment,
+}
+
+impl Default for TwoSegmentParameters {
+    fn default() -> Self {
+        TwoSegmentParameters {
+            elevation_threshold: 5.0,
+            low: LogDistanceSegment {
+                intercept: 116.4,
+                exponent: 1.6,
+                reference_distance: 3000.0,
+                shadowing_std: 3.3,
+                direction_offset: 3.0,
+            },
+            high: LogDistanceSegment {
+                intercept: 123.5,
+                exponent: 1.8,
+                reference_distance: 6600.0,
+                shadowing_std: 3.0,
+                direction_offset: 1.7,
+            },
+        }
+    }
+}
+
+#[pymethods]
+impl TwoSegmentParameters {
+    #[new]
+    fn py_new() -> Self {
+        TwoSegmentParameters::default()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// Elevation-dependent two-segment log-distance model with a direction-of-travel term, a
+/// single line-of-sight path. The shadowing is drawn independently for every update; use
+/// the shadowing layer of the scenario with `shadowing_std` set to 0 for correlated shadowing.
+pub struct TwoSegmentLogDistance {
+    scenario: Scenario,
+    seed: u64,
+    rng: ModelRng,
+}
+
+impl TwoSegmentLogDistance {
+    /// uses the seed of the scenario, or a random one if the scenario does not fix it
+    pub fn new(scenario: &Scenario) -> Self {
+        let seed = resolve_seed(scenario);
+        TwoSegmentLogDistance { scenario: scenario.clone(), seed, rng: seeded_rng(seed) }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// +1 when the UAV flies towards the ground station, -1 when it flies away. The direction
+    /// of travel is the horizontal velocity if known, else the heading given by the yaw.
+    fn direction_of_travel(&self, state: &State) -> f32 {
+        let (heading_x, heading_y) = if state.velocity[0] != 0.0 || state.velocity[1] != 0.0 {
+            (state.velocity[0], state.velocity[1])
+        } else {
+            (state.yaw.cos(), state.yaw.sin())
+        };
+        let away_x = state.x - self.scenario.station_x;
+        let away_y = state.y - self.scenario.station_y;
+        if away_x * heading_x + away_y * heading_y > 0.0 {
+            -1.0
+        } else {
+            1.0
+        }
+    }
+}
+
+const PARAMETER_NAMES: [&str; 11] = [
+    "elevation_threshold",
+    "low_intercept",
+    "low_exponent",
+    "low_reference_distance",
+    "low_shadowing_std",
+    "low_direction_offset",
+    "high_intercept",
+    "high_exponent",
+    "high_reference_distance",
+    "high_shadowing_std",
+    "high_direction_offset",
+];
+
+impl ChannelModel for TwoSegmentLogDistance {
+    fn name(&self) -> &'static str {
+        "two_segment_log_distance"
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f64)> {
+        let TwoSegmentParameters { elevation_threshold, low, high } = self.scenario.two_segment;
+        let values = [
+            elevation_threshold,
+            low.intercept,
+            low.exponent,
+            low.reference_distance,
+            low.shadowing_std,
+            low.direction_offset,
+            high.intercept,
+            high.exponent,
+            high.reference_distance,
+            high.shadowing_std,
+            high.direction_offset,
+        ];
+        PARAMETER_NAMES.into_iter().zip(values.map(|value| value as f64)).collect()
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f64) -> Result<(), UnknownParameter> {
+        let parameters = &mut self.scenario.two_segment;
+        let value = value as f32;
+        match name {
+            "elevation_threshold" => parameters.elevation_threshold = value,
+            "low_intercept" => parameters.low.intercept = value,
+            "low_exponent" => parameters.low.exponent = value,
+            "low_reference_distance" => parameters.low.reference_distance = value,
+            "low_shadowing_std" => parameters.low.shadowing_std = value,
+            "low_direction_offset" => parameters.low.direction_offset = value,
+            "high_intercept" => parameters.high.intercept = value,
+            "high_exponent" => parameters.high.exponent = value,
+            "high_reference_distance" => parameters.high.reference_distance = value,
+            "high_shadowing_std" => parameters.high.shadowing_std = value,
+            "high_direction_offset" => parameters.high.direction_offset = value,
+            _ => return Err(UnknownParameter(name.to_string())),
+        }
+        Ok(())
+    }
+
+    fn paths(&mut self, state: &State) -> Vec<PropagationPath> {
+        let scenario = &self.scenario;
+        let dist = scenario.distance(state.x, state.y, state.z);
+        let horizontal_dist = scenario.distance(state.x, state.y, scenario.station_z);
+        let elevation = (state.z - scenario.station_z).atan2(horizontal_dist);
+        let parameters = &scenario.two_segment;
+        let segment = if elevation < parameters.elevation_threshold.to_radians() { parameters.low } else { parameters.high };
+        let shadowing = Normal::new(0.0, segment.shadowing_std.max(0.0)).unwrap().sample(&mut self.rng);
+        let path_loss = segment.intercept
+            + 10.0 * segment.exponent * (dist / segment.reference_distance).log10()
+            + shadowing
+            + self.direction_of_travel(state) * segment.direction_offset;
+        dsl!("distance {}, elevation {}rad, path-loss {}dB", dist, elevation, path_loss);
+        let loss = 10.0_f32.powf(path_loss / 20.0);
+        vec![PropagationPath::new(PathKind::LineOfSight, loss, 0., 0.).with_geometry(los_geometry(&self.scenario, state))]
+    }
+
+    fn reset(&mut self) {
+        self.rng = seeded_rng(self.seed);
+    }
+}
@@ -0,0 +1,143 @@
+use num::complex::Complex32;
+use pyo3::prelude::*;
+
+use crate::geometry::PathGeometry;
+
+/// Physical origin of a propagation path
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PathKind {
+    LineOfSight,
+    GroundReflection,
+    /// k-th (zero based) intermittent multipath component of the nine-ray model
+    Intermittent(u8),
+    /// k-th (zero based) scattering cluster of a stochastic model
+    Cluster(u8),
+    /// k-th (zero based) specular reflection off the buildings, strongest first
+    Reflection(u8),
+}
+
+impl PathKind {
+    /// short label, also used as `kind` on the Python side
+    pub fn label(&self) -> &'static str {
+        match self {
+            PathKind::LineOfSight => "los",
+            PathKind::GroundReflection => "ground_reflection",
+            PathKind::Intermittent(_) => "intermittent",
+            PathKind::Cluster(_) => "cluster",
+            PathKind::Reflection(_) => "reflection",
+        }
+    }
+}
+
+/// Direction of a path in radians, azimuth counter-clockwise from the x axis, elevation
+/// positive towards +z.
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Direction {
+    #[pyo3(get)]
+    pub azimuth: f32,
+    #[pyo3(get)]
+    pub elevation: f32,
+}
+
+impl Direction {
+    pub fn new(azimuth: f32, elevation: f32) -> Self {
+        Direction { azimuth, elevation }
+    }
+}
+
+#[pymethods]
+impl Direction {
+    fn __repr__(&self) -> String {
+        format!("Direction(azimuth={}, elevation={})", self.azimuth, self.elevation)
+    }
+}
+
+/// A single discrete propagation path between ground station and UAV.
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropagationPath {
+    /// linear amplitude loss, i.e. 1 / |gain|; 0 counts as no loss, see `gain`
+    #[pyo3(get)]
+    pub loss: f32,
+    /// phase shift in radians on top of the carrier phase rotation caused by the delay
+    #[pyo3(get)]
+    pub phase: f32,
+    /// excess delay relative to the line-of-sight path in seconds
+    #[pyo3(get)]
+    pub delay: f32,
+    /// Doppler shift in Hz caused by the motion of the UAV
+    #[pyo3(get)]
+    pub doppler: f32,
+    pub kind: PathKind,
+    /// angle of departure at the ground station in the local frame, if the model knows the geometry
+    #[pyo3(get)]
+    pub departure: Option<Direction>,
+    /// angle of arrival at the UAV in its body frame (pointing towards where the wave comes
+    /// from), if the model knows the geometry
+    #[pyo3(get)]
+    pub arrival: Option<Direction>,
+}
+
+impl PropagationPath {
+    pub fn new(kind: PathKind, loss: f32, delay: f32, phase: f32) -> Self {
+        PropagationPath {
+            loss,
+            phase,
+            delay,
+            doppler: 0.0,
+            kind,
+            departure: None,
+            arrival: None,
+        }
+    }
+
+    pub fn with_geometry(mut self, geometry: PathGeometry) -> Self {
+        self.departure = Some(geometry.departure);
+        self.arrival = Some(geometry.arrival);
+        self.doppler = geometry.doppler;
+        self
+    }
+
+    /// complex amplitude gain of the path (without the delay induced carrier phase); a loss of
+    /// 0, e.g. free space at zero distance, counts as no loss
+    pub fn gain(&self) -> Complex32 {
+        let amplitude = if self.loss == 0.0 { 1.0 } else { 1.0 / self.loss };
+        Complex32::from_polar(amplitude, self.phase)
+    }
+}
+
+#[pymethods]
+impl PropagationPath {
+    #[getter(kind)]
+    fn py_kind(&self) -> &'static str {
+        self.kind.label()
+    }
+
+    /// index of the intermittent ray, cluster or reflection, None for all other kinds of paths
+    #[getter]
+    fn ray_index(&self) -> Option<u8> {
+        match self.kind {
+            PathKind::Intermittent(k) | PathKind::Cluster(k) | PathKind::Reflection(k) => Some(k),
+            _ => None,
+        }
+    }
+
+    #[getter(gain)]
+    fn py_gain(&self) -> Complex32 {
+        self.gain()
+    }
+
+    fn __repr__(&self) -> String {
+        let kind = match self.kind {
+            PathKind::Intermittent(k) => format!("intermittent_{}", k),
+            PathKind::Cluster(k) => format!("cluster_{}", k),
+            PathKind::Reflection(k) => format!("reflection_{}", k),
+            kind => kind.label().to_string(),
+        };
+        format!(
+            "PropagationPath(kind={}, loss={}, delay={}, phase={}, doppler={})",
+            kind, self.loss, self.delay, self.phase, self.doppler
+        )
+    }
+}
@@ -0,0 +1,288 @@
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+use serde::Deserialize;
+use std::io;
+use std::path::Path;
+
+use crate::environment::deserialize_environment;
+use crate::{Antenna, AntennaPattern, Buildings, Environment, Fading, GeodeticPosition, GroundMaterial, Shadowing, Terrain, TwoSegmentParameters, SPEED_OF_LIGHT};
+
+#[pyclass]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Polarization {
+    Vertical,
+    Horizontal,
+    /// both antennas circularly polarized with the same handedness
+    Circular,
+}
+
+/// Everything the channel models need to know about the emulated setup that is not part of
+/// the UAV state: where the ground station is, which carrier is used and what the ground
+/// looks like. The defaults reproduce the original hard-coded setup (2.45 GHz, mast at 1.5 m,
+/// vertically polarized antennas over average ground).
+#[pyclass]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    /// ground station position in metres
+    #[pyo3(get, set)]
+    pub station_x: f32,
+    #[pyo3(get, set)]
+    pub station_y: f32,
+    #[pyo3(get, set)]
+    pub station_z: f32,
+    /// carrier frequency in Hz
+    #[pyo3(get, set)]
+    pub frequency: f32,
+    /// largest antenna dimension in metres, determines the far-field distance
+    #[pyo3(get, set)]
+    pub antenna_size: f32,
+    #[pyo3(get, set)]
+    pub polarization: Polarization,
+    /// pattern and mounting of the ground station antenna, relative to the local frame
+    #[pyo3(get, set)]
+    pub station_antenna: Antenna,
+    /// pattern and mounting of the UAV antenna, relative to the body frame
+    #[pyo3(get, set)]
+    pub uav_antenna: Antenna,
+    /// material of the ground near the reflection point; if given, its frequency-dependent
+    /// properties replace `ground_permittivity` and `ground_conductivity`
+    #[pyo3(get, set)]
+    pub ground_material: Option<GroundMaterial>,
+    /// relative permittivity of the ground near the reflection point
+    #[pyo3(get, set)]
+    pub ground_permittivity: f64,
+    /// conductivity of the ground near the reflection point in S/m
+    #[pyo3(get, set)]
+    pub ground_conductivity: f64,
+    /// standard deviation of the surface height near the reflection point in metres
+    #[pyo3(get, set)]
+    pub ground_roughness: f32,
+    /// width (in metres of horizontal distance) of the transition between flat-earth and
+    /// curved-earth grazing angle in the CE2R model
+    #[pyo3(get, set)]
+    pub smoothing_factor: f64,
+    /// seed of the random number generators of the stochastic models, random if not given
+    #[pyo3(get, set)]
+    pub seed: Option<u64>,
+    /// statistics of the intermittent rays, a preset name, a table file or inline tables
+    #[serde(deserialize_with = "deserialize_environment")]
+    #[pyo3(get, set)]
+    pub environment: Environment,
+    /// spatially correlated log-normal shadowing applied on top of every model
+    #[pyo3(get, set)]
+    pub shadowing: Shadowing,
+    /// small-scale fading of the paths between position updates, applied by the emulator
+    #[pyo3(get, set)]
+    pub fading: Fading,
+    /// constants of the two-segment log-distance model
+    #[pyo3(get, set)]
+    pub two_segment: TwoSegmentParameters,
+    /// geodetic position of the origin of the local frame, whose x axis points east and y axis
+    /// north; required to place the local frame on the terrain
+    #[pyo3(get, set)]
+    pub origin: Option<GeodeticPosition>,
+    /// digital elevation model used by the terrain-aware models; heights in the local frame
+    /// (station_z, UAV z) are then relative to the altitude of the origin, not to the ground
+    #[pyo3(get, set)]
+    pub terrain: Option<Terrain>,
+    /// building scene of the ray-tracing model
+    #[pyo3(get, set)]
+    pub buildings: Option<Buildings>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario {
+            station_x: 0.0,
+            station_y: 0.0,
+            station_z: 1.5,
+            frequency: 2.45e9,
+            antenna_size: 0.1,
+            // antennas mounted orthogonal to earths surface (e.g. straight downward from uav)
+            polarization: Polarization::Vertical,
+            station_antenna: Antenna::default(),
+            uav_antenna: Antenna::default(),
+            ground_material: None,
+            // ground reflective constants for average ground taken from ISBN 978-0-471-98857-1
+            ground_permittivity: 15.0,
+            ground_conductivity: 0.005,
+            // 0.1m standard deviation of earth surface near reflection point for urban/suburban setting, see 10.1109/TVT.2017.2659651
+            ground_roughness: 0.1,
+            smoothing_factor: 0.8,
+            seed: None,
+            environment: Environment::default(),
+            shadowing: Shadowing::default(),
+            fading: Fading::default(),
+            two_segment: TwoSegmentParameters::default(),
+            origin: None,
+            terrain: None,
+            buildings: None,
+        }
+    }
+}
+
+impl Scenario {
+    /// Reads a scenario from a TOML file. Keys that are not given keep their default value,
+    /// relative file names are resolved against the directory of the file.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Scenario> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        let mut table: toml::Table = content.parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let Some(directory) = path.parent() {
+            resolve_file_names(&mut table, directory);
+        }
+        table.try_into().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// wavelength of the carrier in metres
+    pub fn lambda(&self) -> f32 {
+        SPEED_OF_LIGHT / self.frequency
+    }
+
+    /// Fraunhofer distance of the antennas, below it the path models are not valid
+    pub fn far_field_distance(&self) -> f32 {
+        (2. * self.antenna_size * self.antenna_size) / self.lambda()
+    }
+
+    /// relative permittivity and conductivity (S/m) of the ground at the carrier frequency
+    pub fn ground_properties(&self) -> (f64, f64) {
+        match self.ground_material {
+            Some(material) => material.electrical_properties(self.frequency),
+            None => (self.ground_permittivity, self.ground_conductivity),
+        }
+    }
+
+    /// distance between the ground station and the given position in metres
+    pub fn distance(&self, x: f32, y: f32, z: f32) -> f32 {
+        ((self.station_x - x).powi(2) + (self.station_y - y).powi(2) + (self.station_z - z).powi(2)).sqrt()
+    }
+}
+
+/// Joins the relative file names in a scenario table (terrain tiles, building scene, antenna
+/// patterns and environment tables) to `directory`, so that they do not depend on the working
+/// directory. Built-in pattern and environment names are left as they are.
+pub fn resolve_file_names(scenario: &mut toml::Table, directory: &Path) {
+    let resolve = |value: &mut toml::Value| {
+        if let toml::Value::String(name) = value {
+            if Path::new(name.as_str()).is_relative() {
+                *name = directory.join(name.as_str()).to_string_lossy().into_owned();
+            }
+        }
+    };
+    if let Some(tiles) = scenario.get_mut("terrain").and_then(|terrain| terrain.get_mut("tiles")).and_then(toml::Value::as_array_mut) {
+        tiles.iter_mut().for_each(resolve);
+    }
+    if let Some(file) = scenario.get_mut("buildings").and_then(|buildings| buildings.get_mut("file")) {
+        resolve(file);
+    }
+    for antenna in ["station_antenna", "uav_antenna"] {
+        if let Some(pattern) = scenario.get_mut(antenna).and_then(|antenna| antenna.get_mut("pattern")) {
+            if pattern.as_str().is_some_and(|name| !AntennaPattern::BUILT_IN.contains(&name)) {
+                resolve(pattern);
+            }
+        }
+    }
+    if let Some(environment) = scenario.get_mut("environment") {
+        if environment.as_str().is_some_and(|name| Environment::preset(name).is_none()) {
+            resolve(environment);
+        }
+    }
+}
+
+#[pymethods]
+impl Scenario {
+    #[new]
+    #[pyo3(signature = (
+        station_x = 0.0,
+        station_y = 0.0,
+        station_z = 1.5,
+        frequency = 2.45e9,
+        antenna_size = 0.1,
+        polarization = Polarization::Vertical,
+        station_antenna = None,
+        uav_antenna = None,
+        ground_material = None,
+        ground_permittivity = 15.0,
+        ground_conductivity = 0.005,
+        ground_roughness = 0.1,
+        smoothing_factor = 0.8,
+        seed = None,
+        environment = None,
+        shadowing = None,
+        fading = None,
+        two_segment = None,
+        origin = None,
+        terrain = None,
+        buildings = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn py_new(
+        station_x: f32,
+        station_y: f32,
+        station_z: f32,
+        frequency: f32,
+        antenna_size: f32,
+        polarization: Polarization,
+        station_antenna: Option<Antenna>,
+        uav_antenna: Option<Antenna>,
+        ground_material: Option<GroundMaterial>,
+        ground_permittivity: f64,
+        ground_conductivity: f64,
+        ground_roughness: f32,
+        smoothing_factor: f64,
+        seed: Option<u64>,
+        environment: Option<Environment>,
+        shadowing: Option<Shadowing>,
+        fading: Option<Fading>,
+        two_segment: Option<TwoSegmentParameters>,
+        origin: Option<GeodeticPosition>,
+        terrain: Option<Terrain>,
+        buildings: Option<Buildings>,
+    ) -> Self {
+        Scenario {
+            station_x,
+            station_y,
+            station_z,
+            frequency,
+            antenna_size,
+            polarization,
+            station_antenna: station_antenna.unwrap_or_default(),
+            uav_antenna: uav_antenna.unwrap_or_default(),
+            ground_material,
+            ground_permittivity,
+            ground_conductivity,
+            ground_roughness,
+            smoothing_factor,
+            seed,
+            environment: environment.unwrap_or_default(),
+            shadowing: shadowing.unwrap_or_default(),
+            fading: fading.unwrap_or_default(),
+            two_segment: two_segment.unwrap_or_default(),
+            origin,
+            terrain,
+            buildings,
+        }
+    }
+
+    #[staticmethod]
+    #[pyo3(name = "from_file")]
+    fn py_from_file(path: &str) -> PyResult<Scenario> {
+        Scenario::from_file(path).map_err(|e| PyIOError::new_err(format!("{}: {}", path, e)))
+    }
+
+    #[getter(lambda_)]
+    fn py_lambda(&self) -> f32 {
+        self.lambda()
+    }
+
+    #[getter(far_field_distance)]
+    fn py_far_field_distance(&self) -> f32 {
+        self.far_field_distance()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
@@ -0,0 +1,198 @@
+use pyo3::prelude::*;
+use rand::Rng;
+use rand_distr::{Distribution, Uniform};
+use serde::Deserialize;
+use std::f32::consts::PI;
+
+use crate::models::{resolve_seed, seeded_rng, ChannelModel, UnknownParameter};
+use crate::{PropagationPath, Scenario, State};
+
+/// number of sinusoids summed up per field, enough to make the field close to Gaussian and to
+/// follow the correlation function up to a few decorrelation distances
+const SINUSOIDS: usize = 500;
+/// random stream of the shadowing fields, keeps them independent of the other stochastic
+/// parts of a model that share the same seed
+const SHADOWING_STREAM: u64 = 1;
+
+/// Zero-mean, unit-variance Gaussian random field over the horizontal plane with exponential
+/// autocorrelation `exp(-r / decorrelation_distance)` (Gudmundson), synthesized as a sum of
+/// sinusoids with randomly drawn wave vectors. The field is a deterministic function of the
+/// position, so revisiting a location gives the same value.
+#[derive(Clone, Debug)]
+pub(crate) struct CorrelatedField {
+    /// (k_x, k_y, phase) per sinusoid
+    components: Vec<(f32, f32, f32)>,
+}
+
+impl CorrelatedField {
+    pub(crate) fn exponential<R: Rng + ?Sized>(decorrelation_distance: f32, rng: &mut R) -> Self {
+        let uniform = Uniform::new(0.0_f32, 1.0);
+        let components = (0..SINUSOIDS)
+            .map(|_| {
+                // the isotropic 2D spectrum of the exponential correlation is proportional to
+                // (1 + (k d)^2)^(-3/2); the radial wave number is drawn by inverting its CDF
+                let u: f32 = uniform.sample(rng);
+                let k = ((1.0 / (1.0 - u).powi(2) - 1.0).sqrt() / decorrelation_distance).min(1.0e6);
+                let direction = 2.0 * PI * uniform.sample(rng);
+                let phase = 2.0 * PI * uniform.sample(rng);
+                (k * direction.cos(), k * direction.sin(), phase)
+            })
+            .collect();
+        CorrelatedField { components }
+    }
+
+    pub(crate) fn value(&self, x: f32, y: f32) -> f32 {
+        let sum: f32 = self.components.iter().map(|(k_x, k_y, phase)| (k_x * x + k_y * y + phase).cos()).sum();
+        sum * (2.0 / self.components.len() as f32).sqrt()
+    }
+}
+
+/// Log-normal shadowing on top of any model, a standard deviation of 0 disables it.
+#[pyclass]
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Shadowing {
+    /// standard deviation in dB
+    #[pyo3(get, set)]
+    pub std_dev: f32,
+    /// distance in metres at which the correlation has dropped to 1/e
+    #[pyo3(get, set)]
+    pub decorrelation_distance: f32,
+}
+
+impl Default for Shadowing {
+    fn default() -> Self {
+        Shadowing { std_dev: 0.0, decorrelation_distance: 50.0 }
+    }
+}
+
+impl Shadowing {
+    pub fn is_enabled(&self) -> bool {
+        self.std_dev > 0.0
+    }
+}
+
+#[pymethods]
+impl Shadowing {
+    #[new]
+    #[pyo3(signature = (std_dev = 0.0, decorrelation_distance = 50.0))]
+    fn py_new(std_dev: f32, decorrelation_distance: f32) -> Self {
+        Shadowing { std_dev, decorrelation_distance }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// Attenuates all paths of a model by the shadowing at the UAV position.
+pub struct WithShadowing {
+    model: Box<dyn ChannelModel>,
+    shadowing: Shadowing,
+    seed: u64,
+    field: CorrelatedField,
+}
+
+impl WithShadowing {
+    /// uses the seed of the scenario, or a random one if the scenario does not fix it
+    pub fn new(model: Box<dyn ChannelModel>, scenario: &Scenario) -> Self {
+        let seed = resolve_seed(scenario);
+        let shadowing = scenario.shadowing.clone();
+        let field = Self::field(&shadowing, seed);
+        WithShadowing { model, shadowing, seed, field }
+    }
+
+    fn field(shadowing: &Shadowing, seed: u64) -> CorrelatedField {
+        let mut rng = seeded_rng(seed);
+        rng.set_stream(SHADOWING_STREAM);
+        CorrelatedField::exponential(shadowing.decorrelation_distance, &mut rng)
+    }
+
+    /// shadowing in dB at the given position
+    pub fn shadowing_db(&self, x: f32, y: f32) -> f32 {
+        self.shadowing.std_dev * self.field.value(x, y)
+    }
+}
+
+impl ChannelModel for WithShadowing {
+    fn name(&self) -> &'static str {
+        self.model.name()
+    }
+
+    fn parameters(&self) -> Vec<(&'static str, f64)> {
+        let mut parameters = self.model.parameters();
+        parameters.push(("shadowing_std_dev", self.shadowing.std_dev as f64));
+        parameters.push(("shadowing_decorrelation_distance", self.shadowing.decorrelation_distance as f64));
+        parameters
+    }
+
+    fn set_parameter(&mut self, name: &str, value: f64) -> Result<(), UnknownParameter> {
+        match name {
+            "shadowing_std_dev" => self.shadowing.std_dev = value as f32,
+            "shadowing_decorrelation_distance" => {
+                self.shadowing.decorrelation_distance = value as f32;
+                self.field = Self::field(&self.shadowing, self.seed);
+            }
+            _ => return self.model.set_parameter(name, value),
+        }
+        Ok(())
+    }
+
+    fn paths(&mut self, state: &State) -> Vec<PropagationPath> {
+        let additional_loss = 10.0_f32.powf(self.shadowing_db(state.x, state.y) / 20.0);
+        let mut paths = self.model.paths(state);
+        for path in &mut paths {
+            path.loss *= additional_loss;
+        }
+        paths
+    }
+
+    fn reset(&mut self) {
+        self.model.reset()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FreeSpace;
+
+    fn shadowing(seed: u64) -> WithShadowing {
+        let scenario = Scenario { shadowing: Shadowing { std_dev: 8.0, decorrelation_distance: 50.0 }, seed: Some(seed), ..Scenario::default() };
+        WithShadowing::new(Box::new(FreeSpace::new(&scenario)), &scenario)
+    }
+
+    /// grid of 120 x 120 points 60 m apart, i.e. over many decorrelation distances
+    fn grid() -> impl Iterator<Item = (f32, f32)> {
+        (0..120).flat_map(|i| (0..120).map(move |j| (60.0 * i as f32, 60.0 * j as f32)))
+    }
+
+    #[test]
+    fn field_has_the_configured_standard_deviation() {
+        let shadowing = shadowing(11);
+        let values: Vec<f32> = grid().map(|(x, y)| shadowing.shadowing_db(x, y)).collect();
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        let std_dev = (values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len() as f32).sqrt();
+        assert!(mean.abs() < 0.5, "mean {}", mean);
+        assert!((std_dev - 8.0).abs() < 0.8, "standard deviation {}", std_dev);
+    }
+
+    #[test]
+    fn field_decorrelates_at_the_decorrelation_distance() {
+        let shadowing = shadowing(11);
+        let correlation = |lag: f32| {
+            let products: f32 = grid().map(|(x, y)| shadowing.shadowing_db(x, y) * shadowing.shadowing_db(x + lag, y)).sum();
+            products / grid().count() as f32 / 64.0
+        };
+        assert!((correlation(0.0) - 1.0).abs() < 0.2);
+        assert!((correlation(50.0) - (-1.0_f32).exp()).abs() < 0.1, "correlation at 50 m {}", correlation(50.0));
+        assert!(correlation(250.0).abs() < 0.1, "correlation at 250 m {}", correlation(250.0));
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_field() {
+        let (first, second, other) = (shadowing(5), shadowing(5), shadowing(6));
+        assert_eq!(first.shadowing_db(123.0, -45.0), second.shadowing_db(123.0, -45.0));
+        assert_ne!(first.shadowing_db(123.0, -45.0), other.shadowing_db(123.0, -45.0));
+    }
+}
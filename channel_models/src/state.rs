@@ -0,0 +1,29 @@
+/// Position (metres, local frame), attitude (radians) and velocity (m/s, local frame) of the
+/// UAV, i.e. the content of a single position update received by chanem.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct State {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub roll: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    /// zero if unknown, the paths then carry no Doppler shift
+    pub velocity: [f32; 3],
+}
+
+impl State {
+    pub fn new(x: f32, y: f32, z: f32, roll: f32, pitch: f32, yaw: f32) -> Self {
+        State { x, y, z, roll, pitch, yaw, velocity: [0.0; 3] }
+    }
+
+    pub fn with_velocity(mut self, velocity: [f32; 3]) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    /// state at the given position with level attitude
+    pub fn at(x: f32, y: f32, z: f32) -> Self {
+        State { x, y, z, ..Default::default() }
+    }
+}
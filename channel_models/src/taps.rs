@@ -0,0 +1,204 @@
+use num::complex::Complex32;
+use serde::Deserialize;
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::PropagationPath;
+
+/// number of taps of the FIR filter on the FPGA
+pub const MAX_TAPS: usize = 41;
+/// tap value of a path without loss before scaling
+pub const TAP_VALUE_NO_LOSS: Complex32 = Complex32::new(32767.0, 0.0);
+/// default scaling of the taps, compensates the free-space loss of typical distances
+pub const MAGIC_SCALING_COEFF: f32 = 30000.0;
+pub const TAP_VALUE_MAX: i16 = 32760;
+pub const TAP_VALUE_MIN: i16 = -32760;
+
+/// Tap values as the FPGA expects them: all real parts followed by all imaginary parts, scaled
+/// by `magic_scaling_coeff` and clamped to the range of the filter.
+pub fn quantize_taps(taps: &[Complex32], magic_scaling_coeff: f32) -> Vec<i16> {
+    let quantize = |value: f32| (value as i16).clamp(TAP_VALUE_MIN, TAP_VALUE_MAX);
+    let scaled: Vec<Complex32> = taps.iter().map(|tap| tap * TAP_VALUE_NO_LOSS * magic_scaling_coeff).collect();
+    scaled.iter().map(|tap| quantize(tap.re)).chain(scaled.iter().map(|tap| quantize(tap.im))).collect()
+}
+
+/// How a path with a delay between two sampling instants is distributed over the taps
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TapKernel {
+    /// whole path on the tap its delay falls into, paths closer than a sample merge
+    #[default]
+    Floor,
+    /// path split between the two neighbouring taps
+    Linear,
+    /// Blackman-windowed sinc, i.e. a band-limited fractional delay; delays all paths by
+    /// `sinc_half_width` samples
+    Sinc,
+}
+
+impl TapKernel {
+    pub const NAMES: [&'static str; 3] = ["floor", "linear", "sinc"];
+
+    /// constant delay in taps added to all paths so that the kernel stays causal
+    pub fn latency(&self, sinc_half_width: usize) -> usize {
+        match self {
+            TapKernel::Sinc => sinc_half_width,
+            TapKernel::Floor | TapKernel::Linear => 0,
+        }
+    }
+
+    /// (tap index, weight) pairs of a path delayed by `delay` taps
+    pub fn weights(&self, delay: f32, sinc_half_width: usize) -> Vec<(usize, f32)> {
+        let integer = delay.floor();
+        let fraction = delay - integer;
+        let integer = integer as i64;
+        let weights: Vec<(i64, f32)> = match self {
+            TapKernel::Floor => vec![(integer, 1.)],
+            TapKernel::Linear => vec![(integer, 1. - fraction), (integer + 1, fraction)],
+            // the sinc vanishes on all other taps, sin(PI * n) only rounds to almost zero
+            TapKernel::Sinc if fraction == 0. => vec![(integer, 1.)],
+            TapKernel::Sinc => {
+                let half_width = sinc_half_width.max(1) as i64;
+                (integer - half_width + 1..=integer + half_width)
+                    .map(|n| {
+                        let x = n as f32 - delay;
+                        let sinc = if x == 0. { 1. } else { (PI * x).sin() / (PI * x) };
+                        let t = x / half_width as f32;
+                        let window = if t.abs() < 1. { 0.42 + 0.5 * (PI * t).cos() + 0.08 * (2. * PI * t).cos() } else { 0. };
+                        (n, sinc * window)
+                    })
+                    .collect()
+            }
+        };
+        weights
+            .into_iter()
+            .filter(|&(n, weight)| n >= 0 && weight != 0.)
+            .map(|(n, weight)| (n as usize, weight))
+            .collect()
+    }
+}
+
+impl FromStr for TapKernel {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "floor" => Ok(TapKernel::Floor),
+            "linear" => Ok(TapKernel::Linear),
+            "sinc" => Ok(TapKernel::Sinc),
+            _ => Err(format!("unknown tap kernel '{}', available: {:?}", name, TapKernel::NAMES)),
+        }
+    }
+}
+
+impl fmt::Display for TapKernel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TapKernel::Floor => "floor",
+            TapKernel::Linear => "linear",
+            TapKernel::Sinc => "sinc",
+        };
+        f.write_str(name)
+    }
+}
+
+/// complex gain a path contributes to the taps: `PropagationPath::gain` rotated by the
+/// carrier phase of its delay at `frequency`
+pub(crate) fn tap_gain(path: &PropagationPath, frequency: f32) -> Complex32 {
+    path.gain() * Complex32::from_polar(1., 2. * PI * path.delay * frequency)
+}
+
+/// The FIR filter the paths are mapped to: `num_taps` taps spaced one sample apart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TapGrid {
+    /// samples per second
+    pub sample_rate: f32,
+    pub num_taps: usize,
+    pub kernel: TapKernel,
+    /// half width in taps of the windowed-sinc kernel, all paths are delayed by as many
+    /// samples to keep it causal
+    pub sinc_half_width: usize,
+}
+
+impl TapGrid {
+    pub fn new(sample_rate: f32, num_taps: usize) -> Self {
+        TapGrid { sample_rate, num_taps, kernel: TapKernel::default(), sinc_half_width: 4 }
+    }
+
+    pub fn with_kernel(mut self, kernel: TapKernel, sinc_half_width: usize) -> Self {
+        self.kernel = kernel;
+        self.sinc_half_width = sinc_half_width;
+        self
+    }
+
+    /// Complex tap gains of the given paths at the carrier `frequency`, each path's `tap_gain`
+    /// spread over the taps by the kernel. Paths delayed beyond the last tap are dropped.
+    pub fn complex_taps(&self, paths: &[PropagationPath], frequency: f32) -> Vec<Complex32> {
+        let latency = self.kernel.latency(self.sinc_half_width) as f32;
+        let mut taps = vec![Complex32::new(0., 0.); self.num_taps];
+        for path in paths {
+            let delay = latency + path.delay * self.sample_rate;
+            if delay >= self.num_taps as f32 {
+                continue;
+            }
+            let gain = tap_gain(path, frequency);
+            for (tap_index, weight) in self.kernel.weights(delay, self.sinc_half_width) {
+                if tap_index < self.num_taps {
+                    taps[tap_index] += gain * weight;
+                }
+            }
+        }
+        taps
+    }
+
+    /// `complex_taps` quantized by `quantize_taps`, i.e. exactly what the emulator sends
+    pub fn quantized_taps(&self, paths: &[PropagationPath], frequency: f32, magic_scaling_coeff: f32) -> Vec<i16> {
+        quantize_taps(&self.complex_taps(paths, frequency), magic_scaling_coeff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PathKind;
+
+    #[test]
+    fn integer_delay_gives_a_single_tap() {
+        for kernel in [TapKernel::Floor, TapKernel::Linear, TapKernel::Sinc] {
+            assert_eq!(kernel.weights(7., 4), vec![(7, 1.)], "{}", kernel);
+        }
+    }
+
+    #[test]
+    fn fractional_delay_weights_sum_to_one() {
+        for kernel in [TapKernel::Floor, TapKernel::Linear, TapKernel::Sinc] {
+            for delay in [7.1, 7.25, 7.5, 7.9] {
+                let sum: f32 = kernel.weights(delay, 4).iter().map(|(_, weight)| weight).sum();
+                assert!((sum - 1.).abs() < 0.02, "{} at {}: {}", kernel, delay, sum);
+            }
+        }
+    }
+
+    #[test]
+    fn sinc_kernel_delays_by_its_half_width() {
+        let path = PropagationPath::new(PathKind::LineOfSight, 1., 3. / 200e6, 0.);
+        let grid = TapGrid::new(200e6, MAX_TAPS).with_kernel(TapKernel::Sinc, 4);
+        let taps = grid.complex_taps(&[path], 0.);
+        let nonzero: Vec<usize> = (0..MAX_TAPS).filter(|&i| taps[i].norm() > 0.).collect();
+        assert_eq!(nonzero, vec![7]);
+    }
+
+    #[test]
+    fn zero_loss_counts_as_no_loss() {
+        let path = PropagationPath::new(PathKind::LineOfSight, 0., 0., 0.5);
+        let lossless = PropagationPath { loss: 1., ..path.clone() };
+        assert_eq!(path.gain(), Complex32::from_polar(1., 0.5));
+        assert_eq!(tap_gain(&path, 2.45e9), tap_gain(&lossless, 2.45e9));
+        // unscaled, a path without loss is a full-scale tap with the phase of the path
+        let grid = TapGrid::new(200e6, MAX_TAPS);
+        let taps = grid.quantized_taps(&[path], 2.45e9, 1.);
+        let expected = TAP_VALUE_NO_LOSS * Complex32::from_polar(1., 0.5);
+        assert_eq!((taps[0], taps[MAX_TAPS]), (expected.re as i16, expected.im as i16));
+    }
+}
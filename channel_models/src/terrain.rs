@@ -0,0 +1,595 @@
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+use serde::Deserialize;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+
+use crate::{GeodeticPosition, Scenario, State, KA};
+
+const GT_MODEL_TYPE_GEO_KEY: u16 = 1024;
+const GT_RASTER_TYPE_GEO_KEY: u16 = 1025;
+const MODEL_TYPE_PROJECTED: u16 = 1;
+const RASTER_PIXEL_IS_POINT: u16 = 2;
+/// marks missing samples in SRTM tiles
+const SRTM_VOID: i16 = -32768;
+/// at most this many edges are considered by the Deygout construction, as in ITU-R P.526
+const DEYGOUT_DEPTH: usize = 1;
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Regular grid of elevations in metres, NaN where the tile has no data.
+struct Tile {
+    /// latitude of the sample centres in the first row, in degrees
+    north: f64,
+    /// longitude of the sample centres in the first column, in degrees
+    west: f64,
+    latitude_step: f64,
+    longitude_step: f64,
+    rows: usize,
+    columns: usize,
+    heights: Vec<f32>,
+}
+
+/// the same grid with the same heights, missing samples included
+impl PartialEq for Tile {
+    fn eq(&self, other: &Tile) -> bool {
+        (self.north, self.west, self.latitude_step, self.longitude_step, self.rows, self.columns) == (other.north, other.west, other.latitude_step, other.longitude_step, other.rows, other.columns)
+            && self.heights.iter().map(|height| height.to_bits()).eq(other.heights.iter().map(|height| height.to_bits()))
+    }
+}
+
+impl Tile {
+    /// SRTM tile, the file name (e.g. `N50E008.hgt`) gives its south-west corner
+    fn from_hgt(path: &Path) -> io::Result<Tile> {
+        let name = path.file_stem().and_then(|name| name.to_str()).unwrap_or("").to_ascii_uppercase();
+        let corner = |hemisphere: &str, positive: char, negative: char| -> Option<f64> {
+            let sign = match hemisphere.chars().next()? {
+                c if c == positive => 1.0,
+                c if c == negative => -1.0,
+                _ => return None,
+            };
+            hemisphere[1..].parse::<f64>().ok().map(|value| sign * value)
+        };
+        let (south, west) = match (name.get(0..3), name.get(3..7)) {
+            (Some(latitude), Some(longitude)) => (corner(latitude, 'N', 'S'), corner(longitude, 'E', 'W')),
+            _ => (None, None),
+        };
+        let (south, west) = south.zip(west).ok_or_else(|| {
+            invalid_data(format!("{}: SRTM file names must look like N50E008.hgt", path.display()))
+        })?;
+        let bytes = std::fs::read(path)?;
+        let size = ((bytes.len() / 2) as f64).sqrt() as usize;
+        if size < 2 || size * size * 2 != bytes.len() {
+            return Err(invalid_data(format!("{}: not a square grid of 16 bit samples", path.display())));
+        }
+        let heights = bytes
+            .chunks_exact(2)
+            .map(|sample| match i16::from_be_bytes([sample[0], sample[1]]) {
+                SRTM_VOID => f32::NAN,
+                height => height as f32,
+            })
+            .collect();
+        let step = 1.0 / (size - 1) as f64;
+        Ok(Tile { north: south + 1.0, west, latitude_step: step, longitude_step: step, rows: size, columns: size, heights })
+    }
+
+    /// single-band GeoTIFF in geographic coordinates (e.g. EPSG:4326)
+    fn from_geotiff(path: &Path) -> io::Result<Tile> {
+        let tiff_error = |e: tiff::TiffError| invalid_data(format!("{}: {}", path.display(), e));
+        let mut decoder = Decoder::new(BufReader::new(File::open(path)?)).map_err(tiff_error)?;
+        let (columns, rows) = decoder.dimensions().map_err(tiff_error)?;
+        let scale = decoder.get_tag_f64_vec(Tag::ModelPixelScaleTag).map_err(tiff_error)?;
+        let tiepoint = decoder.get_tag_f64_vec(Tag::ModelTiepointTag).map_err(tiff_error)?;
+        if scale.len() < 2 || tiepoint.len() < 6 {
+            return Err(invalid_data(format!("{}: incomplete georeferencing", path.display())));
+        }
+        let geo_keys = decoder.get_tag_u16_vec(Tag::GeoKeyDirectoryTag).unwrap_or_default();
+        let geo_key = |id: u16| geo_keys.get(4..).unwrap_or(&[]).chunks_exact(4).find(|key| key[0] == id && key[1] == 0).map(|key| key[3]);
+        if geo_key(GT_MODEL_TYPE_GEO_KEY) == Some(MODEL_TYPE_PROJECTED) {
+            return Err(invalid_data(format!(
+                "{}: projected GeoTIFFs are not supported, reproject to geographic coordinates",
+                path.display()
+            )));
+        }
+        // tiepoints refer to pixel corners unless the raster declares point samples
+        let offset = if geo_key(GT_RASTER_TYPE_GEO_KEY) == Some(RASTER_PIXEL_IS_POINT) { 0.0 } else { 0.5 };
+        let no_data = decoder
+            .get_tag_ascii_string(Tag::GdalNodata)
+            .ok()
+            .and_then(|value| value.trim_matches(char::from(0)).trim().parse::<f64>().ok());
+        let samples: Vec<f64> = match decoder.read_image().map_err(tiff_error)? {
+            DecodingResult::U8(values) => values.into_iter().map(f64::from).collect(),
+            DecodingResult::U16(values) => values.into_iter().map(f64::from).collect(),
+            DecodingResult::U32(values) => values.into_iter().map(f64::from).collect(),
+            DecodingResult::U64(values) => values.into_iter().map(|value| value as f64).collect(),
+            DecodingResult::I8(values) => values.into_iter().map(f64::from).collect(),
+            DecodingResult::I16(values) => values.into_iter().map(f64::from).collect(),
+            DecodingResult::I32(values) => values.into_iter().map(f64::from).collect(),
+            DecodingResult::I64(values) => values.into_iter().map(|value| value as f64).collect(),
+            DecodingResult::F32(values) => values.into_iter().map(f64::from).collect(),
+            DecodingResult::F64(values) => values,
+        };
+        let (rows, columns) = (rows as usize, columns as usize);
+        if samples.len() != rows * columns {
+            return Err(invalid_data(format!("{}: only single-band elevation rasters are supported", path.display())));
+        }
+        let heights = samples
+            .into_iter()
+            .map(|sample| if Some(sample) == no_data { f32::NAN } else { sample as f32 })
+            .collect();
+        Ok(Tile {
+            north: tiepoint[4] - (offset - tiepoint[1]) * scale[1],
+            west: tiepoint[3] + (offset - tiepoint[0]) * scale[0],
+            latitude_step: scale[1],
+            longitude_step: scale[0],
+            rows,
+            columns,
+            heights,
+        })
+    }
+
+    /// bilinearly interpolated elevation, None outside the tile or where a missing sample
+    /// contributes to it
+    fn elevation(&self, latitude: f64, longitude: f64) -> Option<f32> {
+        let row = (self.north - latitude) / self.latitude_step;
+        let column = (longitude - self.west) / self.longitude_step;
+        if !(0.0..=(self.rows - 1) as f64).contains(&row) || !(0.0..=(self.columns - 1) as f64).contains(&column) {
+            return None;
+        }
+        let (row_0, column_0) = (row.floor() as usize, column.floor() as usize);
+        let (row_1, column_1) = ((row_0 + 1).min(self.rows - 1), (column_0 + 1).min(self.columns - 1));
+        let (t_row, t_column) = ((row - row_0 as f64) as f32, (column - column_0 as f64) as f32);
+        let corners = [
+            (row_0, column_0, (1.0 - t_row) * (1.0 - t_column)),
+            (row_0, column_1, (1.0 - t_row) * t_column),
+            (row_1, column_0, t_row * (1.0 - t_column)),
+            (row_1, column_1, t_row * t_column),
+        ];
+        // samples without weight are skipped, a missing one must not void exact grid points
+        let height: f32 = corners.iter().filter(|corner| corner.2 > 0.0).map(|&(row, column, weight)| weight * self.heights[row * self.columns + column]).sum();
+        (!height.is_nan()).then_some(height)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TerrainConfig {
+    tiles: Vec<String>,
+    #[serde(default = "default_profile_spacing")]
+    profile_spacing: f32,
+}
+
+fn default_profile_spacing() -> f32 {
+    10.0
+}
+
+/// Digital elevation model made of SRTM `.hgt` and GeoTIFF (`.tif`, `.tiff`) tiles. Each
+/// entry of `tiles` is a tile file or a directory whose tiles are all loaded. Where tiles
+/// overlap, the first one listed wins.
+#[pyclass]
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(try_from = "TerrainConfig")]
+pub struct Terrain {
+    #[pyo3(get)]
+    pub tiles: Vec<String>,
+    /// distance in metres between the samples of the terrain profile between station and UAV
+    #[pyo3(get, set)]
+    pub profile_spacing: f32,
+    elevation_model: Arc<Vec<Tile>>,
+}
+
+impl TryFrom<TerrainConfig> for Terrain {
+    type Error = io::Error;
+
+    fn try_from(config: TerrainConfig) -> io::Result<Terrain> {
+        Terrain::load(&config.tiles, config.profile_spacing)
+    }
+}
+
+impl fmt::Debug for Terrain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Terrain {{ tiles: {:?}, profile_spacing: {} }}", self.tiles, self.profile_spacing)
+    }
+}
+
+fn load_tile(path: &Path) -> io::Result<Option<Tile>> {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("").to_ascii_lowercase();
+    match extension.as_str() {
+        "hgt" => Tile::from_hgt(path).map(Some),
+        "tif" | "tiff" => Tile::from_geotiff(path).map(Some),
+        _ => Ok(None),
+    }
+}
+
+impl Terrain {
+    pub fn load(tiles: &[String], profile_spacing: f32) -> io::Result<Terrain> {
+        if profile_spacing.is_nan() || profile_spacing <= 0.0 {
+            return Err(invalid_data("profile_spacing must be positive".to_string()));
+        }
+        let mut elevation_model = Vec::new();
+        for entry in tiles {
+            let path = Path::new(entry);
+            if path.is_dir() {
+                let mut files: Vec<_> = std::fs::read_dir(path)?.map(|file| file.map(|file| file.path())).collect::<io::Result<_>>()?;
+                files.sort();
+                for file in files {
+                    elevation_model.extend(load_tile(&file)?);
+                }
+            } else {
+                let tile = load_tile(path)?.ok_or_else(|| invalid_data(format!("{}: expected a .hgt, .tif or .tiff file", entry)))?;
+                elevation_model.push(tile);
+            }
+        }
+        Ok(Terrain { tiles: tiles.to_vec(), profile_spacing, elevation_model: Arc::new(elevation_model) })
+    }
+
+    /// elevation in metres at the given position, None where no tile has data
+    pub fn elevation(&self, latitude: f64, longitude: f64) -> Option<f32> {
+        self.elevation_model.iter().find_map(|tile| tile.elevation(latitude, longitude))
+    }
+
+    /// terrain height in the local frame whose origin is `origin`, 0 where no tile has data
+    pub fn height(&self, origin: &GeodeticPosition, x: f32, y: f32) -> f32 {
+        let position = origin.offset(x, y, 0.0);
+        self.local_height(origin, position.latitude, position.longitude)
+    }
+
+    /// Terrain heights in the local frame at `samples + 1` evenly spaced points from `from` to
+    /// `to` (x, y in the local frame). Only the end points are converted to geodetic
+    /// coordinates, the points in between are interpolated in latitude and longitude.
+    pub(crate) fn heights_along(&self, origin: &GeodeticPosition, from: (f32, f32), to: (f32, f32), samples: usize) -> Vec<f32> {
+        let (start, end) = (origin.offset(from.0, from.1, 0.0), origin.offset(to.0, to.1, 0.0));
+        (0..=samples)
+            .map(|i| {
+                let fraction = i as f64 / samples.max(1) as f64;
+                let latitude = start.latitude + fraction * (end.latitude - start.latitude);
+                let longitude = start.longitude + fraction * (end.longitude - start.longitude);
+                self.local_height(origin, latitude, longitude)
+            })
+            .collect()
+    }
+
+    fn local_height(&self, origin: &GeodeticPosition, latitude: f64, longitude: f64) -> f32 {
+        self.elevation(latitude, longitude).map_or(0.0, |elevation| (elevation as f64 - origin.altitude) as f32)
+    }
+}
+
+#[pymethods]
+impl Terrain {
+    #[new]
+    #[pyo3(signature = (tiles, profile_spacing = 10.0))]
+    fn py_new(tiles: Vec<String>, profile_spacing: f32) -> PyResult<Terrain> {
+        Terrain::load(&tiles, profile_spacing).map_err(|e| PyIOError::new_err(e.to_string()))
+    }
+
+    /// elevation in metres at the given position, None where no tile has data
+    #[pyo3(name = "elevation")]
+    fn py_elevation(&self, latitude: f64, longitude: f64) -> Option<f32> {
+        self.elevation(latitude, longitude)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// attenuation in dB of a single knife edge with Fresnel-Kirchhoff parameter `nu`, ITU-R
+/// P.526 equation (31)
+pub fn knife_edge_loss(nu: f32) -> f32 {
+    if nu > -0.78 {
+        6.9 + 20.0 * (((nu - 0.1).powi(2) + 1.0).sqrt() + nu - 0.1).log10()
+    } else {
+        0.0
+    }
+}
+
+/// height of `point` above the straight line from `a` to `b`, all as (distance, height)
+fn height_above_line(point: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    point.1 - (a.1 + (b.1 - a.1) * (point.0 - a.0) / (b.0 - a.0))
+}
+
+/// Deygout construction over the obstacles strictly between `a` and `b`: the edge with the
+/// largest Fresnel parameter, plus recursively the edges on both sides of it
+fn deygout(obstacles: &[(f32, f32)], a: (f32, f32), b: (f32, f32), wavelength: f32, depth: usize) -> f32 {
+    let principal = obstacles
+        .iter()
+        .enumerate()
+        .filter(|(_, point)| point.0 > a.0 && point.0 < b.0)
+        .map(|(index, &point)| {
+            let (d_1, d_2) = (point.0 - a.0, b.0 - point.0);
+            let nu = height_above_line(point, a, b) * (2.0 * (d_1 + d_2) / (wavelength * d_1 * d_2)).sqrt();
+            (index, nu)
+        })
+        .max_by(|x, y| x.1.total_cmp(&y.1));
+    match principal {
+        Some((index, nu)) if nu > -0.78 => {
+            let mut loss = knife_edge_loss(nu);
+            if depth > 0 {
+                let edge = obstacles[index];
+                loss += deygout(&obstacles[..index], a, edge, wavelength, depth - 1);
+                loss += deygout(&obstacles[index + 1..], edge, b, wavelength, depth - 1);
+            }
+            loss
+        }
+        _ => 0.0,
+    }
+}
+
+/// specular reflection on the terrain profile found by the image method
+pub(crate) struct TerrainReflection {
+    /// (horizontal distance from the station, height) of the reflection point
+    pub point: (f32, f32),
+    /// grazing angle in radians relative to the local terrain slope
+    pub grazing_angle: f32,
+    /// length of the reflected path in metres
+    pub length: f32,
+}
+
+/// Terrain in the vertical plane through station and UAV, as (horizontal distance from the
+/// station, height) in the local frame.
+pub(crate) struct Profile {
+    /// samples of the ground, including below the station and the UAV
+    ground: Vec<(f32, f32)>,
+    station: (f32, f32),
+    uav: (f32, f32),
+}
+
+impl Profile {
+    /// flat ground at height 0 unless the scenario has a terrain model and an origin
+    pub(crate) fn between(scenario: &Scenario, state: &State) -> Profile {
+        let horizontal_distance = scenario.distance(state.x, state.y, scenario.station_z);
+        let spacing = scenario.terrain.as_ref().map_or(default_profile_spacing(), |terrain| terrain.profile_spacing);
+        let samples = ((horizontal_distance / spacing).ceil() as usize).max(1);
+        let heights = match (&scenario.terrain, &scenario.origin) {
+            (Some(terrain), Some(origin)) => terrain.heights_along(origin, (scenario.station_x, scenario.station_y), (state.x, state.y), samples),
+            _ => vec![0.0; samples + 1],
+        };
+        let ground = heights
+            .into_iter()
+            .enumerate()
+            .map(|(i, height)| (i as f32 / samples as f32 * horizontal_distance, height))
+            .collect();
+        Profile { ground, station: (0.0, scenario.station_z), uav: (horizontal_distance, state.z) }
+    }
+
+    pub(crate) fn horizontal_distance(&self) -> f32 {
+        self.uav.0
+    }
+
+    /// diffraction loss in dB of the direct path over the terrain (with 4/3 earth bulge)
+    pub(crate) fn diffraction_loss(&self, wavelength: f32) -> f32 {
+        let distance = self.horizontal_distance();
+        if distance <= 0.0 {
+            return 0.0;
+        }
+        let obstacles: Vec<(f32, f32)> = self
+            .ground
+            .iter()
+            .map(|&(s, height)| (s, height + (s as f64 * (distance - s) as f64 / (2.0 * KA)) as f32))
+            .collect();
+        deygout(&obstacles, self.station, self.uav, wavelength, DEYGOUT_DEPTH)
+    }
+
+    /// whether the straight line between two points of the profile clears the ground
+    fn is_clear(&self, a: (f32, f32), b: (f32, f32)) -> bool {
+        let (a, b) = if a.0 <= b.0 { (a, b) } else { (b, a) };
+        // the samples are sorted by distance, only those between the two points are checked
+        let first = self.ground.partition_point(|point| point.0 <= a.0 + 1e-3);
+        let last = self.ground.partition_point(|point| point.0 < b.0 - 1e-3);
+        self.ground[first..last.max(first)].iter().all(|&point| b.0 - a.0 <= 0.0 || height_above_line(point, a, b) <= 1e-3)
+    }
+
+    /// Shortest specular reflection on one of the straight facets between the ground samples
+    /// with both legs clear of the terrain: the station is mirrored at the facet and the line
+    /// from its image to the UAV intersected with the facet. The clearance of the legs is only
+    /// checked for the candidates in order of length until one is clear.
+    pub(crate) fn reflection(&self) -> Option<TerrainReflection> {
+        let sub = |a: (f32, f32), b: (f32, f32)| (a.0 - b.0, a.1 - b.1);
+        let dot = |a: (f32, f32), b: (f32, f32)| a.0 * b.0 + a.1 * b.1;
+        let length = |a: (f32, f32)| dot(a, a).sqrt();
+        let mut candidates = Vec::new();
+        for facet in self.ground.windows(2) {
+            let (start, end) = (facet[0], facet[1]);
+            let facet_length = length(sub(end, start));
+            if facet_length <= 0.0 {
+                continue;
+            }
+            let tangent = ((end.0 - start.0) / facet_length, (end.1 - start.1) / facet_length);
+            let normal = (-tangent.1, tangent.0);
+            let station_height = dot(sub(self.station, start), normal);
+            let uav_height = dot(sub(self.uav, start), normal);
+            if station_height <= 0.0 || uav_height <= 0.0 {
+                continue;
+            }
+            let image = (self.station.0 - 2.0 * station_height * normal.0, self.station.1 - 2.0 * station_height * normal.1);
+            let towards_uav = sub(self.uav, image);
+            let u = dot(sub(start, image), normal) / dot(towards_uav, normal);
+            let point = (image.0 + u * towards_uav.0, image.1 + u * towards_uav.1);
+            let along = dot(sub(point, start), tangent);
+            if !(0.0..=facet_length).contains(&along) {
+                continue;
+            }
+            let incident = length(sub(self.station, point));
+            candidates.push(TerrainReflection {
+                point,
+                grazing_angle: (station_height / incident.max(f32::EPSILON)).clamp(-1.0, 1.0).asin(),
+                length: incident + length(sub(self.uav, point)),
+            });
+        }
+        candidates.sort_by(|a, b| a.length.total_cmp(&b.length));
+        candidates.into_iter().find(|reflection| self.is_clear(self.station, reflection.point) && self.is_clear(reflection.point, self.uav))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tiff::encoder::{colortype, TiffEncoder};
+
+    /// empty directory for the files of one test
+    fn scratch_directory(test: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("channel_models-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    /// 3 x 3 SRTM tile with 0.5 degree spacing and one void sample
+    fn write_hgt(path: &Path) {
+        let heights: [i16; 9] = [100, 200, 300, 400, 500, 600, 700, SRTM_VOID, 900];
+        std::fs::write(path, heights.iter().flat_map(|height| height.to_be_bytes()).collect::<Vec<u8>>()).unwrap();
+    }
+
+    fn write_geotiff(path: &Path, geo_keys: &[u16]) {
+        let mut encoder = TiffEncoder::new(File::create(path).unwrap()).unwrap();
+        let mut image = encoder.new_image::<colortype::GrayI16>(3, 2).unwrap();
+        image.encoder().write_tag(Tag::ModelPixelScaleTag, &[0.5, 0.25, 0.0][..]).unwrap();
+        image.encoder().write_tag(Tag::ModelTiepointTag, &[0.0, 0.0, 0.0, 8.0, 51.0, 0.0][..]).unwrap();
+        image.encoder().write_tag(Tag::GeoKeyDirectoryTag, geo_keys).unwrap();
+        image.encoder().write_tag(Tag::GdalNodata, "-9999").unwrap();
+        image.write_data(&[10, 20, 30, 40, -9999, 60]).unwrap();
+    }
+
+    fn geo_keys(keys: &[(u16, u16)]) -> Vec<u16> {
+        let mut directory = vec![1, 1, 0, keys.len() as u16];
+        for &(id, value) in keys {
+            directory.extend([id, 0, 1, value]);
+        }
+        directory
+    }
+
+    #[test]
+    fn hgt_tiles_are_read() {
+        let directory = scratch_directory("hgt");
+        let path = directory.join("N50E008.hgt");
+        write_hgt(&path);
+        let tile = Tile::from_hgt(&path).unwrap();
+        assert_eq!((tile.north, tile.west, tile.latitude_step, tile.rows), (51.0, 8.0, 0.5, 3));
+        assert_eq!(tile.elevation(51.0, 8.0), Some(100.0));
+        assert_eq!(tile.elevation(50.5, 9.0), Some(600.0));
+        assert_eq!(tile.elevation(50.75, 8.25), Some(300.0));
+        // next to the void sample and outside of the tile
+        assert_eq!(tile.elevation(50.25, 8.5), None);
+        assert_eq!(tile.elevation(51.5, 8.5), None);
+
+        let misnamed = directory.join("tile.hgt");
+        std::fs::copy(&path, &misnamed).unwrap();
+        assert!(Tile::from_hgt(&misnamed).is_err());
+        let truncated = directory.join("S01W001.hgt");
+        std::fs::write(&truncated, [0u8; 10]).unwrap();
+        assert!(Tile::from_hgt(&truncated).is_err());
+    }
+
+    #[test]
+    fn geotiff_tiles_are_read() {
+        let directory = scratch_directory("geotiff");
+        let area = directory.join("area.tif");
+        write_geotiff(&area, &geo_keys(&[]));
+        let tile = Tile::from_geotiff(&area).unwrap();
+        // the tiepoint is the corner of the first pixel, the samples are at the pixel centres
+        assert_eq!((tile.north, tile.west, tile.rows, tile.columns), (50.875, 8.25, 2, 3));
+        assert_eq!(tile.elevation(50.875, 8.25), Some(10.0));
+        assert_eq!(tile.elevation(50.875, 8.5), Some(15.0));
+        assert_eq!(tile.elevation(50.625, 9.25), Some(60.0));
+        assert_eq!(tile.elevation(50.625, 8.75), None);
+
+        let point = directory.join("point.tif");
+        write_geotiff(&point, &geo_keys(&[(GT_RASTER_TYPE_GEO_KEY, RASTER_PIXEL_IS_POINT)]));
+        let tile = Tile::from_geotiff(&point).unwrap();
+        assert_eq!((tile.north, tile.west), (51.0, 8.0));
+
+        let projected = directory.join("projected.tif");
+        write_geotiff(&projected, &geo_keys(&[(GT_MODEL_TYPE_GEO_KEY, MODEL_TYPE_PROJECTED)]));
+        assert!(Tile::from_geotiff(&projected).is_err());
+    }
+
+    #[test]
+    fn tiles_are_relative_to_the_scenario_file() {
+        let directory = scratch_directory("scenario");
+        std::fs::create_dir(directory.join("dem")).unwrap();
+        write_hgt(&directory.join("dem").join("N50E008.hgt"));
+        let path = directory.join("scenario.toml");
+        std::fs::write(&path, "[origin]\nlatitude = 50.5\nlongitude = 8.5\naltitude = 0.0\n\n[terrain]\ntiles = [\"dem\"]\n").unwrap();
+        let terrain = Scenario::from_file(&path).unwrap().terrain.unwrap();
+        assert_eq!(terrain.tiles, vec![directory.join("dem").to_string_lossy().into_owned()]);
+        assert_eq!(terrain.elevation(50.5, 8.5), Some(500.0));
+    }
+
+    #[test]
+    fn knife_edge_loss_matches_p526() {
+        // J(0) is 6 dB, J(-0.78) about 0 dB and for large nu J approaches 13 + 20 log(nu)
+        assert!((knife_edge_loss(0.0) - 6.0).abs() < 0.05);
+        assert!(knife_edge_loss(-0.77) < 0.1);
+        assert_eq!(knife_edge_loss(-1.0), 0.0);
+        assert!((knife_edge_loss(1.0) - 13.9).abs() < 0.1);
+        for nu in [2.4_f32, 5.0, 10.0] {
+            assert!((knife_edge_loss(nu) - (13.0 + 20.0 * nu.log10())).abs() < 0.2, "nu {}", nu);
+        }
+    }
+
+    #[test]
+    fn deygout_takes_the_principal_edge_first() {
+        let (a, b, wavelength) = ((0.0, 0.0), (3000.0, 0.0), 0.1);
+        // nu is 3.46 for the first and 1.73 for the second edge, which lies exactly on the
+        // line from the first edge to the receiver
+        let obstacles = [(1000.0, 20.0), (2000.0, 10.0)];
+        let principal = knife_edge_loss(20.0 * 0.03_f32.sqrt());
+        assert!((deygout(&obstacles, a, b, wavelength, 0) - principal).abs() < 1e-4);
+        assert!((deygout(&obstacles, a, b, wavelength, 1) - (principal + knife_edge_loss(0.0))).abs() < 1e-4);
+        // edges well below the line do not diffract
+        assert_eq!(deygout(&[(1000.0, -20.0)], a, b, wavelength, 1), 0.0);
+    }
+
+    fn flat_profile(station: (f32, f32), uav: (f32, f32)) -> Profile {
+        let ground = (0..=100).map(|i| (i as f32 * uav.0 / 100.0, 0.0)).collect();
+        Profile { ground, station, uav }
+    }
+
+    #[test]
+    fn flat_ground_reflects_at_the_image_point() {
+        let reflection = flat_profile((0.0, 10.0), (1000.0, 90.0)).reflection().unwrap();
+        assert!((reflection.point.0 - 100.0).abs() < 1e-2 && reflection.point.1.abs() < 1e-4);
+        assert!((reflection.length - 1000.0_f32.hypot(100.0)).abs() < 1e-2);
+        assert!((reflection.grazing_angle - 0.1_f32.atan()).abs() < 1e-4);
+        assert_eq!(flat_profile((0.0, 10.0), (1000.0, 90.0)).diffraction_loss(0.1), 0.0);
+    }
+
+    #[test]
+    fn blocked_reflections_are_skipped() {
+        let mut profile = flat_profile((0.0, 10.0), (1000.0, 90.0));
+        // a ridge between the station and the flat-ground reflection point
+        profile.ground[5].1 = 9.5;
+        assert!(!profile.is_clear(profile.station, (100.0, 0.0)));
+        // the slopes of the ridge face away from the station or the UAV
+        assert!(profile.reflection().is_none());
+        profile.ground[5].1 = 4.0;
+        assert!((profile.reflection().unwrap().point.0 - 100.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn profile_follows_the_terrain() {
+        // heights rising by 1 m per row to the south and 0.5 m per column to the east
+        let (rows, columns) = (1001, 1001);
+        let heights = (0..rows * columns).map(|i| (i / columns) as f32 + 0.5 * (i % columns) as f32).collect();
+        let tile = Tile { north: 51.0, west: 8.0, latitude_step: 0.001, longitude_step: 0.001, rows, columns, heights };
+        let terrain = Terrain { tiles: Vec::new(), profile_spacing: 10.0, elevation_model: Arc::new(vec![tile]) };
+        let origin = GeodeticPosition { latitude: 50.5, longitude: 8.5, altitude: 100.0 };
+        let scenario = Scenario { origin: Some(origin), terrain: Some(terrain.clone()), ..Scenario::default() };
+        let profile = Profile::between(&scenario, &State::at(2000.0, -1500.0, 50.0));
+        assert_eq!(profile.ground.len(), 251);
+        for (i, &(distance, height)) in profile.ground.iter().enumerate() {
+            let fraction = i as f32 / 250.0;
+            assert!((distance - fraction * 2500.0).abs() < 1e-2);
+            let expected = terrain.height(&origin, fraction * 2000.0, fraction * -1500.0);
+            assert!((height - expected).abs() < 0.05, "sample {}: {} instead of {}", i, height, expected);
+        }
+    }
+}